@@ -1,7 +1,7 @@
 use super::get_selected_entries;
 use halo2_proofs::pairing::bn256::pairing;
 use halo2_proofs::pairing::bn256::Fr;
-use halo2_proofs::pairing::bn256::{G1Affine, G2Affine, G1, G2};
+use halo2_proofs::pairing::bn256::{G1Affine, G2Affine, Gt, G1, G2};
 use halo2_proofs::pairing::group::Group;
 use halo2_proofs::{
     arithmetic::FieldExt,
@@ -18,16 +18,40 @@ pub const BN256GT_SIZE: usize = 60;
 const BN256PAIR_SIZE: usize = BN256G1_SIZE + BN256G2_SIZE + BN256GT_SIZE;
 const BN256SUM_SIZE: usize = 1 + BN256FR_SIZE + 2 * BN256G1_SIZE;
 
-use crate::circuits::bn256::{Bn256ChipConfig, Bn256PairChip, Bn256SumChip};
+use crate::circuits::bn256::{Bn256ChipConfig, Bn256MultiPairChip, Bn256PairChip, Bn256SumChip};
 
 use crate::circuits::host::{HostOpConfig, HostOpSelector};
 use crate::utils::Limb;
 
 use crate::host::{ExternalHostCallEntry, ForeignInst};
 
+/// All the pairings of a circuit are laid out in one region and share the
+/// range check and integer multiplication tables which are finalized once.
+/// One pairing (miller loop, final exponentiation and `fq12_reduce`) together
+/// with its share of those tables takes more than 2^21 and less than 2^22 rows,
+/// so two pairings do not fit into k = 22 and `max_rounds` doubles with each
+/// increment of k. `stats::tests::test_bn256_pair_stats` checks both bounds and
+/// `test_bn256_pair_capacity` checks that the two rounds of k = 23 fit.
 const TOTAL_CONSTRUCTIONS_PAIR: usize = 1;
 const TOTAL_CONSTRUCTIONS_SUM: usize = 32;
 
+/// The pairs of one multi pairing, which is enough for a groth16 verification
+/// e(A, B) * e(-alpha, beta) * e(-L, gamma) * e(-C, delta) = 1
+pub const MAX_MULTI_PAIRS: usize = 4;
+const TOTAL_CONSTRUCTIONS_MULTI_PAIR: usize = 1;
+
+/// Bn254MultiPairNew(n), MAX_MULTI_PAIRS * (Bn254MultiPairG1, Bn254MultiPairG2),
+/// Bn254MultiPairResult where the pairs after the first n are padding
+const BN256MULTIPAIR_SIZE: usize =
+    1 + MAX_MULTI_PAIRS * (BN256G1_SIZE + BN256G2_SIZE) + BN256GT_SIZE;
+// arguments of a pair: G1 (3 * 2 + 1) and G2 (3 * 4 + 1)
+const BN256G1_ARGS: usize = 7;
+const BN256G2_ARGS: usize = 13;
+const BN256GT_ARGS: usize = 36;
+const BN256PAIR_ARGS: usize = BN256G1_ARGS + BN256G2_ARGS + BN256GT_ARGS;
+const BN256MULTIPAIR_ARGS: usize =
+    1 + MAX_MULTI_PAIRS * (BN256G1_ARGS + BN256G2_ARGS) + BN256GT_ARGS;
+
 fn bn256_fr_default(op: ForeignInst) -> Vec<ExternalHostCallEntry> {
     let mut r = vec![];
     for _ in 0..BN256FR_SIZE {
@@ -81,6 +105,200 @@ fn bn256_g2_generator(op: ForeignInst) -> Vec<ExternalHostCallEntry> {
     r
 }
 
+fn bn256_g1_to_args(a: &G1Affine, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    r.append(&mut crate::adaptor::fr_to_args(a.x, 5, 54, op));
+    r.append(&mut crate::adaptor::fr_to_args(a.y, 5, 54, op));
+    r.push(ExternalHostCallEntry {
+        op: op as usize,
+        value: G1::from(*a).is_identity().unwrap_u8() as u64,
+        is_ret: false,
+    });
+    r
+}
+
+fn bn256_g2_to_args(b: &G2Affine, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    r.append(&mut crate::adaptor::fr_to_args(b.x.c0, 5, 54, op));
+    r.append(&mut crate::adaptor::fr_to_args(b.x.c1, 5, 54, op));
+    r.append(&mut crate::adaptor::fr_to_args(b.y.c0, 5, 54, op));
+    r.append(&mut crate::adaptor::fr_to_args(b.y.c1, 5, 54, op));
+    r.push(ExternalHostCallEntry {
+        op: op as usize,
+        value: G2::from(*b).is_identity().unwrap_u8() as u64,
+        is_ret: false,
+    });
+    r
+}
+
+fn bn256_gt_to_args(ab: &Gt, op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for c in [ab.0.c0, ab.0.c1] {
+        for c in [c.c0, c.c1, c.c2] {
+            r.append(&mut crate::adaptor::fr_to_args(c.c0, 5, 54, op));
+            r.append(&mut crate::adaptor::fr_to_args(c.c1, 5, 54, op));
+        }
+    }
+    r
+}
+
+/// Host calls of the multi pairing e(a_1, b_1) * ... * e(a_n, b_n). The call
+/// always pushes MAX_MULTI_PAIRS pairs so that the rounds have a fixed layout
+/// and the pairs after the first n are identity points.
+pub fn multi_pair_to_host_call_table(
+    pairs: &Vec<(G1Affine, G2Affine)>,
+) -> Vec<ExternalHostCallEntry> {
+    assert!(pairs.len() > 0 && pairs.len() <= MAX_MULTI_PAIRS);
+    let mut r = vec![ExternalHostCallEntry {
+        op: ForeignInst::Bn254MultiPairNew as usize,
+        value: pairs.len() as u64,
+        is_ret: false,
+    }];
+    let mut ab = Gt::identity();
+    for (a, b) in pairs.iter() {
+        r.append(&mut bn256_g1_to_args(a, ForeignInst::Bn254MultiPairG1));
+        r.append(&mut bn256_g2_to_args(b, ForeignInst::Bn254MultiPairG2));
+        ab = ab + pairing(a, b);
+    }
+    for _ in pairs.len()..MAX_MULTI_PAIRS {
+        r.append(&mut bn256_g1_to_args(
+            &G1Affine::from(G1::identity()),
+            ForeignInst::Bn254MultiPairG1,
+        ));
+        r.append(&mut bn256_g2_to_args(
+            &G2Affine::from(G2::identity()),
+            ForeignInst::Bn254MultiPairG2,
+        ));
+    }
+    r.append(&mut bn256_gt_to_args(
+        &ab,
+        ForeignInst::Bn254MultiPairResult,
+    ));
+    r
+}
+
+/// Assign the filtered rows of consecutive Fq elements in 5 limbs of 54 bits,
+/// each of them is merged into the cells of 108, 108 and 54 bits
+fn assign_bn256_fqs(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    entries: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    for fq in entries.chunks_exact(5) {
+        for i in 0..2 {
+            let (limb, _op) = config.assign_merged_operands(
+                region,
+                offset,
+                vec![&fq[2 * i], &fq[2 * i + 1]],
+                Fr::from_u128(1u128 << 54),
+                enable,
+            )?;
+            r.push(limb);
+        }
+        r.push(assign_bn256_flag(region, offset, &fq[4], config, enable)?);
+    }
+    Ok(r)
+}
+
+fn assign_bn256_flag(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    entry: &((Fr, Fr), Fr),
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Limb<Fr>, Error> {
+    let ((operand, opcode), index) = *entry;
+    let (limb, _op) = config.assign_one_line(
+        region,
+        offset,
+        operand,
+        opcode,
+        index,
+        operand,
+        Fr::zero(),
+        enable,
+    )?;
+    Ok(limb)
+}
+
+/// Assign the filtered rows of a pairing round: G1, G2 and the Gt result
+fn assign_pair_round(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    let (g1, rest) = group.split_at(BN256G1_SIZE);
+    let (g2, gt) = rest.split_at(BN256G2_SIZE);
+    r.append(&mut assign_bn256_fqs(
+        region,
+        offset,
+        &g1[0..10],
+        config,
+        enable,
+    )?);
+    r.push(assign_bn256_flag(region, offset, &g1[10], config, enable)?);
+    r.append(&mut assign_bn256_fqs(
+        region,
+        offset,
+        &g2[0..20],
+        config,
+        enable,
+    )?);
+    r.push(assign_bn256_flag(region, offset, &g2[20], config, enable)?);
+    r.append(&mut assign_bn256_fqs(region, offset, gt, config, enable)?);
+    Ok(r)
+}
+
+/// Assign the filtered rows of a multi pairing round of MAX_MULTI_PAIRS pairs
+fn assign_multi_pair_round(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    assert!(group[0].0 .1 == Fr::from(ForeignInst::Bn254MultiPairNew as u64));
+    r.push(assign_bn256_flag(
+        region, offset, &group[0], config, enable,
+    )?);
+    let pairs = group[1..group.len() - BN256GT_SIZE].chunks_exact(BN256G1_SIZE + BN256G2_SIZE);
+    assert!(pairs.len() == MAX_MULTI_PAIRS);
+    for pair in pairs {
+        let (g1, g2) = pair.split_at(BN256G1_SIZE);
+        r.append(&mut assign_bn256_fqs(
+            region,
+            offset,
+            &g1[0..10],
+            config,
+            enable,
+        )?);
+        r.push(assign_bn256_flag(region, offset, &g1[10], config, enable)?);
+        r.append(&mut assign_bn256_fqs(
+            region,
+            offset,
+            &g2[0..20],
+            config,
+            enable,
+        )?);
+        r.push(assign_bn256_flag(region, offset, &g2[20], config, enable)?);
+    }
+    r.append(&mut assign_bn256_fqs(
+        region,
+        offset,
+        &group[group.len() - BN256GT_SIZE..],
+        config,
+        enable,
+    )?);
+    Ok(r)
+}
+
 fn bn256_gt_pairing_generator(op: ForeignInst) -> Vec<ExternalHostCallEntry> {
     let mut r = vec![];
     let a = G1::generator();
@@ -117,6 +335,10 @@ impl HostOpSelector for Bn256PairChip<Fr> {
         super::get_max_round(k, TOTAL_CONSTRUCTIONS_PAIR)
     }
 
+    fn chunk_size() -> usize {
+        BN256PAIR_SIZE
+    }
+
     fn construct(c: Self::Config) -> Self {
         Bn256PairChip::construct(c)
     }
@@ -148,111 +370,7 @@ impl HostOpSelector for Bn256PairChip<Fr> {
         let mut r = vec![];
 
         for group in selected_entries.chunks_exact(BN256PAIR_SIZE) {
-            // get g1_x and g1_y: ((1,1) (1,1) 1) * 2
-            for j in 0..2 {
-                for i in 0..2 {
-                    let (p_01, _op) = config.assign_merged_operands(
-                        region,
-                        offset,
-                        vec![&group[5 * j + 2 * i], &group[5 * j + 2 * i + 1]],
-                        Fr::from_u128(1u128 << 54),
-                        true,
-                    )?;
-                    r.push(p_01);
-                }
-                let ((operand, opcode), index) = *group.get(5 * j + 4).clone().unwrap();
-                let (p_2, _op) = config.assign_one_line(
-                    region,
-                    offset,
-                    operand,
-                    opcode,
-                    index,
-                    operand,
-                    Fr::zero(),
-                    true,
-                )?;
-                r.push(p_2);
-            }
-
-            // whether g1 is zero or not
-            let ((operand, opcode), index) = *group.get(10).clone().unwrap();
-
-            let (g1zero, _op) = config.assign_one_line(
-                region,
-                offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
-                true,
-            )?;
-            r.push(g1zero);
-
-            for j in 0..4 {
-                for i in 0..2 {
-                    let (p_01, _op) = config.assign_merged_operands(
-                        region,
-                        offset,
-                        vec![&group[5 * j + 2 * i + 11], &group[5 * j + 2 * i + 1 + 11]],
-                        Fr::from_u128(1u128 << 54),
-                        true,
-                    )?;
-                    r.push(p_01);
-                }
-                let ((operand, opcode), index) = *group.get(5 * j + 4 + 11).clone().unwrap();
-                let (p_2, _op) = config.assign_one_line(
-                    region,
-                    offset,
-                    operand,
-                    opcode,
-                    index,
-                    operand,
-                    Fr::zero(),
-                    true,
-                )?;
-                r.push(p_2);
-            }
-
-            // whether g2 is zero or not
-            let ((operand, opcode), index) = *group.get(31).clone().unwrap();
-
-            let (g2zero, _op) = config.assign_one_line(
-                region,
-                offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
-                true,
-            )?;
-            r.push(g2zero);
-
-            for j in 0..12 {
-                for i in 0..2 {
-                    let (q, _op) = config.assign_merged_operands(
-                        region,
-                        offset,
-                        vec![&group[5 * j + 2 * i + 32], &group[5 * j + 2 * i + 1 + 32]],
-                        Fr::from_u128(1u128 << 54),
-                        true,
-                    )?;
-                    r.push(q);
-                }
-                let ((operand, opcode), index) = *group.get(5 * j + 4 + 32).clone().unwrap();
-                let (q, _op) = config.assign_one_line(
-                    region,
-                    offset,
-                    operand,
-                    opcode,
-                    index,
-                    operand,
-                    Fr::zero(),
-                    true,
-                )?;
-                r.push(q);
-            }
+            r.append(&mut assign_pair_round(region, offset, group, config, true)?);
         }
 
         let mut default_table = vec![];
@@ -266,122 +384,124 @@ impl HostOpSelector for Bn256PairChip<Fr> {
             .collect::<Vec<((Fr, Fr), Fr)>>();
 
         let total_avail_rounds = Self::max_rounds(k);
+        assert!(total_used_instructions <= total_avail_rounds);
 
         for _ in 0..total_avail_rounds - total_used_instructions {
-            // get g1_x and g1_y: ((1,1) (1,1) 1) * 2
-            for j in 0..2 {
-                for i in 0..2 {
-                    let (p_01, _op) = config.assign_merged_operands(
-                        region,
-                        offset,
-                        vec![
-                            &default_entries[5 * j + 2 * i],
-                            &default_entries[5 * j + 2 * i + 1],
-                        ],
-                        Fr::from_u128(1u128 << 54),
-                        false,
-                    )?;
-                    r.push(p_01);
-                }
-                let ((operand, opcode), index) = default_entries[5 * j + 4].clone();
-                let (p_2, _op) = config.assign_one_line(
-                    region,
-                    offset,
-                    operand,
-                    opcode,
-                    index,
-                    operand,
-                    Fr::zero(),
-                    false,
-                )?;
-                r.push(p_2);
-            }
-
-            // whether g1 is zero or not
-            let ((operand, opcode), index) = default_entries[10].clone();
-
-            let (g1zero, _op) = config.assign_one_line(
+            r.append(&mut assign_pair_round(
                 region,
                 offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
+                &default_entries,
+                config,
                 false,
-            )?;
-            r.push(g1zero);
+            )?);
+        }
+        Ok(r)
+    }
 
-            for j in 0..4 {
-                for i in 0..2 {
-                    let (p_01, _op) = config.assign_merged_operands(
-                        region,
-                        offset,
-                        vec![
-                            &default_entries[5 * j + 2 * i + 11],
-                            &default_entries[5 * j + 2 * i + 1 + 11],
-                        ],
-                        Fr::from_u128(1u128 << 54),
-                        false,
-                    )?;
-                    r.push(p_01);
-                }
-                let ((operand, opcode), index) = default_entries[5 * j + 4 + 11].clone();
-                let (p_2, _op) = config.assign_one_line(
-                    region,
-                    offset,
-                    operand,
-                    opcode,
-                    index,
-                    operand,
-                    Fr::zero(),
-                    false,
-                )?;
-                r.push(p_2);
-            }
+    fn synthesize_separate(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        // every round (including the padding ones) is a pairing of the region
+        let pairs = arg_cells
+            .chunks_exact(BN256PAIR_ARGS)
+            .map(|args| {
+                (
+                    args[0..BN256G1_ARGS].to_vec(),
+                    args[BN256G1_ARGS..BN256G1_ARGS + BN256G2_ARGS].to_vec(),
+                    args[BN256G1_ARGS + BN256G2_ARGS..].to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        self.load_bn256_pair_circuit(&pairs, layouter)?;
+        Ok(())
+    }
 
-            // whether g2 is zero or not
-            let ((operand, opcode), index) = default_entries[31].clone();
+    fn synthesize(
+        &mut self,
+        _offset: &mut usize,
+        _arg_cells: &Vec<Limb<Fr>>,
+        _region: &Region<Fr>,
+        _helper: &(),
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
 
-            let (g2zero, _op) = config.assign_one_line(
+impl HostOpSelector for Bn256MultiPairChip<Fr> {
+    type Config = Bn256ChipConfig;
+    type Helper = ();
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+        _shared_advice: &Vec<Column<Advice>>,
+    ) -> Self::Config {
+        Bn256MultiPairChip::<Fr>::configure(meta)
+    }
+
+    fn max_rounds(k: usize) -> usize {
+        super::get_max_round(k, TOTAL_CONSTRUCTIONS_MULTI_PAIR)
+    }
+
+    fn chunk_size() -> usize {
+        BN256MULTIPAIR_SIZE
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        Bn256MultiPairChip::construct(c)
+    }
+
+    fn opcodes() -> Vec<Fr> {
+        vec![
+            Fr::from(ForeignInst::Bn254MultiPairNew as u64),
+            Fr::from(ForeignInst::Bn254MultiPairG1 as u64),
+            Fr::from(ForeignInst::Bn254MultiPairG2 as u64),
+            Fr::from(ForeignInst::Bn254MultiPairResult as u64),
+        ]
+    }
+
+    fn assign(
+        region: &Region<Fr>,
+        k: usize,
+        offset: &mut usize,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes = Self::opcodes();
+        let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &opcodes);
+
+        assert_eq!(*offset, 0);
+
+        assert!(selected_entries.len() % BN256MULTIPAIR_SIZE == 0);
+        let total_used_instructions = selected_entries.len() / BN256MULTIPAIR_SIZE;
+
+        let mut r = vec![];
+        for group in selected_entries.chunks_exact(BN256MULTIPAIR_SIZE) {
+            r.append(&mut assign_multi_pair_round(
+                region, offset, group, config, true,
+            )?);
+        }
+
+        let default_entries = multi_pair_to_host_call_table(&vec![(
+            G1Affine::from(G1::generator()),
+            G2Affine::from(G2::generator()),
+        )])
+        .into_iter()
+        .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+        .collect::<Vec<((Fr, Fr), Fr)>>();
+
+        let total_avail_rounds = Self::max_rounds(k);
+        assert!(total_used_instructions <= total_avail_rounds);
+
+        for _ in 0..total_avail_rounds - total_used_instructions {
+            r.append(&mut assign_multi_pair_round(
                 region,
                 offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
+                &default_entries,
+                config,
                 false,
-            )?;
-            r.push(g2zero);
-
-            for j in 0..12 {
-                for i in 0..2 {
-                    let (q, _op) = config.assign_merged_operands(
-                        region,
-                        offset,
-                        vec![
-                            &default_entries[5 * j + 2 * i + 32],
-                            &default_entries[5 * j + 2 * i + 1 + 32],
-                        ],
-                        Fr::from_u128(1u128 << 54),
-                        false,
-                    )?;
-                    r.push(q);
-                }
-                let ((operand, opcode), index) = default_entries[5 * j + 4 + 32].clone();
-                let (q, _op) = config.assign_one_line(
-                    region,
-                    offset,
-                    operand,
-                    opcode,
-                    index,
-                    operand,
-                    Fr::zero(),
-                    false,
-                )?;
-                r.push(q);
-            }
+            )?);
         }
         Ok(r)
     }
@@ -391,10 +511,20 @@ impl HostOpSelector for Bn256PairChip<Fr> {
         arg_cells: &Vec<Limb<Fr>>,
         layouter: &impl Layouter<Fr>,
     ) -> Result<(), Error> {
-        let a = arg_cells[0..7].to_vec();
-        let b = arg_cells[7..20].to_vec();
-        let ab = arg_cells[20..56].to_vec();
-        self.load_bn256_pair_circuit(&a, &b, &ab, layouter)?;
+        // every round (including the padding ones) lays out MAX_MULTI_PAIRS pairs
+        for args in arg_cells.chunks_exact(BN256MULTIPAIR_ARGS) {
+            let (pairs, ab) = args[1..].split_at(MAX_MULTI_PAIRS * (BN256G1_ARGS + BN256G2_ARGS));
+            let pairs = pairs
+                .chunks_exact(BN256G1_ARGS + BN256G2_ARGS)
+                .map(|pair| {
+                    (
+                        pair[0..BN256G1_ARGS].to_vec(),
+                        pair[BN256G1_ARGS..].to_vec(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            self.load_bn256_multi_pair_circuit(&args[0], &pairs, &ab.to_vec(), layouter)?;
+        }
         Ok(())
     }
 
@@ -427,6 +557,15 @@ impl HostOpSelector for Bn256SumChip<Fr> {
         super::get_max_round(k, TOTAL_CONSTRUCTIONS_SUM)
     }
 
+    fn chunk_size() -> usize {
+        BN256SUM_SIZE
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // Bn254SumNew with a non zero value resets the accumulator
+        round[0].value != 0
+    }
+
     fn opcodes() -> Vec<Fr> {
         vec![
             Fr::from(ForeignInst::Bn254SumNew as u64),
@@ -639,3 +778,125 @@ impl HostOpSelector for Bn256SumChip<Fr> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{bn256_g1_to_args, bn256_g2_to_args};
+    use super::{bn256_gt_to_args, multi_pair_to_host_call_table, BN256MULTIPAIR_SIZE};
+    use crate::circuits::bn256::{Bn256MultiPairChip, Bn256PairChip};
+    use crate::circuits::host::HostOpSelector;
+    use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable, ForeignInst};
+    use crate::proof::build_host_circuit;
+    use halo2_proofs::dev::{MockProver, VerifyFailure};
+    use halo2_proofs::pairing::bn256::{pairing, Fr, G1Affine, G2Affine, Gt, G1, G2};
+    use halo2_proofs::pairing::group::Group;
+    use std::fs::File;
+
+    fn multi_pair_circuit_verify(
+        table: Vec<ExternalHostCallEntry>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let circuit = build_host_circuit::<Bn256MultiPairChip<Fr>>(
+            &ExternalHostCallEntryTable(table),
+            22,
+            (),
+        );
+        MockProver::run(22, &circuit, vec![]).unwrap().verify()
+    }
+
+    #[test]
+    fn generate_bn256_multi_pair_input() {
+        let a = G1::generator();
+        let b = G2::generator();
+        // e(a, b) * e(-a, b) = 1
+        let pairs = vec![
+            (G1Affine::from(a), G2Affine::from(b)),
+            (G1Affine::from(-a), G2Affine::from(b)),
+        ];
+        let table = multi_pair_to_host_call_table(&pairs);
+        assert_eq!(table.len(), BN256MULTIPAIR_SIZE);
+        let result = table[table.len() - super::BN256GT_SIZE..]
+            .iter()
+            .map(|x| x.value)
+            .collect::<Vec<_>>();
+        let identity = bn256_gt_to_args(&Gt::identity(), ForeignInst::Bn254MultiPairResult)
+            .iter()
+            .map(|x| x.value)
+            .collect::<Vec<_>>();
+        assert_eq!(result, identity);
+        let file = File::create("bn256_multi_pair.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(table))
+            .expect("can not write to file");
+    }
+
+    #[test]
+    fn test_bn256_multi_pair_circuit() {
+        let a = G1::generator();
+        let b = G2::generator();
+        // a round of MAX_MULTI_PAIRS pairs fits in a circuit of 2^22 rows
+        let pairs = vec![
+            (G1Affine::from(a), G2Affine::from(b)),
+            (G1Affine::from(a.double()), G2Affine::from(b)),
+            (G1Affine::from(-a), G2Affine::from(b)),
+            (G1Affine::from(-a.double()), G2Affine::from(b)),
+        ];
+        let table = multi_pair_to_host_call_table(&pairs);
+        assert_eq!(multi_pair_circuit_verify(table), Ok(()));
+
+        // the pairs after the first n are padded with identity points
+        let pairs = vec![pairs[0], pairs[2]];
+        let table = multi_pair_to_host_call_table(&pairs);
+        assert_eq!(multi_pair_circuit_verify(table), Ok(()));
+    }
+
+    #[test]
+    fn test_bn256_multi_pair_forged_count() {
+        let a = G1::generator();
+        let b = G2::generator();
+        let pairs = vec![
+            (G1Affine::from(a), G2Affine::from(b)),
+            (G1Affine::from(a.double()), G2Affine::from(b)),
+            (G1Affine::from(-a), G2Affine::from(b)),
+            (G1Affine::from(-a.double()), G2Affine::from(b)),
+        ];
+        // the product of all the pairs claimed for a call of two pairs
+        let mut table = multi_pair_to_host_call_table(&pairs);
+        table[0].value = 2;
+        assert!(multi_pair_circuit_verify(table).is_err());
+    }
+
+    #[test]
+    fn test_bn256_multi_pair_forged_flag() {
+        let a = G1::generator();
+        let b = G2::generator();
+        let pairs = vec![
+            (G1Affine::from(a), G2Affine::from(b)),
+            (G1Affine::from(-a), G2Affine::from(b)),
+        ];
+        // the identity flag of the first G1 point follows its 10 coordinate limbs
+        let mut table = multi_pair_to_host_call_table(&pairs);
+        assert_eq!(table[1 + 10].op, ForeignInst::Bn254MultiPairG1 as usize);
+        table[1 + 10].value = 2;
+        assert!(multi_pair_circuit_verify(table).is_err());
+    }
+
+    #[test]
+    fn test_bn256_pair_capacity() {
+        let k = 23;
+        assert_eq!(Bn256PairChip::<Fr>::max_rounds(k), 2);
+        let a = G1Affine::from(G1::generator());
+        let b = G2Affine::from(G2::generator());
+        let mut table = vec![];
+        for a in [a, G1Affine::from(G1::generator().double())] {
+            table.append(&mut bn256_g1_to_args(&a, ForeignInst::Bn254PairG1));
+            table.append(&mut bn256_g2_to_args(&b, ForeignInst::Bn254PairG2));
+            table.append(&mut bn256_gt_to_args(
+                &pairing(&a, &b),
+                ForeignInst::Bn254PairG3,
+            ));
+        }
+        let circuit =
+            build_host_circuit::<Bn256PairChip<Fr>>(&ExternalHostCallEntryTable(table), k, ());
+        let prover = MockProver::run(k as u32, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
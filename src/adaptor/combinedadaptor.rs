@@ -0,0 +1,632 @@
+use crate::adaptor::get_selected_entries;
+use crate::adaptor::hashadaptor::{
+    assign_poseidon_round, poseidon_default_entries, CHUNK_SIZE as POSEIDON_CHUNK_SIZE,
+};
+use crate::adaptor::merkleadaptor::{
+    assign_merkle_round, merkle_default_entries, MerkleHelper, CHUNK_SIZE as MERKLE_CHUNK_SIZE,
+};
+use crate::adaptor::msmadaptor::{
+    assign_msm_round, msm_default_entries, CHUNK_SIZE as JUBJUB_CHUNK_SIZE,
+};
+use crate::circuits::babyjub::AltJubChip;
+use crate::circuits::combined::CombinedChip;
+use crate::circuits::host::{HostOpConfig, HostOpSelector};
+use crate::circuits::merkle::MerkleChip;
+use crate::circuits::poseidon::PoseidonChip;
+use crate::circuits::poseidon::PoseidonGateConfig;
+use crate::circuits::CommonGateConfig;
+use crate::host::ExternalHostCallEntry;
+use crate::host::ForeignInst;
+use crate::host::ForeignInst::{
+    JubjubSumNew, JubjubSumPush, JubjubSumResult, MerkleAddress, MerkleDelete, MerkleGet,
+    MerkleGetRoot, MerkleSet, MerkleSetRoot, PoseidonFinalize, PoseidonNew, PoseidonPush,
+};
+use crate::utils::Limb;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Layouter, Region};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::plonk::{Advice, Column, Error};
+
+/* The rounds of the sub chips share the rows of one circuit, so each of them
+ * only gets a part of the capacity of its own circuit.
+ */
+const POSEIDON_CONSTRUCTIONS: usize = 512;
+const MERKLE_CONSTRUCTIONS: usize = 270;
+const JUBJUB_CONSTRUCTIONS: usize = 150;
+
+// arguments passed to the synthesize of the sub chip by one round
+const POSEIDON_ARGS: usize = 1 + 8 + 1;
+const MERKLE_ARGS: usize = 6;
+const JUBJUB_ARGS: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RoundKind {
+    Poseidon,
+    Merkle,
+    JubJub,
+}
+
+const ROUND_KINDS: [RoundKind; 3] = [RoundKind::Poseidon, RoundKind::Merkle, RoundKind::JubJub];
+
+impl RoundKind {
+    /// the kind of the round that starts with opcode `op`, none if no round
+    /// starts with it
+    fn of(op: usize) -> Option<Self> {
+        if op == PoseidonNew as usize {
+            Some(RoundKind::Poseidon)
+        } else if op == MerkleAddress as usize {
+            Some(RoundKind::Merkle)
+        } else if op == JubjubSumNew as usize {
+            Some(RoundKind::JubJub)
+        } else {
+            None
+        }
+    }
+
+    fn of_field(op: Fr) -> Option<Self> {
+        Self::of(op.get_lower_128() as usize)
+    }
+
+    fn chunk_size(&self) -> usize {
+        match self {
+            RoundKind::Poseidon => POSEIDON_CHUNK_SIZE,
+            RoundKind::Merkle => MERKLE_CHUNK_SIZE,
+            RoundKind::JubJub => JUBJUB_CHUNK_SIZE,
+        }
+    }
+
+    fn max_rounds(&self, k: usize) -> usize {
+        match self {
+            RoundKind::Poseidon => super::get_max_round(k, POSEIDON_CONSTRUCTIONS),
+            RoundKind::Merkle => super::get_max_round(k, MERKLE_CONSTRUCTIONS),
+            RoundKind::JubJub => super::get_max_round(k, JUBJUB_CONSTRUCTIONS),
+        }
+    }
+
+    /// arguments passed to the synthesize of the sub chip by one round
+    fn args(&self) -> usize {
+        match self {
+            RoundKind::Poseidon => POSEIDON_ARGS,
+            RoundKind::Merkle => MERKLE_ARGS,
+            RoundKind::JubJub => JUBJUB_ARGS,
+        }
+    }
+
+    /// the opcodes allowed at each filtered row of a round
+    fn row_opcodes(&self) -> Vec<Vec<ForeignInst>> {
+        match self {
+            RoundKind::Poseidon => [
+                vec![vec![PoseidonNew]],
+                vec![vec![PoseidonPush]; 8 * 4],
+                vec![vec![PoseidonFinalize]; 4],
+            ]
+            .concat(),
+            RoundKind::Merkle => [
+                vec![vec![MerkleAddress]],
+                vec![vec![MerkleSetRoot]; 4],
+                vec![vec![MerkleSet, MerkleGet, MerkleDelete]; 4],
+                vec![vec![MerkleGetRoot]; 4],
+            ]
+            .concat(),
+            RoundKind::JubJub => [
+                vec![vec![JubjubSumNew]],
+                vec![vec![JubjubSumPush]; 3 * 4],
+                vec![vec![JubjubSumResult]; 2 * 4],
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// The helper of `CombinedChip`: the proofs of the merkle chip and the rounds
+/// of every sub chip, which are padded to their capacity at size k plus one.
+#[derive(Clone)]
+pub struct CombinedHelper<const DEPTH: usize> {
+    pub merkle: MerkleHelper<DEPTH>,
+    /// rounds of the poseidon, merkle and jubjub chips
+    pub rounds: [usize; 3],
+}
+
+impl<const DEPTH: usize> CombinedHelper<DEPTH> {
+    pub fn new(k: usize, merkle: MerkleHelper<DEPTH>) -> Self {
+        CombinedHelper {
+            merkle,
+            rounds: ROUND_KINDS.map(|kind| kind.max_rounds(k) + 1),
+        }
+    }
+}
+
+/// The helper of the default circuit, whose size is 22
+impl<const DEPTH: usize> Default for CombinedHelper<DEPTH> {
+    fn default() -> Self {
+        CombinedHelper::new(22, MerkleHelper::default())
+    }
+}
+
+/// Split the arguments of the sub chips and the filtered opcodes of their
+/// rounds, which follow the arguments, in the order of poseidon, merkle and jubjub
+fn split_args(rounds: [usize; 3], arg_cells: &[Limb<Fr>]) -> (Vec<&[Limb<Fr>]>, Vec<&[Limb<Fr>]>) {
+    let args_size: usize = ROUND_KINDS
+        .iter()
+        .zip(rounds)
+        .map(|(kind, rounds)| rounds * (kind.args() + kind.chunk_size()))
+        .sum();
+    assert_eq!(
+        arg_cells.len(),
+        args_size,
+        "combined arguments do not match the rounds {:?}",
+        rounds
+    );
+    let mut rest = arg_cells;
+    let mut args = vec![];
+    let mut opcodes = vec![];
+    for (kind, rounds) in ROUND_KINDS.iter().zip(rounds) {
+        let (head, tail) = rest.split_at(rounds * kind.args());
+        args.push(head);
+        rest = tail;
+    }
+    for (kind, rounds) in ROUND_KINDS.iter().zip(rounds) {
+        let (head, tail) = rest.split_at(rounds * kind.chunk_size());
+        opcodes.push(head);
+        rest = tail;
+    }
+    (args, opcodes)
+}
+
+/// Assign a round and copy the opcode cells of its filtered rows, which are
+/// assigned again with the same values, into `opcodes`
+fn assign_round(
+    kind: RoundKind,
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+    opcodes: &mut Vec<Limb<Fr>>,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let start = *offset;
+    let args = match kind {
+        RoundKind::Poseidon => assign_poseidon_round::<9>(region, offset, group, config, enable)?,
+        RoundKind::Merkle => assign_merkle_round(region, offset, group, config, enable)?,
+        RoundKind::JubJub => assign_msm_round(region, offset, group, config, enable)?,
+    };
+    for (row, ((_, opcode), _)) in group.iter().enumerate() {
+        opcodes.push(config.assign_cell(
+            region,
+            start + row,
+            &HostOpConfig::filtered_opcode(),
+            *opcode,
+        )?);
+    }
+    Ok(args)
+}
+
+/// Constrain the filtered opcodes of every round to the ones of its kind, so
+/// that the arguments of a round can only be dispatched to the chip of its kind
+fn constrain_round_opcodes(
+    config: &CommonGateConfig,
+    region: &Region<Fr>,
+    offset: &mut usize,
+    kind: RoundKind,
+    opcodes: &[Limb<Fr>],
+) -> Result<(), Error> {
+    let row_opcodes = kind.row_opcodes();
+    let mut constants: Vec<(Fr, Limb<Fr>)> = vec![];
+    for round in opcodes.chunks_exact(kind.chunk_size()) {
+        for (opcode, allowed) in round.iter().zip(row_opcodes.iter()) {
+            let allowed = allowed
+                .iter()
+                .map(|op| Fr::from(*op as u64))
+                .collect::<Vec<_>>();
+            if allowed.len() > 1 {
+                config.one_of_constants(region, &mut (), offset, opcode, &allowed)?;
+                continue;
+            }
+            let constant = match constants.iter().find(|(op, _)| *op == allowed[0]) {
+                Some((_, constant)) => constant.clone(),
+                None => {
+                    let constant = config.assign_constant(region, &mut (), offset, &allowed[0])?;
+                    constants.push((allowed[0], constant.clone()));
+                    constant
+                }
+            };
+            region.constrain_equal(opcode.get_the_cell().cell(), constant.get_the_cell().cell())?;
+        }
+    }
+    Ok(())
+}
+
+impl<const DEPTH: usize> HostOpSelector for CombinedChip<DEPTH> {
+    type Config = (CommonGateConfig, PoseidonGateConfig);
+    type Helper = CombinedHelper<DEPTH>;
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+        shared_advices: &Vec<Column<Advice>>,
+    ) -> Self::Config {
+        CombinedChip::<DEPTH>::configure(meta, shared_advices)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        CombinedChip::new(c.0, c.1)
+    }
+
+    fn max_rounds(k: usize) -> usize {
+        RoundKind::Poseidon.max_rounds(k)
+            + RoundKind::Merkle.max_rounds(k)
+            + RoundKind::JubJub.max_rounds(k)
+    }
+
+    fn chunk_size() -> usize {
+        // the rounds of the sub chips differ in size, so the trace is split and
+        // assigned by the size of each round and this is never asked for
+        unreachable!("combined host calls have no common round size, see round_size")
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        match RoundKind::of(round[0].op) {
+            Some(RoundKind::Poseidon) => PoseidonChip::<Fr, 9, 8>::is_fresh_round(round),
+            Some(RoundKind::Merkle) => MerkleChip::<Fr, DEPTH>::is_fresh_round(round),
+            Some(RoundKind::JubJub) => AltJubChip::<Fr>::is_fresh_round(round),
+            None => false,
+        }
+    }
+
+    fn round_size(entries: &[ExternalHostCallEntry]) -> Option<usize> {
+        RoundKind::of(entries[0].op).map(|kind| kind.chunk_size())
+    }
+
+    fn fits_in_circuit(rounds: &[&[ExternalHostCallEntry]], k: usize) -> bool {
+        ROUND_KINDS.into_iter().all(|kind| {
+            rounds
+                .iter()
+                .filter(|round| RoundKind::of(round[0].op) == Some(kind))
+                .count()
+                <= kind.max_rounds(k)
+        })
+    }
+
+    fn opcodes() -> Vec<Fr> {
+        let mut opcodes = PoseidonChip::<Fr, 9, 8>::opcodes();
+        opcodes.append(&mut MerkleChip::<Fr, DEPTH>::opcodes());
+        opcodes.append(&mut AltJubChip::<Fr>::opcodes());
+        opcodes
+    }
+
+    fn assign(
+        region: &Region<Fr>,
+        k: usize,
+        offset: &mut usize,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes = Self::opcodes();
+        let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &opcodes);
+
+        let mut args = [vec![], vec![], vec![]];
+        let mut round_opcodes = [vec![], vec![], vec![]];
+        let index = |kind: RoundKind| ROUND_KINDS.iter().position(|x| *x == kind).unwrap();
+
+        // the filtered rows follow the order of the shared table, so the rounds
+        // are assigned as they come and their arguments are dispatched by kind,
+        // a trace that does not consist of complete rounds can not be assigned
+        let mut position = 0;
+        while position < selected_entries.len() {
+            let kind =
+                RoundKind::of_field(selected_entries[position].0 .1).ok_or(Error::Synthesis)?;
+            let end = position + kind.chunk_size();
+            if end > selected_entries.len() {
+                return Err(Error::Synthesis);
+            }
+            let group = &selected_entries[position..end];
+            let interleaved = group
+                .iter()
+                .zip(kind.row_opcodes())
+                .any(|(((_, op), _), allowed)| !allowed.iter().any(|x| Fr::from(*x as u64) == *op));
+            if interleaved {
+                return Err(Error::Synthesis);
+            }
+            let i = index(kind);
+            args[i].append(&mut assign_round(
+                kind,
+                region,
+                offset,
+                group,
+                config,
+                true,
+                &mut round_opcodes[i],
+            )?);
+            position = end;
+        }
+
+        assert!(k >= 22);
+        for (i, kind) in ROUND_KINDS.into_iter().enumerate() {
+            let used = args[i].len() / kind.args();
+            let available = kind.max_rounds(k);
+            if used > available {
+                return Err(Error::Synthesis);
+            }
+            let default_entries = match kind {
+                RoundKind::Poseidon => poseidon_default_entries::<9, 8>(),
+                RoundKind::Merkle => merkle_default_entries::<DEPTH>(),
+                RoundKind::JubJub => msm_default_entries(),
+            };
+            for _ in 0..=available - used {
+                args[i].append(&mut assign_round(
+                    kind,
+                    region,
+                    offset,
+                    &default_entries,
+                    config,
+                    false,
+                    &mut round_opcodes[i],
+                )?);
+            }
+        }
+
+        Ok(args.into_iter().chain(round_opcodes).flatten().collect())
+    }
+
+    fn synthesize_separate(
+        &mut self,
+        arg_cells: &Vec<Limb<Fr>>,
+        layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let (args, _) = split_args(self.rounds, arg_cells);
+        self.poseidon_chip
+            .synthesize_separate(&args[0].to_vec(), layouter)?;
+        self.merkle_chip
+            .synthesize_separate(&args[1].to_vec(), layouter)?;
+        self.jubjub_chip
+            .synthesize_separate(&args[2].to_vec(), layouter)?;
+        Ok(())
+    }
+
+    fn synthesize(
+        &mut self,
+        offset: &mut usize,
+        arg_cells: &Vec<Limb<Fr>>,
+        region: &Region<Fr>,
+        helper: &Self::Helper,
+    ) -> Result<(), Error> {
+        self.rounds = helper.rounds;
+        let (args, opcodes) = split_args(helper.rounds, arg_cells);
+        let config = self.poseidon_chip.config.clone();
+        for (kind, opcodes) in ROUND_KINDS.into_iter().zip(opcodes) {
+            constrain_round_opcodes(&config, region, offset, kind, opcodes)?;
+        }
+        self.poseidon_chip
+            .synthesize(offset, &args[0].to_vec(), region, &())?;
+        self.merkle_chip
+            .synthesize(offset, &args[1].to_vec(), region, &helper.merkle)?;
+        self.jubjub_chip
+            .synthesize(offset, &args[2].to_vec(), region, &())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constrain_round_opcodes, CombinedHelper, RoundKind, ROUND_KINDS};
+    use crate::adaptor::hashadaptor::poseidon_default_entries;
+    use crate::adaptor::merkleadaptor::{merkle_default_entries, MerkleHelper};
+    use crate::adaptor::msmadaptor::msm_default_entries;
+    use crate::circuits::combined::CombinedChip;
+    use crate::circuits::{CommonGateConfig, Limb};
+    use crate::host::db::{RocksDB, TreeDB};
+    use crate::host::ForeignInst::{
+        JubjubSumNew, JubjubSumPush, JubjubSumResult, Log, MerkleAddress, MerkleDelete, MerkleGet,
+        MerkleGetRoot, MerkleSetRoot, PoseidonFinalize, PoseidonNew, PoseidonPush,
+    };
+    use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable, ForeignInst};
+    use crate::proof::{build_host_circuit, split_host_call_table, MERKLE_DEPTH};
+    use halo2_proofs::arithmetic::FieldExt;
+    use halo2_proofs::circuit::{floor_planner::FlatFloorPlanner, Layouter};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn entry(op: ForeignInst, value: u64) -> ExternalHostCallEntry {
+        ExternalHostCallEntry {
+            op: op as usize,
+            value,
+            is_ret: false,
+        }
+    }
+
+    fn round(ops: Vec<(ForeignInst, usize)>) -> Vec<ExternalHostCallEntry> {
+        ops.into_iter()
+            .flat_map(|(op, n)| vec![entry(op, 1); n])
+            .collect()
+    }
+
+    /// Checks the opcodes of the filtered rows of a round against `kind`
+    #[derive(Clone, Debug)]
+    struct RoundOpcodesCircuit {
+        kind: RoundKind,
+        opcodes: Vec<ForeignInst>,
+    }
+
+    impl Circuit<Fr> for RoundOpcodesCircuit {
+        type Config = CommonGateConfig;
+        type FloorPlanner = FlatFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let shared_advices = (0..5).map(|_| meta.advice_column()).collect();
+            CommonGateConfig::configure(meta, &(), &shared_advices)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "round opcodes test",
+                |region| {
+                    // the opcode cells take the rows before the checks
+                    let opcodes = self
+                        .opcodes
+                        .iter()
+                        .enumerate()
+                        .map(|(row, op)| {
+                            config.assign_cell(
+                                &region,
+                                row,
+                                &CommonGateConfig::l0(),
+                                Fr::from(*op as u64),
+                            )
+                        })
+                        .collect::<Result<Vec<Limb<Fr>>, Error>>()?;
+                    let mut offset = opcodes.len();
+                    constrain_round_opcodes(&config, &region, &mut offset, self.kind, &opcodes)
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    fn verify_round_opcodes(kind: RoundKind, opcodes: Vec<ForeignInst>) -> bool {
+        let circuit = RoundOpcodesCircuit { kind, opcodes };
+        let prover = MockProver::run(10, &circuit, vec![]).unwrap();
+        prover.verify().is_ok()
+    }
+
+    fn entries_of(selected: Vec<((Fr, Fr), Fr)>) -> Vec<ExternalHostCallEntry> {
+        selected
+            .into_iter()
+            .map(|((operand, op), _)| ExternalHostCallEntry {
+                op: op.get_lower_128() as usize,
+                value: operand.get_lower_128() as u64,
+                is_ret: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_combined_rounds() {
+        let rounds = |k| CombinedHelper::<MERKLE_DEPTH>::new(k, MerkleHelper::default()).rounds;
+        assert_eq!(rounds(22), [513, 271, 151]);
+        assert_eq!(rounds(23), [1025, 541, 301]);
+        for kind in ROUND_KINDS {
+            assert_eq!(kind.row_opcodes().len(), kind.chunk_size());
+        }
+    }
+
+    #[test]
+    fn test_round_opcodes() {
+        let hash = [
+            vec![PoseidonNew],
+            vec![PoseidonPush; 32],
+            vec![PoseidonFinalize; 4],
+        ]
+        .concat();
+        assert!(verify_round_opcodes(RoundKind::Poseidon, hash.clone()));
+        // a poseidon round dispatched to the merkle chip
+        assert!(!verify_round_opcodes(
+            RoundKind::Merkle,
+            hash[0..13].to_vec()
+        ));
+
+        let delete = [
+            vec![MerkleAddress],
+            vec![MerkleSetRoot; 4],
+            vec![MerkleDelete; 4],
+            vec![MerkleGetRoot; 4],
+        ]
+        .concat();
+        assert!(verify_round_opcodes(RoundKind::Merkle, delete.clone()));
+        // a root in place of the value of the merkle call
+        let mut wrong = delete;
+        wrong[6] = MerkleGetRoot;
+        assert!(!verify_round_opcodes(RoundKind::Merkle, wrong));
+    }
+
+    #[test]
+    fn test_combined_circuit() {
+        let mut entries = entries_of(poseidon_default_entries::<9, 8>());
+        entries.push(entry(Log, 0));
+        entries.append(&mut entries_of(merkle_default_entries::<MERKLE_DEPTH>()));
+        entries.append(&mut entries_of(msm_default_entries()));
+        let table = ExternalHostCallEntryTable(entries);
+
+        let dir = tempfile::tempdir().unwrap();
+        let db: Rc<RefCell<dyn TreeDB>> = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+        let helper = CombinedHelper::new(22, MerkleHelper::TreeDB(Some(db)));
+        let circuit = build_host_circuit::<CombinedChip<MERKLE_DEPTH>>(&table, 22, helper);
+        let prover = MockProver::run(22, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_split_combined_trace() {
+        let hash = round(vec![
+            (PoseidonNew, 1),
+            (PoseidonPush, 32),
+            (PoseidonFinalize, 4),
+        ]);
+        let get = round(vec![
+            (MerkleAddress, 1),
+            (MerkleSetRoot, 4),
+            (MerkleGet, 4),
+            (MerkleGetRoot, 4),
+        ]);
+        let msm = round(vec![
+            (JubjubSumNew, 1),
+            (JubjubSumPush, 12),
+            (JubjubSumResult, 8),
+        ]);
+        let mut entries = vec![];
+        for _ in 0..151 {
+            entries.append(&mut hash.clone());
+            entries.push(entry(Log, 0));
+            entries.append(&mut get.clone());
+            entries.append(&mut msm.clone());
+        }
+        let table = ExternalHostCallEntryTable(entries);
+
+        // only the last msm round exceeds the share of the jubjub chip
+        let segments = split_host_call_table::<CombinedChip<MERKLE_DEPTH>>(&table, 22).unwrap();
+        let size = hash.len() + get.len() + msm.len();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].1.end, 150 * size + hash.len() + get.len());
+        assert_eq!(segments[1].1.start, segments[0].1.end);
+        assert_eq!(segments[1].1.end, 151 * size);
+        assert_eq!(segments[1].0 .0.len(), msm.len());
+    }
+
+    #[test]
+    fn test_malformed_combined_trace() {
+        let get = round(vec![
+            (MerkleAddress, 1),
+            (MerkleSetRoot, 4),
+            (MerkleGet, 4),
+            (MerkleGetRoot, 4),
+        ]);
+        let split = |entries: Vec<ExternalHostCallEntry>| {
+            let table = ExternalHostCallEntryTable(entries);
+            split_host_call_table::<CombinedChip<MERKLE_DEPTH>>(&table, 22)
+        };
+        // a round that does not start with its first opcode
+        assert!(split(get[1..].to_vec()).is_err());
+        // a round cut off by the end of the trace
+        assert!(split([get.clone(), get[0..12].to_vec()].concat()).is_err());
+
+        // a merkle round with a poseidon call in place of its first root limb
+        // has the size of a merkle round and is only rejected by the assign
+        let mut interleaved = get;
+        interleaved[1] = entry(PoseidonPush, 1);
+        let table = ExternalHostCallEntryTable(interleaved);
+        assert_eq!(split(table.0.clone()).unwrap().len(), 1);
+        let helper = CombinedHelper::new(22, MerkleHelper::default());
+        let circuit = build_host_circuit::<CombinedChip<MERKLE_DEPTH>>(&table, 22, helper);
+        assert!(MockProver::run(22, &circuit, vec![]).is_err());
+    }
+}
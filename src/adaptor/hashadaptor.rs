@@ -5,9 +5,11 @@ use crate::circuits::poseidon::PoseidonGateConfig;
 use crate::circuits::CommonGateConfig;
 use crate::circuits::LookupAssistChip;
 use crate::circuits::LookupAssistConfig;
-use crate::host::poseidon::POSEIDON_HASHER;
-use crate::host::poseidon::POSEIDON_HASHER_SPEC;
-use crate::host::ForeignInst::{PoseidonFinalize, PoseidonNew, PoseidonPush};
+use crate::host::poseidon::{poseidon_hasher, poseidon_spec};
+use crate::host::ForeignInst::{
+    PoseidonFinalize, PoseidonNew, PoseidonPush, PoseidonT3Finalize, PoseidonT3New, PoseidonT3Push,
+    PoseidonT5Finalize, PoseidonT5New, PoseidonT5Push,
+};
 use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable, ForeignInst};
 use ark_std::{end_timer, start_timer};
 use halo2_proofs::arithmetic::FieldExt;
@@ -40,50 +42,133 @@ impl<F: FieldExt> LookupAssistChip<F> for () {
     }
 }
 
-fn hash_cont(restart: bool) -> Vec<ExternalHostCallEntry> {
+/// The (new, push, finalize) opcodes of the poseidon host calls of width t
+pub(crate) fn poseidon_opcodes(t: usize) -> [ForeignInst; 3] {
+    match t {
+        3 => [PoseidonT3New, PoseidonT3Push, PoseidonT3Finalize],
+        5 => [PoseidonT5New, PoseidonT5Push, PoseidonT5Finalize],
+        9 => [PoseidonNew, PoseidonPush, PoseidonFinalize],
+        _ => unreachable!("unsupported poseidon width {}", t),
+    }
+}
+
+fn hash_cont(new: ForeignInst, restart: bool) -> Vec<ExternalHostCallEntry> {
     vec![ExternalHostCallEntry {
-        op: PoseidonNew as usize,
+        op: new as usize,
         value: if restart { 1u64 } else { 0u64 },
         is_ret: false,
     }]
 }
 
-fn hash_to_host_call_table(inputs: [Fr; 8], result: Fr) -> ExternalHostCallEntryTable {
+pub(crate) fn hash_to_host_call_table(
+    t: usize,
+    inputs: &[Fr],
+    result: Fr,
+) -> ExternalHostCallEntryTable {
+    let [new, push, finalize] = poseidon_opcodes(t);
     let mut r = vec![];
-    r.push(hash_cont(true));
+    r.push(hash_cont(new, true));
     for f in inputs.iter() {
-        r.push(crate::adaptor::fr_to_args(*f, 4, 64, PoseidonPush));
+        r.push(crate::adaptor::fr_to_args(*f, 4, 64, push));
     }
-    r.push(crate::adaptor::fr_to_args(result, 4, 64, PoseidonFinalize));
+    r.push(crate::adaptor::fr_to_args(result, 4, 64, finalize));
     ExternalHostCallEntryTable(r.into_iter().flatten().collect())
 }
 
-const TOTAL_CONSTRUCTIONS: usize = 2048;
+/// The entries of a round: the new call, the inputs and the result in four u64 limbs each
+pub(crate) const fn chunk_size(rate: usize) -> usize {
+    1 + rate * 4 + 4
+}
+
+pub(crate) const CHUNK_SIZE: usize = chunk_size(8);
+
+/// The rounds of a k = 22 circuit. A round of width 9, 5 and 3 takes
+/// about 1200, 700 and 130 rows including its filtered entries.
+pub(crate) fn total_constructions(t: usize) -> usize {
+    match t {
+        3 => 16384,
+        5 => 3584,
+        9 => 2048,
+        _ => unreachable!("unsupported poseidon width {}", t),
+    }
+}
+
+/// The selected entries of the round used to pad the unused rounds
+pub(crate) fn poseidon_default_entries<const T: usize, const RATE: usize>() -> Vec<((Fr, Fr), Fr)> {
+    let mut inputs = [Fr::zero(); RATE];
+    inputs[0] = Fr::one();
+    let default_table = hash_to_host_call_table(T, &inputs, poseidon_hasher::<T, RATE>().squeeze());
+    default_table
+        .0
+        .into_iter()
+        .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+        .collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+/// Assign the filtered rows of one round: the new line of width T followed by
+/// the merged inputs and the merged result.
+pub(crate) fn assign_poseidon_round<const T: usize>(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    let ((operand, opcode), index) = group[0].clone();
+    assert!(opcode.clone() == Fr::from(poseidon_opcodes(T)[0] as u64));
+
+    let (limb, _op) = config.assign_one_line(
+        region,
+        offset,
+        operand,
+        opcode,
+        index,
+        operand,
+        Fr::zero(),
+        enable,
+    )?;
+    r.push(limb);
+
+    for subgroup in group.iter().skip(1).collect::<Vec<_>>().chunks_exact(4) {
+        let (limb, _op) =
+            config.assign_merged_fr_operands(region, offset, subgroup.to_vec(), enable)?;
+        r.push(limb);
+    }
+    Ok(r)
+}
 
-impl HostOpSelector for PoseidonChip<Fr, 9, 8> {
+/// The poseidon host calls of width T, which are PoseidonNew/Push/Finalize for
+/// `PoseidonChip<Fr, 9, 8>` and PoseidonT3* or PoseidonT5* for the widths 3 and 5.
+impl<const T: usize, const RATE: usize> HostOpSelector for PoseidonChip<Fr, T, RATE> {
     type Config = (CommonGateConfig, PoseidonGateConfig);
     type Helper = ();
     fn configure(
         meta: &mut ConstraintSystem<Fr>,
         shared_advices: &Vec<Column<Advice>>,
     ) -> Self::Config {
-        PoseidonChip::<Fr, 9, 8>::configure(meta, shared_advices)
+        PoseidonChip::<Fr, T, RATE>::configure(meta, shared_advices)
     }
 
     fn construct(c: Self::Config) -> Self {
-        PoseidonChip::construct(c.0, c.1, POSEIDON_HASHER_SPEC.clone())
+        PoseidonChip::construct(c.0, c.1, poseidon_spec::<T, RATE>())
     }
 
     fn max_rounds(k: usize) -> usize {
-        super::get_max_round(k, TOTAL_CONSTRUCTIONS)
+        super::get_max_round(k, total_constructions(T))
+    }
+
+    fn chunk_size() -> usize {
+        chunk_size(RATE)
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // PoseidonNew with value 1 restarts the hasher
+        round[0].value == 1
     }
 
     fn opcodes() -> Vec<Fr> {
-        vec![
-            Fr::from(ForeignInst::PoseidonNew as u64),
-            Fr::from(ForeignInst::PoseidonPush as u64),
-            Fr::from(ForeignInst::PoseidonFinalize as u64),
-        ]
+        poseidon_opcodes(T).map(|x| Fr::from(x as u64)).to_vec()
     }
 
     fn assign(
@@ -96,101 +181,30 @@ impl HostOpSelector for PoseidonChip<Fr, 9, 8> {
     ) -> Result<Vec<Limb<Fr>>, Error> {
         let opcodes = Self::opcodes();
         let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &opcodes);
-        let total_used_instructions = selected_entries.len() / (1 + 8 * 4 + 4);
+        let total_used_instructions = selected_entries.len() / chunk_size(RATE);
 
         let mut r = vec![];
 
-        // TODO: Change 8 to RATE ?
-        for group in selected_entries.chunks_exact(1 + 8 * 4 + 4) {
-            let ((operand, opcode), index) = *group.get(0).clone().unwrap();
-            assert!(opcode.clone() == Fr::from(PoseidonNew as u64));
-
-            let (limb, _op) = config.assign_one_line(
-                region,
-                offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
-                true,
-            )?;
-            r.push(limb);
-
-            for subgroup in group
-                .into_iter()
-                .skip(1)
-                .collect::<Vec<_>>()
-                .chunks_exact(4)
-            {
-                let (limb, _op) = config.assign_merged_operands(
-                    region,
-                    offset,
-                    subgroup.to_vec(),
-                    Fr::from_u128(1u128 << 64),
-                    true,
-                )?;
-                r.push(limb);
-            }
+        for group in selected_entries.chunks_exact(chunk_size(RATE)) {
+            r.append(&mut assign_poseidon_round::<T>(
+                region, offset, group, config, true,
+            )?);
         }
 
-        let default_table = hash_to_host_call_table(
-            [
-                Fr::one(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-            ],
-            POSEIDON_HASHER.clone().squeeze(),
-        );
-
-        //let entries = default_table.
-        let default_entries: Vec<((Fr, Fr), Fr)> = default_table
-            .0
-            .into_iter()
-            .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
-            .collect::<Vec<((Fr, Fr), Fr)>>();
+        let default_entries = poseidon_default_entries::<T, RATE>();
 
         assert!(k >= 22);
         let total_available = Self::max_rounds(k);
         assert!(total_used_instructions <= total_available);
 
         for _ in 0..=total_available - total_used_instructions {
-            let ((operand, opcode), index) = default_entries[0].clone();
-            assert!(opcode.clone() == Fr::from(PoseidonNew as u64));
-
-            let (limb, _op) = config.assign_one_line(
+            r.append(&mut assign_poseidon_round::<T>(
                 region,
                 offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
+                &default_entries,
+                config,
                 false,
-            )?;
-            r.push(limb);
-
-            for subgroup in default_entries
-                .clone()
-                .iter()
-                .skip(1)
-                .collect::<Vec<_>>()
-                .chunks_exact(4)
-            {
-                let (limb, _op) = config.assign_merged_operands(
-                    region,
-                    offset,
-                    subgroup.to_vec(),
-                    Fr::from_u128(1u128 << 64),
-                    false,
-                )?;
-                r.push(limb);
-            }
+            )?);
         }
 
         Ok(r)
@@ -217,15 +231,15 @@ impl HostOpSelector for PoseidonChip<Fr, 9, 8> {
             let timer = start_timer!(|| "assign");
             let config = self.config.clone();
             self.initialize(&config, region, &mut local_offset)?;
-            for arg_group in arg_cells.chunks_exact(10).into_iter() {
+            for arg_group in arg_cells.chunks_exact(RATE + 2).into_iter() {
                 let args = arg_group.into_iter().map(|x| x.clone());
                 let args = args.collect::<Vec<_>>();
                 self.assign_permute(
                     region,
                     &mut local_offset,
-                    &args[1..9].to_vec().try_into().unwrap(),
+                    &args[1..RATE + 1].to_vec().try_into().unwrap(),
                     &args[0],
-                    &args[9],
+                    &args[RATE + 1],
                 )?;
             }
             end_timer!(timer);
@@ -235,6 +249,25 @@ impl HostOpSelector for PoseidonChip<Fr, 9, 8> {
     }
 }
 
+#[cfg(test)]
+impl<const T: usize, const RATE: usize> crate::circuits::host::TestRounds
+    for PoseidonChip<Fr, T, RATE>
+{
+    fn default_round() -> Vec<((Fr, Fr), Fr)> {
+        poseidon_default_entries::<T, RATE>()
+    }
+
+    fn assign_round(
+        region: &Region<Fr>,
+        offset: &mut usize,
+        group: &[((Fr, Fr), Fr)],
+        config: &HostOpConfig,
+        enable: bool,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        assign_poseidon_round::<T>(region, offset, group, config, enable)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable};
@@ -301,4 +334,24 @@ mod tests {
         let file = File::create("poseidontest_multi.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &table).expect("can not write to file");
     }
+
+    #[test]
+    fn generate_poseidon_t3_t5_input() {
+        let inputs = [Fr::one(), Fr::from(2u64), Fr::from(3u64), Fr::from(4u64)];
+        let result = crate::host::poseidon::POSEIDON_T3_HASHER
+            .clone()
+            .update_exact(&[inputs[0], inputs[1]]);
+        let table = super::hash_to_host_call_table(3, &inputs[0..2], result);
+        assert_eq!(table.0.len(), super::chunk_size(2));
+        let file = File::create("poseidon_t3_test.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+
+        let result = crate::host::poseidon::POSEIDON_T5_HASHER
+            .clone()
+            .update_exact(&inputs);
+        let table = super::hash_to_host_call_table(5, &inputs, result);
+        assert_eq!(table.0.len(), super::chunk_size(4));
+        let file = File::create("poseidon_t5_test.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
 }
@@ -0,0 +1,226 @@
+use crate::adaptor::field_to_bn;
+use crate::adaptor::get_selected_entries;
+use crate::circuits::babyjub::Point as CircuitPoint;
+use crate::circuits::host::{HostOpConfig, HostOpSelector};
+use crate::circuits::jubjubverify::JubjubVerifyChip;
+use crate::circuits::poseidon::PoseidonGateConfig;
+use crate::circuits::CommonGateConfig;
+use crate::host::jubjub::{verify, Point};
+use crate::host::ExternalHostCallEntry;
+use crate::host::ForeignInst::{JubjubVerifyMsg, JubjubVerifyPk, JubjubVerifySig};
+use crate::utils::Limb;
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::circuit::{Layouter, Region};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::plonk::{Advice, Column, Error};
+
+/* The calling convention of a signature verification
+ * JubjubVerifyPk: A.x, A.y
+ * JubjubVerifySig: R.x, R.y, s
+ * JubjubVerifyMsg: msg
+ * where each field element is pushed as four u64 limbs.
+ */
+
+const MERGE_SIZE: usize = 4;
+pub(crate) const CHUNK_SIZE: usize = (2 + 3 + 1) * MERGE_SIZE;
+
+// each verification takes two scalar multiplications and two poseidon rounds
+const TOTAL_CONSTRUCTIONS: usize = 240;
+
+/// Host calls of the signatures (A, R, s, msg)
+pub fn verify_to_host_call_table(
+    inputs: &Vec<(Point, Point, Fr, Fr)>,
+) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for (pk, sig_r, sig_s, msg) in inputs.into_iter() {
+        assert!(verify(pk, sig_r, &field_to_bn(sig_s), msg));
+        r.push(crate::adaptor::fr_to_args(pk.x, 4, 64, JubjubVerifyPk));
+        r.push(crate::adaptor::fr_to_args(pk.y, 4, 64, JubjubVerifyPk));
+        r.push(crate::adaptor::fr_to_args(sig_r.x, 4, 64, JubjubVerifySig));
+        r.push(crate::adaptor::fr_to_args(sig_r.y, 4, 64, JubjubVerifySig));
+        r.push(crate::adaptor::fr_to_args(*sig_s, 4, 64, JubjubVerifySig));
+        r.push(crate::adaptor::fr_to_args(*msg, 4, 64, JubjubVerifyMsg));
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+/// The selected entries of the round used to pad the unused rounds:
+/// R + h.O = 0.B holds for the identity O whatever the message is.
+pub(crate) fn verify_default_entries() -> Vec<((Fr, Fr), Fr)> {
+    verify_to_host_call_table(&vec![(
+        Point::identity(),
+        Point::identity(),
+        Fr::zero(),
+        Fr::zero(),
+    )])
+    .into_iter()
+    .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+    .collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+fn assign_verify_round(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    assert!(group[0].0 .1 == Fr::from(JubjubVerifyPk as u64));
+    for subgroup in group.iter().collect::<Vec<_>>().chunks_exact(MERGE_SIZE) {
+        let (limb, _) =
+            config.assign_merged_fr_operands(region, offset, subgroup.to_vec(), enable)?;
+        r.push(limb);
+    }
+    Ok(r)
+}
+
+impl HostOpSelector for JubjubVerifyChip<Fr> {
+    type Config = (CommonGateConfig, PoseidonGateConfig);
+    type Helper = ();
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+        shared_advice: &Vec<Column<Advice>>,
+    ) -> Self::Config {
+        JubjubVerifyChip::<Fr>::configure(meta, shared_advice)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        JubjubVerifyChip::new(c.0, c.1)
+    }
+
+    fn max_rounds(k: usize) -> usize {
+        super::get_max_round(k, TOTAL_CONSTRUCTIONS)
+    }
+
+    fn chunk_size() -> usize {
+        CHUNK_SIZE
+    }
+
+    fn is_fresh_round(_round: &[ExternalHostCallEntry]) -> bool {
+        // every verification is independent
+        true
+    }
+
+    fn opcodes() -> Vec<Fr> {
+        vec![
+            Fr::from(JubjubVerifyPk as u64),
+            Fr::from(JubjubVerifySig as u64),
+            Fr::from(JubjubVerifyMsg as u64),
+        ]
+    }
+
+    fn assign(
+        region: &Region<Fr>,
+        k: usize,
+        offset: &mut usize,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes = Self::opcodes();
+        let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &opcodes);
+        let total_used_instructions = selected_entries.len() / (CHUNK_SIZE);
+
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+            r.append(&mut assign_verify_round(
+                region, offset, group, config, true,
+            )?);
+        }
+
+        let default_entries = verify_default_entries();
+
+        assert!(k >= 22);
+        let total_available = Self::max_rounds(k);
+        assert!(total_used_instructions <= total_available);
+
+        for _ in 0..=total_available - total_used_instructions {
+            r.append(&mut assign_verify_round(
+                region,
+                offset,
+                &default_entries,
+                config,
+                false,
+            )?);
+        }
+
+        Ok(r)
+    }
+
+    fn synthesize_separate(
+        &mut self,
+        _arg_cells: &Vec<Limb<Fr>>,
+        _layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn synthesize(
+        &mut self,
+        offset: &mut usize,
+        arg_cells: &Vec<Limb<Fr>>,
+        region: &Region<Fr>,
+        _helper: &(),
+    ) -> Result<(), Error> {
+        *offset = {
+            let mut local_offset = *offset;
+            let timer = start_timer!(|| "assign");
+            let config = self.config.clone();
+            self.initialize(&config, region, &mut local_offset)?;
+            // arg_cells format 2 + 3 + 1
+            for args in arg_cells.chunks_exact(6) {
+                self.assign_verify(
+                    region,
+                    &mut local_offset,
+                    &CircuitPoint {
+                        x: args[0].clone(),
+                        y: args[1].clone(),
+                    },
+                    &CircuitPoint {
+                        x: args[2].clone(),
+                        y: args[3].clone(),
+                    },
+                    &args[4],
+                    &args[5],
+                )?;
+            }
+            end_timer!(timer);
+            local_offset
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_to_host_call_table;
+    use crate::host::jubjub::{challenge, Point, BASE, SUBGROUP_ORDER};
+    use crate::host::ExternalHostCallEntryTable;
+    use crate::utils::{bn_to_field, field_to_bn};
+    use halo2_proofs::pairing::bn256::Fr;
+    use num_bigint::BigUint;
+    use std::fs::File;
+
+    #[test]
+    fn generate_jubjub_verify_input() {
+        let sk = BigUint::from(0x1234_5678_9abc_def0u64);
+        let nonce = BigUint::from(0x0fed_cba9_8765_4321u64);
+        let msg = Fr::from(42u64);
+        let pk = BASE.mul_scalar(&sk);
+        let sig_r = BASE.mul_scalar(&nonce);
+        let h = field_to_bn(&challenge(&sig_r, &pk, &msg));
+        let sig_s = (nonce + h * sk) % &*SUBGROUP_ORDER;
+
+        let table = verify_to_host_call_table(&vec![
+            (pk, sig_r, bn_to_field(&sig_s), msg),
+            (Point::identity(), Point::identity(), Fr::zero(), Fr::zero()),
+        ]);
+        assert_eq!(table.len(), super::CHUNK_SIZE * 2);
+        let file = File::create("jubjub_verify.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(table))
+            .expect("can not write to file");
+    }
+}
@@ -2,7 +2,7 @@ use crate::adaptor::get_selected_entries;
 use crate::circuits::host::{HostOpConfig, HostOpSelector};
 use crate::circuits::keccak256::KeccakChip;
 use crate::circuits::keccak256::KeccakGateConfig;
-use crate::host::keccak256::KECCAK_HASHER;
+use crate::host::keccak256::{bytes_to_blocks, keccak_new_operand, pad_block, KECCAK_HASHER};
 use crate::host::ForeignInst::{Keccak256Finalize, Keccak256New, Keccak256Push};
 use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable, ForeignInst};
 use crate::utils::Limb;
@@ -32,7 +32,46 @@ fn hash_to_host_call_table(inputs: &[Fr; 17], result: &[Fr; 4]) -> ExternalHostC
     ExternalHostCallEntryTable(r.into_iter().flatten().collect())
 }
 
-const TOTAL_CONSTRUCTIONS: usize = 50;
+/// Build the host call table of a variable length byte message.
+/// Each block pushes the unpadded message lanes and the operand of
+/// Keccak256New tells the circuit where the padding goes.
+pub fn bytes_to_host_call_table(input: &[u8]) -> ExternalHostCallEntryTable {
+    let mut r = vec![];
+    let mut hasher = KECCAK_HASHER.clone();
+    for (i, (block, last)) in bytes_to_blocks(input).into_iter().enumerate() {
+        r.push(vec![ExternalHostCallEntry {
+            op: Keccak256New as usize,
+            value: keccak_new_operand(i == 0, last),
+            is_ret: false,
+        }]);
+        for f in block.iter() {
+            r.push(crate::adaptor::fr_to_args(
+                Fr::from(*f),
+                1,
+                64,
+                Keccak256Push,
+            ));
+        }
+        let padded = match last {
+            Some(len) => pad_block(&block, len),
+            None => block,
+        };
+        let result = hasher.update_exact(&padded);
+        for f in result.iter() {
+            r.push(crate::adaptor::fr_to_args(
+                Fr::from(*f),
+                1,
+                64,
+                Keccak256Finalize,
+            ));
+        }
+    }
+    ExternalHostCallEntryTable(r.into_iter().flatten().collect())
+}
+
+/// Permutations per 2^22 rows with the chained round layout of 584 rows per
+/// round, the step by step layout of 736 rows per round fits about 230
+pub(crate) const TOTAL_CONSTRUCTIONS: usize = 256;
 
 impl HostOpSelector for KeccakChip<Fr> {
     type Config = KeccakGateConfig;
@@ -52,6 +91,15 @@ impl HostOpSelector for KeccakChip<Fr> {
         super::get_max_round(k, TOTAL_CONSTRUCTIONS)
     }
 
+    fn chunk_size() -> usize {
+        1 + 17 + 4
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // the lowest bit of Keccak256New restarts the hasher
+        round[0].value & 1 == 1
+    }
+
     fn opcodes() -> Vec<Fr> {
         vec![
             Fr::from(ForeignInst::Keccak256New as u64),
@@ -219,6 +267,7 @@ impl HostOpSelector for KeccakChip<Fr> {
 
 #[cfg(test)]
 mod tests {
+    use super::bytes_to_host_call_table;
     use crate::host::ForeignInst::{Keccak256Finalize, Keccak256New, Keccak256Push};
     use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable};
     use crate::utils::field_to_u64;
@@ -389,4 +438,14 @@ mod tests {
         let file = File::create("keccak256_test_multi_byte.json").expect("can not create file");
         serde_json::to_writer_pretty(file, &table).expect("can not write to file");
     }
+
+    #[test]
+    fn generate_keccak_input_bytes() {
+        let input = (0..300u32).map(|x| x as u8).collect::<Vec<_>>();
+        let table = bytes_to_host_call_table(&input);
+        // three blocks of 1 + 17 + 4 calls
+        assert_eq!(table.0.len(), 3 * 22);
+        let file = File::create("keccak256_test_bytes.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &table).expect("can not write to file");
+    }
 }
@@ -0,0 +1,325 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::adaptor::get_selected_entries;
+use crate::adaptor::keccakadaptor::TOTAL_CONSTRUCTIONS as TOTAL_PERMUTATIONS;
+use crate::adaptor::merkleadaptor::CHUNK_SIZE;
+use crate::circuits::host::{HostOpConfig, HostOpSelector};
+use crate::circuits::keccak256::KeccakGateConfig;
+use crate::circuits::keccakmerkle::KeccakMerkleChip;
+use crate::host::db::TreeDB;
+use crate::host::merkle::MerkleTree;
+use crate::host::mongomerkle::{KeccakMerkle, KECCAK_DEFAULT_HASH_VEC};
+use crate::host::ExternalHostCallEntry;
+use crate::host::ForeignInst;
+use crate::host::ForeignInst::{
+    KeccakMerkleAddress, KeccakMerkleGet, KeccakMerkleGetRoot, KeccakMerkleSet, KeccakMerkleSetRoot,
+};
+use crate::utils::bytes_to_u64;
+use crate::utils::field_to_u64;
+use crate::utils::Limb;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Layouter, Region};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::Advice;
+use halo2_proofs::plonk::Column;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::plonk::Error;
+
+/* The calling convention follows the poseidon merkle tree with its own opcodes
+ * KeccakMerkleAddress
+ * KeccakMerkleSetRoot
+ * KeccakMerkleSet / KeccakMerkleGet
+ * KeccakMerkleGetRoot
+ * but the roots and the value are kept as four u64 lanes instead of being merged.
+ */
+
+// 0: address 1-4: root 5-8: value 9-12: new root 13: set/get
+const ARGS_SIZE: usize = CHUNK_SIZE + 1;
+
+fn lanes_to_args(lanes: &[u64; 4], op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+    lanes
+        .iter()
+        .map(|x| ExternalHostCallEntry {
+            op: op as usize,
+            value: *x,
+            is_ret: false,
+        })
+        .collect()
+}
+
+/// Host calls of (address, root, new_root, value, set/get) where the roots are keccak digests
+/// and the value is the 32 bytes data of the leaf.
+pub fn kvpair_to_host_call_table(
+    inputs: &Vec<(u64, [u8; 32], [u8; 32], [u8; 32], ForeignInst)>,
+) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for (addr, root, new_root, value, op) in inputs.into_iter() {
+        r.push(vec![ExternalHostCallEntry {
+            op: KeccakMerkleAddress as usize,
+            value: *addr,
+            is_ret: false,
+        }]);
+        r.push(lanes_to_args(&bytes_to_u64(root), KeccakMerkleSetRoot));
+        r.push(lanes_to_args(&bytes_to_u64(value), *op));
+        r.push(lanes_to_args(&bytes_to_u64(new_root), KeccakMerkleGetRoot));
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+fn lanes_to_bytes(lanes: &[Limb<Fr>]) -> [u8; 32] {
+    lanes
+        .iter()
+        .flat_map(|x| field_to_u64(&x.value).to_le_bytes())
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap()
+}
+
+impl<const DEPTH: usize> HostOpSelector for KeccakMerkleChip<Fr, DEPTH> {
+    type Config = KeccakGateConfig;
+    type Helper = Option<Rc<RefCell<dyn TreeDB>>>; // known tree db if provided
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+        shared_advices: &Vec<Column<Advice>>,
+    ) -> Self::Config {
+        KeccakMerkleChip::<Fr, DEPTH>::configure(meta, shared_advices)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        KeccakMerkleChip::new(c)
+    }
+
+    fn max_rounds(k: usize) -> usize {
+        // each round hashes the leaf and the DEPTH nodes of its path
+        super::get_max_round(k, TOTAL_PERMUTATIONS / (DEPTH + 1))
+    }
+
+    fn chunk_size() -> usize {
+        CHUNK_SIZE
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // a set reuses the assist path of the get before it
+        round[5].op == KeccakMerkleGet as usize
+    }
+
+    fn opcodes() -> Vec<Fr> {
+        vec![
+            Fr::from(KeccakMerkleSetRoot as u64),
+            Fr::from(KeccakMerkleGetRoot as u64),
+            Fr::from(KeccakMerkleAddress as u64),
+            Fr::from(KeccakMerkleSet as u64),
+            Fr::from(KeccakMerkleGet as u64),
+        ]
+    }
+
+    fn assign(
+        region: &Region<Fr>,
+        k: usize,
+        offset: &mut usize,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes = Self::opcodes();
+        let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &opcodes);
+
+        let total_used_instructions = selected_entries.len() / (CHUNK_SIZE);
+
+        let mut r = vec![];
+
+        let mut assign_round = |group: &[((Fr, Fr), Fr)], enable: bool| -> Result<(), Error> {
+            let mut setget = None;
+            for (i, ((operand, opcode), index)) in group.iter().enumerate() {
+                let (limb, op) = config.assign_one_line(
+                    region,
+                    offset,
+                    *operand,
+                    *opcode,
+                    *index,
+                    *operand,   //same as operand as indicator is 0
+                    Fr::zero(), //not merged
+                    enable,
+                )?;
+                if i >= 5 && i < 9 {
+                    setget = Some(op);
+                }
+                r.push(limb);
+            }
+            r.push(setget.unwrap());
+            Ok(())
+        };
+
+        for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+            assert!(group[0].0 .1 == Fr::from(KeccakMerkleAddress as u64));
+            assign_round(group, true)?;
+        }
+
+        // a get of the default leaf that leaves the root unchanged
+        let mt = KeccakMerkle::<DEPTH>::default();
+        let default_proof = mt.default_proof();
+        let default_entries = kvpair_to_host_call_table(&vec![(
+            0,
+            default_proof.root,
+            default_proof.root,
+            [0; 32],
+            KeccakMerkleGet,
+        )])
+        .into_iter()
+        .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+        .collect::<Vec<((Fr, Fr), Fr)>>();
+
+        assert!(k >= 22);
+        let total_available = Self::max_rounds(k);
+        assert!(total_used_instructions <= total_available);
+        println!("total available instructions {}", total_available);
+
+        for _ in 0..=total_available - total_used_instructions {
+            assign_round(&default_entries, false)?;
+        }
+
+        Ok(r)
+    }
+
+    fn synthesize_separate(
+        &mut self,
+        _arg_cells: &Vec<Limb<Fr>>,
+        _layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn synthesize(
+        &mut self,
+        offset: &mut usize,
+        arg_cells: &Vec<Limb<Fr>>,
+        region: &Region<Fr>,
+        helper: &Self::Helper,
+    ) -> Result<(), Error> {
+        let default_index = 1u64 << DEPTH;
+        *offset = {
+            let config = self.config.clone();
+            let mut local_offset = *offset;
+            self.initialize(&config, region, &mut local_offset)?;
+            let mut mt: KeccakMerkle<DEPTH> = if let Some(tree_db) = helper {
+                KeccakMerkle::construct(
+                    [0u8; 32],
+                    KECCAK_DEFAULT_HASH_VEC[DEPTH],
+                    Some(tree_db.clone()),
+                )
+            } else {
+                KeccakMerkle::<DEPTH>::default()
+            };
+            let default_proof = mt.default_proof();
+
+            for args in arg_cells.chunks_exact(ARGS_SIZE) {
+                let address = &args[0];
+                let root: [Limb<Fr>; 4] = args[1..5].to_vec().try_into().unwrap();
+                let value: [Limb<Fr>; 4] = args[5..9].to_vec().try_into().unwrap();
+                let new_root: [Limb<Fr>; 4] = args[9..13].to_vec().try_into().unwrap();
+                let opcode = &args[13];
+                let addr = address.value.get_lower_128();
+                let index = (addr as u64) + default_index - 1;
+                let is_set = opcode.value == Fr::from(KeccakMerkleSet as u64);
+                let proof = if index == default_proof.index
+                    && lanes_to_bytes(&new_root) == default_proof.root
+                {
+                    default_proof.clone()
+                } else if is_set {
+                    mt.update_leaf_data_with_proof(index, &lanes_to_bytes(&value).to_vec())
+                        .expect("set leaf error")
+                } else {
+                    mt.update_root_hash(&lanes_to_bytes(&root));
+                    let (_, proof) = mt.get_leaf_with_proof(index).expect("get leaf error");
+                    proof
+                };
+
+                self.assign_proof(
+                    region,
+                    &mut local_offset,
+                    &proof,
+                    opcode,
+                    address,
+                    &root,
+                    &new_root,
+                    &value,
+                )?;
+            }
+            local_offset
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::kvpair_to_host_call_table;
+    use crate::circuits::keccakmerkle::KeccakMerkleChip;
+    use crate::host::db::{RocksDB, TreeDB};
+    use crate::host::merkle::MerkleTree;
+    use crate::host::mongomerkle::{KeccakMerkle, KECCAK_DEFAULT_HASH_VEC};
+    use crate::host::ExternalHostCallEntryTable;
+    use crate::host::ForeignInst::{KeccakMerkleGet, KeccakMerkleSet};
+    use crate::proof::{build_host_circuit, MERKLE_DEPTH};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::Fr;
+    use std::cell::RefCell;
+    use std::fs::File;
+    use std::rc::Rc;
+
+    #[test]
+    fn generate_keccak_kvpair_input_get_set() {
+        let root_default = KECCAK_DEFAULT_HASH_VEC[MERKLE_DEPTH];
+        let index = 1;
+        let address = (1_u64 << MERKLE_DEPTH as u32) - 1 + index;
+        let mut data = [0u8; 32];
+        data[1] = 0x10;
+
+        let mut mt = KeccakMerkle::<MERKLE_DEPTH>::construct([0u8; 32], root_default, None);
+        let proof = mt
+            .update_leaf_data_with_proof(address, &data.to_vec())
+            .unwrap();
+        assert!(mt.verify_proof(&proof).unwrap());
+        let new_root = mt.get_root_hash();
+
+        let default_table = kvpair_to_host_call_table(&vec![
+            (index, root_default, root_default, [0; 32], KeccakMerkleGet),
+            (index, root_default, new_root, data, KeccakMerkleSet),
+        ]);
+        assert_eq!(default_table.len(), 13 * 2);
+        let file = File::create("keccak_kvpair_test1.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table))
+            .expect("can not write to file");
+    }
+
+    #[test]
+    fn test_keccak_merkle_get_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let db: Rc<RefCell<dyn TreeDB>> = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+        let root_default = KECCAK_DEFAULT_HASH_VEC[MERKLE_DEPTH];
+        let index = 1;
+        let address = (1_u64 << MERKLE_DEPTH as u32) - 1 + index;
+        let mut data = [0u8; 32];
+        data[1] = 0x10;
+
+        let mut mt =
+            KeccakMerkle::<MERKLE_DEPTH>::construct([0u8; 32], root_default, Some(db.clone()));
+        mt.update_leaf_data_with_proof(address, &data.to_vec())
+            .unwrap();
+        let new_root = mt.get_root_hash();
+
+        let table = kvpair_to_host_call_table(&vec![
+            (index, root_default, root_default, [0; 32], KeccakMerkleGet),
+            (index, root_default, new_root, data, KeccakMerkleSet),
+            (index, new_root, new_root, data, KeccakMerkleGet),
+        ]);
+        let circuit = build_host_circuit::<KeccakMerkleChip<Fr, MERKLE_DEPTH>>(
+            &ExternalHostCallEntryTable(table),
+            22,
+            Some(db),
+        );
+        let prover = MockProver::run(22, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
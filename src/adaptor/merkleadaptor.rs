@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 //use ark_std::{end_timer, start_timer};
 use crate::adaptor::get_selected_entries;
@@ -7,11 +8,15 @@ use crate::circuits::merkle::MerkleChip;
 use crate::circuits::poseidon::PoseidonGateConfig;
 use crate::circuits::CommonGateConfig;
 use crate::host::db::TreeDB;
-use crate::host::merkle::{MerkleNode, MerkleTree};
-use crate::host::mongomerkle::{MongoMerkle, DEFAULT_HASH_VEC};
-use crate::host::ExternalHostCallEntry;
+use crate::host::merkle::{
+    verify_merkle_proof, MerkleHasher, MerkleNode, MerkleProof, MerkleTree, MerkleWitness,
+};
+use crate::host::mongomerkle::{MongoMerkle, PoseidonMerkleHasher, DEFAULT_HASH_VEC};
 use crate::host::ForeignInst;
-use crate::host::ForeignInst::{MerkleAddress, MerkleGet, MerkleGetRoot, MerkleSet, MerkleSetRoot};
+use crate::host::ForeignInst::{
+    MerkleAddress, MerkleDelete, MerkleGet, MerkleGetRoot, MerkleSet, MerkleSetRoot,
+};
+use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable};
 use crate::utils::data_to_bytes;
 use crate::utils::field_to_bytes;
 use crate::utils::Limb;
@@ -27,13 +32,14 @@ use halo2_proofs::plonk::Error;
 /* The calling convention will be
  * MerkleAddress
  * MerkleSetRoot
- * MerkleSet / MerkleGet
+ * MerkleSet / MerkleGet / MerkleDelete
  * MerkleGetRoot
+ * where the value of MerkleDelete is ignored and the leaf is reset to the default leaf.
  */
 const MERGE_SIZE: usize = 4;
 const MERGE_DATA_SIZE: usize = 2;
 // 0: set/get 1-4: root 5-8:address 9-12:value 13-16:root
-const CHUNK_SIZE: usize = 1 + 3 * MERGE_SIZE; // should equal to 13
+pub(crate) const CHUNK_SIZE: usize = 1 + 3 * MERGE_SIZE; // should equal to 13
 
 const TOTAL_CONSTRUCTIONS: usize = 1080;
 
@@ -60,9 +66,190 @@ fn kvpair_to_host_call_table(
     r.into_iter().flatten().collect::<Vec<_>>()
 }
 
+/// The selected entries of the round used to pad the unused rounds, which is
+/// a get of the default leaf that leaves the root unchanged
+pub(crate) fn merkle_default_entries<const DEPTH: usize>() -> Vec<((Fr, Fr), Fr)> {
+    let default_proof = MongoMerkle::<DEPTH>::empty_tree_proof();
+    assert!(verify_merkle_proof(
+        &default_proof,
+        PoseidonMerkleHasher::hash_node
+    ));
+
+    let default_table = kvpair_to_host_call_table(&vec![(
+        0,
+        Fr::from_repr(default_proof.root).unwrap(),
+        Fr::from_repr(default_proof.root).unwrap(),
+        [Fr::zero(), Fr::zero()],
+        MerkleGet,
+    )]);
+
+    default_table
+        .into_iter()
+        .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+        .collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+/// Assign the filtered rows of one round and return the arguments
+/// [address, root, value0, value1, new_root, set/get opcode].
+pub(crate) fn assign_merkle_round(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    let ((operand, opcode), index) = group[0].clone();
+    assert!(opcode.clone() == Fr::from(MerkleAddress as u64));
+
+    let (limb, op) = config.assign_one_line(
+        region,
+        offset,
+        operand,
+        opcode,
+        index,
+        operand,
+        Fr::zero(),
+        enable,
+    )?;
+    r.push(limb);
+
+    let mut setget = op;
+
+    // root
+    let (limb, _) = config.assign_merged_fr_operands(
+        region,
+        offset,
+        vec![&group[1], &group[2], &group[3], &group[4]],
+        enable,
+    )?;
+    r.push(limb);
+
+    // set or get
+    for subgroup in group[5..9]
+        .iter()
+        .collect::<Vec<_>>()
+        .chunks_exact(MERGE_DATA_SIZE)
+    {
+        let (limb, op) = config.assign_merged_operands(
+            region,
+            offset,
+            subgroup.to_vec(),
+            Fr::from_u128(1u128 << 64),
+            enable,
+        )?;
+        setget = op;
+        r.push(limb);
+    }
+
+    // new root
+    let (limb, _) = config.assign_merged_fr_operands(
+        region,
+        offset,
+        vec![&group[9], &group[10], &group[11], &group[12]],
+        enable,
+    )?;
+    r.push(limb);
+
+    r.push(setget);
+    Ok(r)
+}
+
+/// Replay a merkle call against the tree and return its proof, which is taken
+/// against the new root for a set or delete and against the root for a get
+fn replay_merkle_call<const DEPTH: usize>(
+    mt: &mut MongoMerkle<DEPTH>,
+    index: u64,
+    opcode: &Fr,
+    root: &Fr,
+    values: [Fr; 2],
+) -> MerkleProof<[u8; 32], DEPTH> {
+    if *opcode == Fr::from(MerkleDelete as u64) {
+        mt.delete_leaf_with_proof(index).expect("delete leaf error")
+    } else if *opcode == Fr::from(MerkleSet as u64) {
+        let (mut leaf, _) = mt.get_leaf_with_proof(index).expect("get leaf error");
+        leaf.set(&data_to_bytes(values.to_vec()).to_vec());
+        mt.set_leaf_with_proof(&leaf).expect("set leaf error")
+    } else {
+        // the case of get value:
+        // 1. load db
+        // 2. get leaf
+        mt.update_root_hash(&field_to_bytes(root));
+        let (_, proof) = mt.get_leaf_with_proof(index).expect("get leaf error");
+        proof
+    }
+}
+
+/// Produce the merkle witness of a trace by replaying its merkle host calls
+/// against the tree db, the local mongodb if not provided, and recording the
+/// proof of each get, set and delete.
+pub fn merkle_witness_of_table<const DEPTH: usize>(
+    table: &ExternalHostCallEntryTable,
+    tree_db: Option<Rc<RefCell<dyn TreeDB>>>,
+) -> MerkleWitness<DEPTH> {
+    let shared_operands = table.0.iter().map(|x| Fr::from(x.value)).collect();
+    let shared_opcodes = table.0.iter().map(|x| Fr::from(x.op as u64)).collect();
+    let opcodes = <MerkleChip<Fr, DEPTH> as HostOpSelector>::opcodes();
+    let selected_entries = get_selected_entries(&shared_operands, &shared_opcodes, &opcodes);
+
+    let mut mt = MongoMerkle::<DEPTH>::construct([0u8; 32], DEFAULT_HASH_VEC[DEPTH], tree_db);
+    let mut witness = MerkleWitness::default();
+    for group in selected_entries.chunks_exact(CHUNK_SIZE) {
+        // 0: address 1-4: root 5-8: values 9-12: new root
+        let limbs = group
+            .iter()
+            .map(|((operand, _), _)| operand.get_lower_128())
+            .collect::<Vec<_>>();
+        let index = limbs[0] as u64 + (1u64 << DEPTH) - 1;
+        let root = Fr::from_raw(
+            limbs[1..5]
+                .iter()
+                .map(|x| *x as u64)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap(),
+        );
+        let values = [
+            Fr::from_u128(limbs[5] + (limbs[6] << 64)),
+            Fr::from_u128(limbs[7] + (limbs[8] << 64)),
+        ];
+        let opcode = group[5].0 .1;
+        witness.record(replay_merkle_call(&mut mt, index, &opcode, &root, values));
+    }
+    witness
+}
+
+/// Where the merkle proofs of the rounds come from
+#[derive(Clone)]
+pub enum MerkleHelper<const DEPTH: usize> {
+    /// replay the calls against the tree db, the local mongodb if not provided
+    TreeDB(Option<Rc<RefCell<dyn TreeDB>>>),
+    /// the proofs of a merkle witness indexed by their leaf index and root
+    Witness(Rc<HashMap<(u64, [u8; 32]), MerkleProof<[u8; 32], DEPTH>>>),
+}
+
+impl<const DEPTH: usize> Default for MerkleHelper<DEPTH> {
+    fn default() -> Self {
+        MerkleHelper::TreeDB(None)
+    }
+}
+
+impl<const DEPTH: usize> MerkleHelper<DEPTH> {
+    /// The proofs are looked up by index and root instead of their position,
+    /// so the same witness serves every segment of a split trace
+    pub fn from_witness(witness: MerkleWitness<DEPTH>) -> Self {
+        let proofs = witness
+            .0
+            .into_iter()
+            .map(|proof| ((proof.index, proof.root), proof))
+            .collect();
+        MerkleHelper::Witness(Rc::new(proofs))
+    }
+}
+
 impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
     type Config = (CommonGateConfig, PoseidonGateConfig);
-    type Helper = Option<Rc<RefCell<dyn TreeDB>>>; // known tree db if provided
+    type Helper = MerkleHelper<DEPTH>;
     fn configure(
         meta: &mut ConstraintSystem<Fr>,
         shared_advices: &Vec<Column<Advice>>,
@@ -78,6 +265,15 @@ impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
         super::get_max_round(k, TOTAL_CONSTRUCTIONS)
     }
 
+    fn chunk_size() -> usize {
+        CHUNK_SIZE
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // a set reuses the assist path of the get before it
+        round[5].op == MerkleGet as usize
+    }
+
     fn opcodes() -> Vec<Fr> {
         vec![
             Fr::from(MerkleSetRoot as u64),
@@ -85,6 +281,7 @@ impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
             Fr::from(MerkleAddress as u64),
             Fr::from(MerkleSet as u64),
             Fr::from(MerkleGet as u64),
+            Fr::from(MerkleDelete as u64),
         ]
     }
 
@@ -104,83 +301,12 @@ impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
         let mut r = vec![];
 
         for group in selected_entries.chunks_exact(CHUNK_SIZE) {
-            let ((operand, opcode), index) = *group.get(0).clone().unwrap();
-            assert!(opcode.clone() == Fr::from(MerkleAddress as u64));
-
-            //println!("opcode {:?} {:?}", opcode, operand);
-
-            let (limb, op) = config.assign_one_line(
-                region,
-                offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
-                true,
-            )?;
-            //println!("detect address is {:?}", limb.value);
-            r.push(limb);
-
-            let mut setget = op;
-
-            // address
-            let (limb, _) = config.assign_merged_operands(
-                region,
-                offset,
-                vec![&group[1], &group[2], &group[3], &group[4]],
-                Fr::from_u128(1u128 << 64),
-                true,
-            )?;
-            r.push(limb);
-
-            // setget
-            for subgroup in group[5..9]
-                .into_iter()
-                .collect::<Vec<_>>()
-                .chunks_exact(MERGE_DATA_SIZE)
-            {
-                let (limb, op) = config.assign_merged_operands(
-                    region,
-                    offset,
-                    subgroup.to_vec(),
-                    Fr::from_u128(1u128 << 64),
-                    true,
-                )?;
-                setget = op;
-                r.push(limb);
-            }
-
-            // new root
-            let (limb_new_root, _) = config.assign_merged_operands(
-                region,
-                offset,
-                vec![&group[9], &group[10], &group[11], &group[12]],
-                Fr::from_u128(1u128 << 64),
-                true,
-            )?;
-            r.push(limb_new_root);
-
-            r.push(setget);
+            r.append(&mut assign_merkle_round(
+                region, offset, group, config, true,
+            )?);
         }
 
-        let mt: MongoMerkle<DEPTH> = MongoMerkle::<DEPTH>::default();
-        let default_proof = mt.default_proof();
-        assert!(mt.verify_proof(&default_proof).unwrap() == true);
-
-        let default_table = kvpair_to_host_call_table(&vec![(
-            0,
-            Fr::from_repr(default_proof.root).unwrap(),
-            Fr::from_repr(default_proof.root).unwrap(),
-            [Fr::zero(), Fr::zero()],
-            MerkleGet,
-        )]);
-
-        //let entries = default_table.
-        let default_entries: Vec<((Fr, Fr), Fr)> = default_table
-            .into_iter()
-            .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
-            .collect::<Vec<((Fr, Fr), Fr)>>();
+        let default_entries = merkle_default_entries::<DEPTH>();
 
         assert!(k >= 22);
         let total_available = Self::max_rounds(k);
@@ -188,70 +314,13 @@ impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
         println!("total available instructions {}", total_available);
 
         for _ in 0..=total_available - total_used_instructions {
-            let ((operand, opcode), index) = default_entries[0].clone();
-            assert!(opcode.clone() == Fr::from(MerkleAddress as u64));
-
-            let (limb, op) = config.assign_one_line(
-                region,
-                offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
-                false,
-            )?;
-            r.push(limb);
-
-            let mut setget = op;
-
-            let (limb, _) = config.assign_merged_operands(
-                region,
-                offset,
-                vec![
-                    &default_entries[1],
-                    &default_entries[2],
-                    &default_entries[3],
-                    &default_entries[4],
-                ],
-                Fr::from_u128(1u128 << 64),
-                false,
-            )?;
-            r.push(limb);
-
-            // set or get
-            for subgroup in default_entries[5..9]
-                .iter()
-                .collect::<Vec<_>>()
-                .chunks_exact(MERGE_DATA_SIZE)
-            {
-                let (limb, op) = config.assign_merged_operands(
-                    region,
-                    offset,
-                    subgroup.to_vec(),
-                    Fr::from_u128(1u128 << 64),
-                    false,
-                )?;
-                setget = op;
-                r.push(limb);
-            }
-
-            // new root does not change
-            let (limb, _) = config.assign_merged_operands(
+            r.append(&mut assign_merkle_round(
                 region,
                 offset,
-                vec![
-                    &default_entries[1],
-                    &default_entries[2],
-                    &default_entries[3],
-                    &default_entries[4],
-                ],
-                Fr::from_u128(1u128 << 64),
+                &default_entries,
+                config,
                 false,
-            )?;
-            r.push(limb);
-
-            r.push(setget);
+            )?);
         }
 
         Ok(r)
@@ -283,12 +352,16 @@ impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
             // 2: new_root
             // 3: value[]
             // 5: op_code
-            let mut mt: MongoMerkle<DEPTH> = if let Some(tree_db) = helper {
-                MongoMerkle::construct([0u8; 32], DEFAULT_HASH_VEC[DEPTH], Some(tree_db.clone()))
-            } else {
-                MongoMerkle::<DEPTH>::default()
+            // a merkle witness provides all the proofs, so no tree db is opened
+            let mut mt: Option<MongoMerkle<DEPTH>> = match helper {
+                MerkleHelper::TreeDB(tree_db) => Some(MongoMerkle::construct(
+                    [0u8; 32],
+                    DEFAULT_HASH_VEC[DEPTH],
+                    tree_db.clone(),
+                )),
+                MerkleHelper::Witness(_) => None,
             };
-            let default_proof = mt.default_proof();
+            let default_proof = MongoMerkle::<DEPTH>::empty_tree_proof();
 
             for args in arg_cells.chunks_exact(6) {
                 let [address, root, value0, value1, new_root, opcode] = args else { unreachable!() };
@@ -299,26 +372,35 @@ impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
                 let addr = address.value.get_lower_128();
                 let index = (addr as u64) + default_index - 1;
                 let is_set = opcode.value == Fr::from(MerkleSet as u64);
+                let is_delete = opcode.value == Fr::from(MerkleDelete as u64);
                 let proof = if index == default_proof.index
                     && field_to_bytes(&new_root.value) == default_proof.root
                 {
                     default_proof.clone()
-                } else {
-                    if is_set {
-                        //println!("op is set, process set:");
-                        let (mut leaf, _) = mt.get_leaf_with_proof(index).expect("get leaf error");
-                        leaf.set(&data_to_bytes(vec![value0.value, value1.value]).to_vec());
-                        mt.set_leaf_with_proof(&leaf).expect("set leaf error")
+                } else if let MerkleHelper::Witness(proofs) = helper {
+                    // the proof of a set or delete is taken against the new root
+                    let proof_root = if is_set || is_delete {
+                        &new_root.value
                     } else {
-                        // the case of get value:
-                        // 1. load db
-                        // 2. get leaf
-                        //println!("op is get, process get");
-                        mt.update_root_hash(&field_to_bytes(&root.value));
-                        let (_, proof) = mt.get_leaf_with_proof(index).expect("get leaf error");
-
-                        proof
-                    }
+                        &root.value
+                    };
+                    let proof = proofs
+                        .get(&(index, field_to_bytes(proof_root)))
+                        .expect("merkle witness has no proof of the call")
+                        .clone();
+                    assert!(
+                        verify_merkle_proof(&proof, PoseidonMerkleHasher::hash_node),
+                        "invalid merkle witness proof"
+                    );
+                    proof
+                } else {
+                    replay_merkle_call(
+                        mt.as_mut().unwrap(),
+                        index,
+                        &opcode.value,
+                        &root.value,
+                        [value0.value, value1.value],
+                    )
                 };
                 /*
                 println!("assist: {:?}", bytes_to_field::<Fr>(&proof.assist[0]));
@@ -343,20 +425,174 @@ impl<const DEPTH: usize> HostOpSelector for MerkleChip<Fr, DEPTH> {
     }
 }
 
+#[cfg(test)]
+impl<const DEPTH: usize> crate::circuits::host::TestRounds for MerkleChip<Fr, DEPTH> {
+    fn default_round() -> Vec<((Fr, Fr), Fr)> {
+        merkle_default_entries::<DEPTH>()
+    }
+
+    fn assign_round(
+        region: &Region<Fr>,
+        offset: &mut usize,
+        group: &[((Fr, Fr), Fr)],
+        config: &HostOpConfig,
+        enable: bool,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        assign_merkle_round(region, offset, group, config, enable)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::kvpair_to_host_call_table;
-    use crate::host::merkle::{MerkleNode, MerkleTree};
+    use super::merkle_witness_of_table;
+    use super::{kvpair_to_host_call_table, MerkleHelper, CHUNK_SIZE};
+    use crate::circuits::merkle::MerkleChip;
+    use crate::host::db::{RocksDB, TreeDB};
+    use crate::host::merkle::{MerkleNode, MerkleTree, MerkleWitness};
     use crate::host::mongomerkle::MongoMerkle;
     use crate::host::mongomerkle::DEFAULT_HASH_VEC;
     use crate::host::ExternalHostCallEntryTable;
-    use crate::host::ForeignInst::{MerkleGet, MerkleSet};
-    use crate::proof::MERKLE_DEPTH;
+    use crate::host::ForeignInst;
+    use crate::host::ForeignInst::{MerkleDelete, MerkleGet, MerkleSet};
+    use crate::proof::{build_host_circuit, MERKLE_DEPTH};
     use crate::utils::bytes_to_field;
     use crate::utils::bytes_to_u64;
+    use crate::utils::data_to_bytes;
     use crate::utils::field_to_bytes;
+    use halo2_proofs::dev::{MockProver, VerifyFailure};
     use halo2_proofs::pairing::bn256::Fr;
+    use std::cell::RefCell;
     use std::fs::File;
+    use std::rc::Rc;
+
+    #[test]
+    fn merkle_witness_lookup() {
+        let mt = MongoMerkle::<MERKLE_DEPTH>::default();
+        let proof = mt.default_proof();
+        let path = std::path::Path::new("merkle_witness_test.json");
+        MerkleWitness(vec![proof.clone()]).save(path).unwrap();
+        let witness = MerkleWitness::<MERKLE_DEPTH>::load(path).unwrap();
+        assert!(mt.verify_proof(&witness.0[0]).unwrap());
+        let MerkleHelper::Witness(proofs) = MerkleHelper::from_witness(witness) else {
+            unreachable!()
+        };
+        let found = proofs.get(&(proof.index, proof.root)).unwrap();
+        assert_eq!(found.assist, proof.assist);
+        assert_eq!(found.source, proof.source);
+    }
+
+    #[test]
+    fn test_merkle_witness_of_table() {
+        let root_default = Fr::from_raw(bytes_to_u64(&DEFAULT_HASH_VEC[MERKLE_DEPTH]));
+        let index = 1;
+        let data = Fr::from(0x1000 as u64);
+
+        let dir = tempfile::tempdir().unwrap();
+        let db: Rc<RefCell<dyn TreeDB>> = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+        let mut mt = MongoMerkle::<MERKLE_DEPTH>::construct(
+            [0u8; 32],
+            DEFAULT_HASH_VEC[MERKLE_DEPTH],
+            Some(db),
+        );
+        let address = (1_u64 << MERKLE_DEPTH as u32) - 1 + index;
+        let (mut leaf, _) = mt.get_leaf_with_proof(address).unwrap();
+        leaf.set(&data_to_bytes(vec![data, Fr::zero()]));
+        mt.set_leaf_with_proof(&leaf).unwrap();
+        let root_new = bytes_to_field(&mt.get_root_hash());
+
+        let table = ExternalHostCallEntryTable(kvpair_to_host_call_table(&vec![
+            (
+                index,
+                root_default,
+                root_default,
+                [Fr::zero(), Fr::zero()],
+                MerkleGet,
+            ),
+            (index, root_default, root_new, [data, Fr::zero()], MerkleSet),
+            (index, root_new, root_new, [data, Fr::zero()], MerkleGet),
+        ]));
+
+        // the trace producer replays the calls against its own tree db
+        let dir = tempfile::tempdir().unwrap();
+        let db: Rc<RefCell<dyn TreeDB>> = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+        let witness = merkle_witness_of_table::<MERKLE_DEPTH>(&table, Some(db));
+        assert_eq!(witness.0.len(), 3);
+        let path = dir.path().join("merkle_witness.json");
+        witness.save(&path).unwrap();
+
+        // the prover only reads the witness file
+        let witness = MerkleWitness::<MERKLE_DEPTH>::load(&path).unwrap();
+        let circuit = build_host_circuit::<MerkleChip<Fr, MERKLE_DEPTH>>(
+            &table,
+            22,
+            MerkleHelper::from_witness(witness),
+        );
+        let prover = MockProver::run(22, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    fn tree_db() -> (tempfile::TempDir, Rc<RefCell<dyn TreeDB>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let db: Rc<RefCell<dyn TreeDB>> = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+        (dir, db)
+    }
+
+    fn merkle_calls_verify(
+        calls: &Vec<(u64, Fr, Fr, [Fr; 2], ForeignInst)>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let (_dir, db) = tree_db();
+        let table = ExternalHostCallEntryTable(kvpair_to_host_call_table(calls));
+        let circuit = build_host_circuit::<MerkleChip<Fr, MERKLE_DEPTH>>(
+            &table,
+            22,
+            MerkleHelper::TreeDB(Some(db)),
+        );
+        MockProver::run(22, &circuit, vec![]).unwrap().verify()
+    }
+
+    #[test]
+    fn test_merkle_get_set_delete() {
+        let root_default = Fr::from_raw(bytes_to_u64(&DEFAULT_HASH_VEC[MERKLE_DEPTH]));
+        let index = 2;
+        let address = (1_u64 << (MERKLE_DEPTH as u32)) - 1 + index;
+        let data = Fr::from(0x1000 as u64);
+
+        let (_dir, db) = tree_db();
+        let mut mt = MongoMerkle::<MERKLE_DEPTH>::construct(
+            [0u8; 32],
+            DEFAULT_HASH_VEC[MERKLE_DEPTH],
+            Some(db),
+        );
+        let (mut leaf, _) = mt.get_leaf_with_proof(address).unwrap();
+        leaf.set(&data_to_bytes(vec![data, Fr::zero()]));
+        mt.set_leaf_with_proof(&leaf).unwrap();
+        let root_new = bytes_to_field(&mt.get_root_hash());
+
+        let calls = |deleted_root| {
+            vec![
+                (
+                    index,
+                    root_default,
+                    root_default,
+                    [Fr::zero(), Fr::zero()],
+                    MerkleGet,
+                ),
+                (index, root_default, root_new, [data, Fr::zero()], MerkleSet),
+                (index, root_new, root_new, [data, Fr::zero()], MerkleGet),
+                (
+                    index,
+                    root_new,
+                    deleted_root,
+                    [Fr::zero(), Fr::zero()],
+                    MerkleDelete,
+                ),
+            ]
+        };
+        // deleting the only leaf restores the root of the empty tree
+        assert_eq!(merkle_calls_verify(&calls(root_default)), Ok(()));
+        // a delete that claims to keep the data of the leaf
+        assert!(merkle_calls_verify(&calls(root_new)).is_err());
+    }
 
     #[test]
     fn generate_kvpair_input_get_set() {
@@ -446,4 +682,53 @@ mod tests {
         serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table))
             .expect("can not write to file");
     }
+
+    #[test]
+    fn generate_kvpair_input_set_delete() {
+        let root_default = Fr::from_raw(bytes_to_u64(&DEFAULT_HASH_VEC[MERKLE_DEPTH]));
+        let index = 2;
+        let address = (1_u64 << (MERKLE_DEPTH as u32)) - 1 + index;
+        let data = Fr::from(0x1000 as u64);
+
+        let mut mt = MongoMerkle::<MERKLE_DEPTH>::construct(
+            [0u8; 32],
+            DEFAULT_HASH_VEC[MERKLE_DEPTH].clone(),
+            None,
+        );
+        mt.update_leaf_data_with_proof(address, &field_to_bytes(&data).to_vec())
+            .unwrap();
+        let root64_new = bytes_to_field(&mt.get_root_hash());
+        let proof = mt.delete_leaf_with_proof(address).unwrap();
+        assert!(mt.verify_proof(&proof).unwrap());
+        assert_eq!(mt.get_root_hash(), DEFAULT_HASH_VEC[MERKLE_DEPTH]);
+
+        let default_table = kvpair_to_host_call_table(&vec![
+            (
+                index,
+                root_default,
+                root_default,
+                [Fr::zero(), Fr::zero()],
+                MerkleGet,
+            ),
+            (
+                index,
+                root_default,
+                root64_new,
+                [data, Fr::zero()],
+                MerkleSet,
+            ),
+            (index, root64_new, root64_new, [data, Fr::zero()], MerkleGet),
+            (
+                index,
+                root64_new,
+                root_default,
+                [Fr::zero(), Fr::zero()],
+                MerkleDelete,
+            ),
+        ]);
+        assert_eq!(default_table.len(), CHUNK_SIZE * 4);
+        let file = File::create("kvpair_test3.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table))
+            .expect("can not write to file");
+    }
 }
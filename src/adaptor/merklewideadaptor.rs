@@ -0,0 +1,406 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::adaptor::get_selected_entries;
+use crate::circuits::host::{HostOpConfig, HostOpSelector};
+use crate::circuits::merklewide::MerkleWideChip;
+use crate::circuits::poseidon::PoseidonGateConfig;
+use crate::circuits::CommonGateConfig;
+use crate::host::db::TreeDB;
+use crate::host::merkle::MerkleTree;
+use crate::host::mongomerkle::WideMerkle;
+use crate::host::ExternalHostCallEntry;
+use crate::host::ForeignInst;
+use crate::host::ForeignInst::{
+    MerkleWideAddress, MerkleWideGet, MerkleWideGetRoot, MerkleWideSet, MerkleWideSetRoot,
+};
+use crate::utils::field_to_bytes;
+use crate::utils::Limb;
+use ff::PrimeField;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Layouter, Region};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::Advice;
+use halo2_proofs::plonk::Column;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::plonk::Error;
+
+/* The calling convention will be
+ * MerkleWideAddress
+ * MerkleWideSetRoot
+ * MerkleWideSet / MerkleWideGet for each of the N words of the leaf
+ * MerkleWideGetRoot
+ * where each word of the leaf is merged from two u64 limbs like the value of MerkleChip.
+ */
+const MERGE_SIZE: usize = 4;
+const MERGE_DATA_SIZE: usize = 2;
+
+/// 0: address 1-4: root 5..5+2N: words 5+2N..9+2N: new root
+pub const fn wide_chunk_size(n: usize) -> usize {
+    1 + 2 * MERGE_SIZE + n * MERGE_DATA_SIZE
+}
+
+// A leaf of 8 words takes two width 9 permutations of about 1.2k rows instead
+// of one width 3 permutation, so a round takes about 6k rows and the budget is
+// checked by stats::tests::test_merkle_wide_stats.
+pub(crate) const TOTAL_CONSTRUCTIONS: usize = 640;
+
+/// Host calls of (address, root, new_root, words, set/get) of a tree whose leaves are N words.
+pub fn wide_kvpair_to_host_call_table<const N: usize>(
+    inputs: &Vec<(u64, Fr, Fr, [Fr; N], ForeignInst)>,
+) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    for (addr, root, new_root, values, op) in inputs.into_iter() {
+        r.push(vec![ExternalHostCallEntry {
+            op: MerkleWideAddress as usize,
+            value: *addr,
+            is_ret: false,
+        }]);
+        r.push(crate::adaptor::fr_to_args(*root, 4, 64, MerkleWideSetRoot));
+        for v in values.iter() {
+            r.push(crate::adaptor::fr_to_args(*v, 2, 64, *op));
+        }
+        r.push(crate::adaptor::fr_to_args(
+            *new_root,
+            4,
+            64,
+            MerkleWideGetRoot,
+        ));
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+/// A get of the default leaf that leaves the root unchanged
+fn wide_default_entries<const DEPTH: usize, const N: usize>() -> Vec<((Fr, Fr), Fr)> {
+    let mt = WideMerkle::<DEPTH, N>::default();
+    let default_proof = mt.default_proof();
+    assert!(mt.verify_proof(&default_proof).unwrap() == true);
+
+    wide_kvpair_to_host_call_table(&vec![(
+        0,
+        Fr::from_repr(default_proof.root).unwrap(),
+        Fr::from_repr(default_proof.root).unwrap(),
+        [Fr::zero(); N],
+        MerkleWideGet,
+    )])
+    .into_iter()
+    .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+    .collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+/// Assign the filtered rows of one round and return the arguments
+/// [address, root, words, new_root, set/get opcode].
+fn assign_wide_round<const N: usize>(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    let ((operand, opcode), index) = group[0].clone();
+    assert!(opcode.clone() == Fr::from(MerkleWideAddress as u64));
+
+    let (limb, mut setget) = config.assign_one_line(
+        region,
+        offset,
+        operand,
+        opcode,
+        index,
+        operand,
+        Fr::zero(),
+        enable,
+    )?;
+    r.push(limb);
+
+    // root
+    let (limb, _) =
+        config.assign_merged_fr_operands(region, offset, group[1..5].iter().collect(), enable)?;
+    r.push(limb);
+
+    // words of set or get
+    let words_end = 5 + N * MERGE_DATA_SIZE;
+    for subgroup in group[5..words_end]
+        .iter()
+        .collect::<Vec<_>>()
+        .chunks_exact(MERGE_DATA_SIZE)
+    {
+        let (limb, op) = config.assign_merged_operands(
+            region,
+            offset,
+            subgroup.to_vec(),
+            Fr::from_u128(1u128 << 64),
+            enable,
+        )?;
+        setget = op;
+        r.push(limb);
+    }
+
+    // new root
+    let (limb, _) = config.assign_merged_fr_operands(
+        region,
+        offset,
+        group[words_end..words_end + MERGE_SIZE].iter().collect(),
+        enable,
+    )?;
+    r.push(limb);
+
+    r.push(setget);
+    Ok(r)
+}
+
+impl<const DEPTH: usize, const N: usize> HostOpSelector for MerkleWideChip<Fr, DEPTH, N> {
+    type Config = (CommonGateConfig, PoseidonGateConfig);
+    type Helper = Option<Rc<RefCell<dyn TreeDB>>>; // known tree db if provided
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+        shared_advices: &Vec<Column<Advice>>,
+    ) -> Self::Config {
+        MerkleWideChip::<Fr, DEPTH, N>::configure(meta, shared_advices)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        MerkleWideChip::new(c.0, c.1)
+    }
+
+    fn max_rounds(k: usize) -> usize {
+        super::get_max_round(k, TOTAL_CONSTRUCTIONS)
+    }
+
+    fn chunk_size() -> usize {
+        wide_chunk_size(N)
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // a set reuses the assist path of the get before it
+        round[5].op == MerkleWideGet as usize
+    }
+
+    fn opcodes() -> Vec<Fr> {
+        vec![
+            Fr::from(MerkleWideSetRoot as u64),
+            Fr::from(MerkleWideGetRoot as u64),
+            Fr::from(MerkleWideAddress as u64),
+            Fr::from(MerkleWideSet as u64),
+            Fr::from(MerkleWideGet as u64),
+        ]
+    }
+
+    fn assign(
+        region: &Region<Fr>,
+        k: usize,
+        offset: &mut usize,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes = Self::opcodes();
+        let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &opcodes);
+
+        let total_used_instructions = selected_entries.len() / wide_chunk_size(N);
+
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(wide_chunk_size(N)) {
+            r.append(&mut assign_wide_round::<N>(
+                region, offset, group, config, true,
+            )?);
+        }
+
+        let default_entries = wide_default_entries::<DEPTH, N>();
+
+        assert!(k >= 22);
+        let total_available = Self::max_rounds(k);
+        assert!(total_used_instructions <= total_available);
+        println!("total available instructions {}", total_available);
+
+        for _ in 0..=total_available - total_used_instructions {
+            r.append(&mut assign_wide_round::<N>(
+                region,
+                offset,
+                &default_entries,
+                config,
+                false,
+            )?);
+        }
+
+        Ok(r)
+    }
+
+    fn synthesize_separate(
+        &mut self,
+        _arg_cells: &Vec<Limb<Fr>>,
+        _layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn synthesize(
+        &mut self,
+        offset: &mut usize,
+        arg_cells: &Vec<Limb<Fr>>,
+        region: &Region<Fr>,
+        helper: &Self::Helper,
+    ) -> Result<(), Error> {
+        let default_index = 1u64 << DEPTH;
+        *offset = {
+            let config = self.merkle_chip.config.clone();
+            let mut local_offset = *offset;
+            self.initialize(&config, region, &mut local_offset)?;
+            let mut mt = if let Some(tree_db) = helper {
+                let default_root = WideMerkle::<DEPTH, N>::default().get_root_hash();
+                WideMerkle::<DEPTH, N>::construct([0u8; 32], default_root, Some(tree_db.clone()))
+            } else {
+                WideMerkle::<DEPTH, N>::default()
+            };
+            let default_proof = mt.default_proof();
+
+            for args in arg_cells.chunks_exact(N + 4) {
+                let address = &args[0];
+                let root = &args[1];
+                let values: [Limb<Fr>; N] = args[2..2 + N].to_vec().try_into().unwrap();
+                let new_root = &args[2 + N];
+                let opcode = &args[3 + N];
+                let addr = address.value.get_lower_128();
+                let index = (addr as u64) + default_index - 1;
+                let is_set = opcode.value == Fr::from(MerkleWideSet as u64);
+                let proof = if index == default_proof.index
+                    && field_to_bytes(&new_root.value) == default_proof.root
+                {
+                    default_proof.clone()
+                } else if is_set {
+                    let data = values
+                        .iter()
+                        .flat_map(|x| field_to_bytes(&x.value))
+                        .collect::<Vec<u8>>();
+                    mt.update_leaf_data_with_proof(index, &data)
+                        .expect("set leaf error")
+                } else {
+                    mt.update_root_hash(&field_to_bytes(&root.value));
+                    let (_, proof) = mt.get_leaf_with_proof(index).expect("get leaf error");
+                    proof
+                };
+
+                self.assign_wide_proof(
+                    region,
+                    &mut local_offset,
+                    &proof,
+                    opcode,
+                    address,
+                    root,
+                    new_root,
+                    &values,
+                )?;
+            }
+            local_offset
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wide_chunk_size, wide_kvpair_to_host_call_table};
+    use crate::circuits::merklewide::MerkleWideChip;
+    use crate::host::db::{RocksDB, TreeDB};
+    use crate::host::merkle::MerkleTree;
+    use crate::host::mongomerkle::WideMerkle;
+    use crate::host::ExternalHostCallEntryTable;
+    use crate::host::ForeignInst::{MerkleWideGet, MerkleWideSet};
+    use crate::proof::{build_host_circuit, MERKLE_DEPTH, MERKLE_WIDE_LEAF_WORDS};
+    use crate::utils::bytes_to_field;
+    use crate::utils::field_to_bytes;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::Fr;
+    use std::cell::RefCell;
+    use std::fs::File;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_wide_merkle_get_set() {
+        // distinct words so that the leaf hash of the circuit, which is asserted
+        // to be the source of the host proof, agrees with the host hasher
+        let words: [Fr; MERKLE_WIDE_LEAF_WORDS] = (0..MERKLE_WIDE_LEAF_WORDS)
+            .map(|i| Fr::from(0x1000 + i as u64))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let db: Rc<RefCell<dyn TreeDB>> = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+        let root_default =
+            WideMerkle::<MERKLE_DEPTH, MERKLE_WIDE_LEAF_WORDS>::default().get_root_hash();
+        let mut mt = WideMerkle::<MERKLE_DEPTH, MERKLE_WIDE_LEAF_WORDS>::construct(
+            [0u8; 32],
+            root_default,
+            Some(db.clone()),
+        );
+        let index = 1;
+        let address = (1_u64 << MERKLE_DEPTH as u32) - 1 + index;
+        let data = words
+            .iter()
+            .flat_map(|x| field_to_bytes(x))
+            .collect::<Vec<u8>>();
+        mt.update_leaf_data_with_proof(address, &data).unwrap();
+        let root_default = bytes_to_field(&root_default);
+        let new_root = bytes_to_field(&mt.get_root_hash());
+
+        let table = wide_kvpair_to_host_call_table(&vec![
+            (
+                index,
+                root_default,
+                root_default,
+                [Fr::zero(); MERKLE_WIDE_LEAF_WORDS],
+                MerkleWideGet,
+            ),
+            (index, root_default, new_root, words, MerkleWideSet),
+            (index, new_root, new_root, words, MerkleWideGet),
+        ]);
+        let circuit = build_host_circuit::<MerkleWideChip<Fr, MERKLE_DEPTH, MERKLE_WIDE_LEAF_WORDS>>(
+            &ExternalHostCallEntryTable(table),
+            22,
+            Some(db),
+        );
+        let prover = MockProver::run(22, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn generate_wide_kvpair_input_get_set() {
+        let mut mt = WideMerkle::<MERKLE_DEPTH, MERKLE_WIDE_LEAF_WORDS>::default();
+        let root_default = mt.get_root_hash();
+        let index = 1;
+        let address = (1_u64 << MERKLE_DEPTH as u32) - 1 + index;
+        let words = [0; MERKLE_WIDE_LEAF_WORDS].map(|_| Fr::from(0x1000 + index));
+        let data = words
+            .iter()
+            .flat_map(|x| field_to_bytes(x))
+            .collect::<Vec<u8>>();
+        let proof = mt.update_leaf_data_with_proof(address, &data).unwrap();
+        assert!(mt.verify_proof(&proof).unwrap());
+        let new_root = mt.get_root_hash();
+
+        let default_table = wide_kvpair_to_host_call_table(&vec![
+            (
+                index,
+                bytes_to_field(&root_default),
+                bytes_to_field(&root_default),
+                [Fr::zero(); MERKLE_WIDE_LEAF_WORDS],
+                MerkleWideGet,
+            ),
+            (
+                index,
+                bytes_to_field(&root_default),
+                bytes_to_field(&new_root),
+                words,
+                MerkleWideSet,
+            ),
+        ]);
+        assert_eq!(
+            default_table.len(),
+            wide_chunk_size(MERKLE_WIDE_LEAF_WORDS) * 2
+        );
+        let file = File::create("wide_kvpair_test1.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table))
+            .expect("can not write to file");
+    }
+}
@@ -6,9 +6,13 @@ use num_bigint::BigUint;
 
 // pub mod bls381adaptor;
 pub mod bn256adaptor;
+pub mod combinedadaptor;
 pub mod hashadaptor;
+pub mod jubjubverifyadaptor;
 pub mod keccakadaptor;
+pub mod keccakmerkleadaptor;
 pub mod merkleadaptor;
+pub mod merklewideadaptor;
 pub mod msmadaptor;
 
 pub fn get_max_round(k: usize, reference_max: usize) -> usize {
@@ -1,11 +1,15 @@
 use crate::adaptor::field_to_bn;
 use crate::adaptor::get_selected_entries;
-use crate::circuits::babyjub::{AltJubChip, Point as CircuitPoint};
+use crate::circuits::babyjub::{AltJubBaseChip, AltJubChip, Point as CircuitPoint};
 use crate::circuits::host::{HostOpConfig, HostOpSelector};
 use crate::circuits::CommonGateConfig;
-use crate::host::jubjub::Point;
+use crate::host::jubjub::{mul_base, Point};
 use crate::host::ExternalHostCallEntry;
-use crate::host::ForeignInst::{JubjubSumNew, JubjubSumPush, JubjubSumResult};
+use crate::host::ForeignInst;
+use crate::host::ForeignInst::{
+    JubjubBaseSumNew, JubjubBaseSumPush, JubjubBaseSumResult, JubjubSumNew, JubjubSumPush,
+    JubjubSumResult,
+};
 use crate::utils::Limb;
 use ark_std::{end_timer, start_timer};
 use halo2_proofs::arithmetic::FieldExt;
@@ -15,13 +19,24 @@ use halo2_proofs::plonk::ConstraintSystem;
 use halo2_proofs::plonk::{Advice, Column, Error};
 
 const MERGE_SIZE: usize = 4;
-const CHUNK_SIZE: usize = 1 + (2 + 1 + 2) * MERGE_SIZE;
+pub(crate) const CHUNK_SIZE: usize = 1 + (2 + 1 + 2) * MERGE_SIZE;
 
 const TOTAL_CONSTRUCTIONS: usize = 600;
 
-fn msm_new(restart: bool) -> Vec<ExternalHostCallEntry> {
+/* The sums of the multiples of BASE take the fixed base multiplication,
+ * JubjubBaseSumNew: restart
+ * JubjubBaseSumPush: scalar
+ * JubjubBaseSumResult: result.x, result.y
+ * and a round takes about 2.3k rows.
+ */
+pub(crate) const BASE_CHUNK_SIZE: usize = 1 + (1 + 2) * MERGE_SIZE;
+
+pub(crate) const TOTAL_BASE_CONSTRUCTIONS: usize = 1700;
+
+/// The operand of JubjubSumNew and JubjubBaseSumNew is the restart flag
+fn msm_new(op: ForeignInst, restart: bool) -> Vec<ExternalHostCallEntry> {
     vec![ExternalHostCallEntry {
-        op: JubjubSumNew as usize,
+        op: op as usize,
         value: if restart { 1u64 } else { 0u64 },
         is_ret: false,
     }]
@@ -32,7 +47,9 @@ fn msm_to_host_call_table<F: FieldExt>(inputs: &Vec<(Point, F)>) -> Vec<External
     let mut start = true;
     let mut result = Point::identity();
     for (p, c) in inputs.into_iter() {
-        r.push(msm_new(start));
+        p.validate(cfg!(feature = "jubjub-subgroup"))
+            .expect("invalid jubjub point");
+        r.push(msm_new(JubjubSumNew, start));
         r.push(crate::adaptor::fr_to_args(p.x, 4, 64, JubjubSumPush));
         r.push(crate::adaptor::fr_to_args(p.y, 4, 64, JubjubSumPush));
         r.push(crate::adaptor::fr_to_args(*c, 4, 64, JubjubSumPush));
@@ -44,6 +61,97 @@ fn msm_to_host_call_table<F: FieldExt>(inputs: &Vec<(Point, F)>) -> Vec<External
     r.into_iter().flatten().collect::<Vec<_>>()
 }
 
+/// Host calls of the sum of scalars[i].BASE
+pub fn msm_base_to_host_call_table<F: FieldExt>(scalars: &Vec<F>) -> Vec<ExternalHostCallEntry> {
+    let mut r = vec![];
+    let mut start = true;
+    let mut result = Point::identity();
+    for c in scalars.into_iter() {
+        r.push(msm_new(JubjubBaseSumNew, start));
+        r.push(crate::adaptor::fr_to_args(*c, 4, 64, JubjubBaseSumPush));
+        result = result.add(&mul_base(&field_to_bn(c)));
+        r.push(crate::adaptor::fr_to_args(
+            result.x,
+            4,
+            64,
+            JubjubBaseSumResult,
+        ));
+        r.push(crate::adaptor::fr_to_args(
+            result.y,
+            4,
+            64,
+            JubjubBaseSumResult,
+        ));
+        start = false;
+    }
+    r.into_iter().flatten().collect::<Vec<_>>()
+}
+
+/// The selected entries of the round used to pad the unused rounds
+pub(crate) fn msm_default_entries() -> Vec<((Fr, Fr), Fr)> {
+    msm_to_host_call_table(&vec![(Point::identity(), Fr::one())])
+        .into_iter()
+        .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+        .collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+fn msm_base_default_entries() -> Vec<((Fr, Fr), Fr)> {
+    msm_base_to_host_call_table(&vec![Fr::zero()])
+        .into_iter()
+        .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
+        .collect::<Vec<((Fr, Fr), Fr)>>()
+}
+
+/// Assign the filtered rows of one round: the JubjubSumNew line followed by
+/// the merged point, scalar and result.
+pub(crate) fn assign_msm_round(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    assign_sum_round(region, offset, group, config, enable, JubjubSumNew)
+}
+
+/// Assign the filtered rows of one round of the op starting with `new`
+fn assign_sum_round(
+    region: &Region<Fr>,
+    offset: &mut usize,
+    group: &[((Fr, Fr), Fr)],
+    config: &HostOpConfig,
+    enable: bool,
+    new: ForeignInst,
+) -> Result<Vec<Limb<Fr>>, Error> {
+    let mut r = vec![];
+    let ((operand, opcode), index) = group[0].clone();
+    assert!(opcode.clone() == Fr::from(new as u64));
+
+    let (limb, _) = config.assign_one_line(
+        region,
+        offset,
+        operand,
+        opcode,
+        index,
+        operand,
+        Fr::zero(),
+        enable,
+    )?;
+    r.push(limb);
+
+    for subgroup in group
+        .iter()
+        .skip(1)
+        .collect::<Vec<_>>()
+        .chunks_exact(MERGE_SIZE)
+    {
+        let (limb, _) =
+            config.assign_merged_fr_operands(region, offset, subgroup.to_vec(), enable)?;
+        r.push(limb);
+    }
+    Ok(r)
+}
+
 impl HostOpSelector for AltJubChip<Fr> {
     type Config = CommonGateConfig;
     type Helper = ();
@@ -62,6 +170,15 @@ impl HostOpSelector for AltJubChip<Fr> {
         super::get_max_round(k, TOTAL_CONSTRUCTIONS)
     }
 
+    fn chunk_size() -> usize {
+        CHUNK_SIZE
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // JubjubSumNew with value 1 resets the accumulator
+        round[0].value == 1
+    }
+
     fn opcodes() -> Vec<Fr> {
         vec![
             Fr::from(JubjubSumNew as u64),
@@ -86,82 +203,23 @@ impl HostOpSelector for AltJubChip<Fr> {
         let mut r = vec![];
 
         for group in selected_entries.chunks_exact(CHUNK_SIZE) {
-            let ((operand, opcode), index) = *group.get(0).clone().unwrap();
-            assert!(opcode.clone() == Fr::from(JubjubSumNew as u64));
-
-            let (limb, _) = config.assign_one_line(
-                region,
-                offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
-                true,
-            )?;
-            r.push(limb);
-
-            for subgroup in group
-                .into_iter()
-                .skip(1)
-                .collect::<Vec<_>>()
-                .chunks_exact(MERGE_SIZE)
-            {
-                let (limb, _) = config.assign_merged_operands(
-                    region,
-                    offset,
-                    subgroup.to_vec(),
-                    Fr::from_u128(1u128 << 64),
-                    true,
-                )?;
-                r.push(limb);
-            }
+            r.append(&mut assign_msm_round(region, offset, group, config, true)?);
         }
 
-        let default_table = msm_to_host_call_table(&vec![(Point::identity(), Fr::one())]);
-
-        //let entries = default_table.
-        let default_entries: Vec<((Fr, Fr), Fr)> = default_table
-            .into_iter()
-            .map(|x| ((Fr::from(x.value), Fr::from(x.op as u64)), Fr::zero()))
-            .collect::<Vec<((Fr, Fr), Fr)>>();
+        let default_entries = msm_default_entries();
 
         assert!(k >= 22);
         let total_available = Self::max_rounds(k);
         assert!(total_used_instructions <= total_available);
 
         for _ in 0..=total_available - total_used_instructions {
-            let ((operand, opcode), index) = default_entries[0].clone();
-            assert!(opcode.clone() == Fr::from(JubjubSumNew as u64));
-
-            let (limb, _) = config.assign_one_line(
+            r.append(&mut assign_msm_round(
                 region,
                 offset,
-                operand,
-                opcode,
-                index,
-                operand,
-                Fr::zero(),
+                &default_entries,
+                config,
                 false,
-            )?;
-            r.push(limb);
-
-            for subgroup in default_entries
-                .clone()
-                .iter()
-                .skip(1)
-                .collect::<Vec<_>>()
-                .chunks_exact(MERGE_SIZE)
-            {
-                let (limb, _) = config.assign_merged_operands(
-                    region,
-                    offset,
-                    subgroup.to_vec(),
-                    Fr::from_u128(1u128 << 64),
-                    false,
-                )?;
-                r.push(limb);
-            }
+            )?);
         }
 
         Ok(r)
@@ -193,13 +251,17 @@ impl HostOpSelector for AltJubChip<Fr> {
             for arg_group in arg_cells.chunks_exact(6).into_iter() {
                 let args = arg_group.into_iter().map(|x| x.clone());
                 let args = args.collect::<Vec<_>>();
-                self.assign_incremental_msm(
+                let point = CircuitPoint {
+                    x: args[1].clone(),
+                    y: args[2].clone(),
+                };
+                if cfg!(feature = "jubjub-subgroup") {
+                    self.assert_in_subgroup(region, &mut local_offset, &point)?;
+                }
+                self.assign_jubjub_sum(
                     region,
                     &mut local_offset,
-                    &CircuitPoint {
-                        x: args[1].clone(),
-                        y: args[2].clone(),
-                    },
+                    &point,
                     &args[3],
                     &args[0],
                     &CircuitPoint {
@@ -215,11 +277,133 @@ impl HostOpSelector for AltJubChip<Fr> {
     }
 }
 
+impl HostOpSelector for AltJubBaseChip {
+    type Config = CommonGateConfig;
+    type Helper = ();
+    fn configure(
+        meta: &mut ConstraintSystem<Fr>,
+        shared_advice: &Vec<Column<Advice>>,
+    ) -> Self::Config {
+        AltJubChip::<Fr>::configure(meta, shared_advice)
+    }
+
+    fn construct(c: Self::Config) -> Self {
+        AltJubBaseChip::new(c)
+    }
+
+    fn max_rounds(k: usize) -> usize {
+        super::get_max_round(k, TOTAL_BASE_CONSTRUCTIONS)
+    }
+
+    fn chunk_size() -> usize {
+        BASE_CHUNK_SIZE
+    }
+
+    fn is_fresh_round(round: &[ExternalHostCallEntry]) -> bool {
+        // JubjubBaseSumNew with value 1 resets the accumulator
+        round[0].value == 1
+    }
+
+    fn opcodes() -> Vec<Fr> {
+        vec![
+            Fr::from(JubjubBaseSumNew as u64),
+            Fr::from(JubjubBaseSumPush as u64),
+            Fr::from(JubjubBaseSumResult as u64),
+        ]
+    }
+
+    fn assign(
+        region: &Region<Fr>,
+        k: usize,
+        offset: &mut usize,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let opcodes = Self::opcodes();
+        let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &opcodes);
+        let total_used_instructions = selected_entries.len() / (BASE_CHUNK_SIZE);
+
+        let mut r = vec![];
+
+        for group in selected_entries.chunks_exact(BASE_CHUNK_SIZE) {
+            r.append(&mut assign_sum_round(
+                region,
+                offset,
+                group,
+                config,
+                true,
+                JubjubBaseSumNew,
+            )?);
+        }
+
+        let default_entries = msm_base_default_entries();
+
+        assert!(k >= 22);
+        let total_available = Self::max_rounds(k);
+        assert!(total_used_instructions <= total_available);
+
+        for _ in 0..=total_available - total_used_instructions {
+            r.append(&mut assign_sum_round(
+                region,
+                offset,
+                &default_entries,
+                config,
+                false,
+                JubjubBaseSumNew,
+            )?);
+        }
+
+        Ok(r)
+    }
+
+    fn synthesize_separate(
+        &mut self,
+        _arg_cells: &Vec<Limb<Fr>>,
+        _layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn synthesize(
+        &mut self,
+        offset: &mut usize,
+        arg_cells: &Vec<Limb<Fr>>,
+        region: &Region<Fr>,
+        _helper: &(),
+    ) -> Result<(), Error> {
+        *offset = {
+            let mut local_offset = *offset;
+            let timer = start_timer!(|| "assign");
+            let config = self.0.config.clone();
+            self.initialize(&config, region, &mut local_offset)?;
+            // arg_cells format 1 + 1 + 2
+            for args in arg_cells.chunks_exact(4) {
+                self.assign_base_sum(
+                    region,
+                    &mut local_offset,
+                    &args[1],
+                    &args[0],
+                    &CircuitPoint {
+                        x: args[2].clone(),
+                        y: args[3].clone(),
+                    },
+                )?;
+            }
+            end_timer!(timer);
+            local_offset
+        };
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::msm_to_host_call_table;
-    use crate::host::jubjub::Point;
-    use crate::host::ExternalHostCallEntryTable;
+    use super::{msm_base_to_host_call_table, msm_to_host_call_table};
+    use crate::host::jubjub::{Point, BASE};
+    use crate::host::ForeignInst;
+    use crate::host::ForeignInst::{JubjubBaseSumNew, JubjubBaseSumResult, JubjubSumResult};
+    use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable};
     use halo2_proofs::pairing::bn256::Fr;
     use std::fs::File;
 
@@ -238,4 +422,35 @@ mod tests {
         serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(default_table))
             .expect("can not write to file");
     }
+
+    #[test]
+    fn generate_jubjub_base_sum_input() {
+        let scalars = vec![Fr::from(3u64), -Fr::from(5u64)];
+        let table = msm_base_to_host_call_table(&scalars);
+        assert_eq!(table.len(), super::BASE_CHUNK_SIZE * 2);
+        // restart then continue the sum
+        let news = table
+            .iter()
+            .filter(|x| x.op == JubjubBaseSumNew as usize)
+            .map(|x| x.value)
+            .collect::<Vec<_>>();
+        assert_eq!(news, vec![1, 0]);
+        // the same sum as the generic rounds of the point BASE
+        let generic =
+            msm_to_host_call_table(&scalars.iter().map(|c| (BASE.clone(), c.clone())).collect());
+        let results = |table: &Vec<ExternalHostCallEntry>, op: ForeignInst| {
+            table
+                .iter()
+                .filter(|x| x.op == op as usize)
+                .map(|x| x.value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            results(&table, JubjubBaseSumResult),
+            results(&generic, JubjubSumResult)
+        );
+        let file = File::create("jubjub_base_sum.json").expect("can not create file");
+        serde_json::to_writer_pretty(file, &ExternalHostCallEntryTable(table))
+            .expect("can not write to file");
+    }
 }
@@ -1,12 +1,42 @@
 use crate::circuits::{CommonGateConfig, Limb};
+use crate::host::jubjub::Point as HostPoint;
+use crate::host::jubjub::{BASE_WINDOW_BITS, FIXED_BASE_TABLE};
 
 use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{Chip, Region},
+    pairing::bn256::Fr,
     plonk::{Advice, Column, ConstraintSystem, Error},
 };
 use std::marker::PhantomData;
 
+lazy_static::lazy_static! {
+    /// The coefficients of the multilinear polynomials in the bits of a window which
+    /// interpolate the x and y coordinates of the entries of FIXED_BASE_TABLE
+    static ref FIXED_BASE_COEFFS: Vec<[Vec<Fr>; 2]> = FIXED_BASE_TABLE
+        .iter()
+        .map(|window| {
+            [
+                window_coeffs(window.iter().map(|p| p.x).collect()),
+                window_coeffs(window.iter().map(|p| p.y).collect()),
+            ]
+        })
+        .collect();
+}
+
+/// Coefficient c_s of the monomial of the bits set in s so that
+/// values[j] = sum of c_s for the subsets s of the bits of j.
+fn window_coeffs(mut values: Vec<Fr>) -> Vec<Fr> {
+    for k in 0..BASE_WINDOW_BITS {
+        for s in 0..values.len() {
+            if s & (1 << k) != 0 {
+                values[s] = values[s] - values[s ^ (1 << k)];
+            }
+        }
+    }
+    values
+}
+
 pub struct AltJubChip<F: FieldExt> {
     pub config: CommonGateConfig,
     state: JubState<F>,
@@ -110,6 +140,21 @@ impl<F: FieldExt> AltJubChip<F> {
         scalar: &Limb<F>,
         reset: &Limb<F>,
         result: &Point<F>,
+    ) -> Result<(), Error> {
+        self.assert_on_curve(region, offset, point)?;
+        let operand = self.mul_scalar(region, offset, scalar, point)?;
+        self.accumulate(region, offset, &operand, reset, result)
+    }
+
+    /// Add the operand to the accumulator, which restarts from the identity
+    /// when reset is set, and constrain the new accumulator to be the result
+    fn accumulate(
+        &mut self,
+        region: &Region<F>,
+        offset: &mut usize,
+        operand: &Point<F>,
+        reset: &Limb<F>,
+        result: &Point<F>,
     ) -> Result<(), Error> {
         self.state.acc.x = self.config.select(
             region,
@@ -131,9 +176,7 @@ impl<F: FieldExt> AltJubChip<F> {
             0,
         )?;
 
-        let operand = self.mul_scalar(region, offset, scalar, point)?;
-
-        let acc = self.add(region, offset, &self.state.acc, &operand)?;
+        let acc = self.add(region, offset, &self.state.acc, operand)?;
 
         self.state.acc = acc;
         region.constrain_equal(
@@ -148,6 +191,83 @@ impl<F: FieldExt> AltJubChip<F> {
         Ok(())
     }
 
+    /// Constrain a.x^2 + y^2 = 1 + d.x^2.y^2, the chip must be initialized
+    pub fn assert_on_curve(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        point: &Point<F>,
+    ) -> Result<(), Error> {
+        let mut squares = vec![];
+        for v in [&point.x, &point.y] {
+            squares.push(
+                self.config.assign_line(
+                    region,
+                    &mut (),
+                    offset,
+                    [
+                        Some(v.clone()),
+                        None,
+                        None,
+                        Some(v.clone()),
+                        Some(Limb::new(None, v.value * v.value)),
+                        None,
+                    ],
+                    [
+                        None,
+                        None,
+                        None,
+                        None,
+                        Some(-F::one()),
+                        None,
+                        Some(F::one()),
+                        None,
+                        None,
+                    ],
+                    0,
+                )?[2]
+                    .clone(),
+            );
+        }
+        let [x2, y2]: [Limb<F>; 2] = squares.try_into().unwrap();
+        // the residual 1 + d.x^2.y^2 - a.x^2 - y^2 is copied to zero, so that an
+        // off curve point fails the verification instead of the witness assertion
+        let residual = F::one() + self.curve_coeff_a_neg * x2.value - y2.value
+            + self.curve_coeff_d * x2.value * y2.value;
+        // -a.x^2 = curve_coeff_a_neg.x^2
+        let residual = self.config.assign_line(
+            region,
+            &mut (),
+            offset,
+            [
+                Some(x2),
+                None,
+                None,
+                Some(y2),
+                Some(Limb::new(None, residual)),
+                None,
+            ],
+            [
+                Some(self.curve_coeff_a_neg),
+                None,
+                None,
+                Some(-F::one()),
+                Some(-F::one()),
+                None,
+                Some(self.curve_coeff_d),
+                None,
+                Some(F::one()),
+            ],
+            0,
+        )?[2]
+            .clone();
+        region.constrain_equal(
+            residual.get_the_cell().cell(),
+            self.state.identity.x.get_the_cell().cell(),
+        )?;
+        Ok(())
+    }
+
     pub fn configure(
         cs: &mut ConstraintSystem<F>,
         shared_advice: &Vec<Column<Advice>>,
@@ -576,12 +696,204 @@ impl<F: FieldExt> AltJubChip<F> {
     }
 }
 
+impl AltJubChip<Fr> {
+    /// One JubjubSumNew round of point and scalar, the operand is the restart flag
+    pub fn assign_jubjub_sum(
+        &mut self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        point: &Point<Fr>,
+        scalar: &Limb<Fr>,
+        operand: &Limb<Fr>,
+        result: &Point<Fr>,
+    ) -> Result<(), Error> {
+        let reset = self.assign_restart(region, offset, operand)?;
+        self.assign_incremental_msm(region, offset, point, scalar, &reset, result)
+    }
+
+    /// Constrain the restart operand of a round to be a bit
+    fn assign_restart(
+        &self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        operand: &Limb<Fr>,
+    ) -> Result<Limb<Fr>, Error> {
+        let mut flags = vec![];
+        self.config
+            .decompose_limb(region, &mut (), offset, operand, &mut flags, 1)?;
+        Ok(flags[0].clone())
+    }
+
+    /// scalar.BASE with 4 bits windows: the entry of each window is interpolated
+    /// from the bits of the window with constant coefficients, so that a window
+    /// takes one addition instead of four doubles and additions
+    pub fn mul_base(
+        &self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        scalar: &Limb<Fr>,
+    ) -> Result<Point<Fr>, Error> {
+        let mut scalar_bin: Vec<Limb<Fr>> = vec![];
+        self.config
+            .decompose_limb(region, &mut (), offset, scalar, &mut scalar_bin, 256)?;
+
+        let mut ret = self.state.identity.clone();
+        for (bits, coeffs) in scalar_bin
+            .chunks_exact(BASE_WINDOW_BITS)
+            .rev()
+            .zip(FIXED_BASE_COEFFS.iter())
+        {
+            // monomials[s - 1] is the product of the bits set in s
+            let mut monomials: Vec<Limb<Fr>> = vec![];
+            for s in 1usize..(1 << BASE_WINDOW_BITS) {
+                let high = 1 << (usize::BITS - 1 - s.leading_zeros());
+                // the bits of a window are in big endian
+                let bit = bits[BASE_WINDOW_BITS - 1 - high.trailing_zeros() as usize].clone();
+                let monomial = if s == high {
+                    bit
+                } else {
+                    let rest = monomials[s - high - 1].clone();
+                    self.config.assign_line(
+                        region,
+                        &mut (),
+                        offset,
+                        [
+                            Some(rest.clone()),
+                            None,
+                            None,
+                            Some(bit.clone()),
+                            Some(Limb::new(None, rest.value * bit.value)),
+                            None,
+                        ],
+                        [
+                            None,
+                            None,
+                            None,
+                            None,
+                            Some(-Fr::one()),
+                            None,
+                            Some(Fr::one()),
+                            None,
+                            None,
+                        ],
+                        0,
+                    )?[2]
+                        .clone()
+                };
+                monomials.push(monomial);
+            }
+            let mut coords = vec![];
+            for c in coeffs.iter() {
+                coords.push(self.config.sum_with_constant(
+                    region,
+                    &mut (),
+                    offset,
+                    monomials.iter().zip(c[1..].iter().cloned()).collect(),
+                    Some(c[0]),
+                )?);
+            }
+            let [x, y]: [Limb<Fr>; 2] = coords.try_into().unwrap();
+            ret = self.add(region, offset, &ret, &Point { x, y })?;
+        }
+        Ok(ret)
+    }
+
+    /// Cofactor cleared subgroup check: witness Q on the curve with 8.Q = point,
+    /// which holds iff the point has no small order component.
+    pub fn assert_in_subgroup(
+        &self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        point: &Point<Fr>,
+    ) -> Result<(), Error> {
+        let root = HostPoint {
+            x: point.x.value,
+            y: point.y.value,
+        }
+        .cofactor_root();
+        let [x, y]: [Limb<Fr>; 2] = self
+            .config
+            .assign_witness(
+                region,
+                &mut (),
+                offset,
+                [
+                    Some(Limb::new(None, root.x)),
+                    Some(Limb::new(None, root.y)),
+                    None,
+                    None,
+                    None,
+                ],
+                0,
+            )?
+            .try_into()
+            .unwrap();
+        let mut acc = Point { x, y };
+        self.assert_on_curve(region, offset, &acc)?;
+        for _ in 0..3 {
+            acc = self.add(region, offset, &acc, &acc)?;
+        }
+        region.constrain_equal(acc.x.get_the_cell().cell(), point.x.get_the_cell().cell())?;
+        region.constrain_equal(acc.y.get_the_cell().cell(), point.y.get_the_cell().cell())?;
+        Ok(())
+    }
+}
+
+/// The sums of scalar multiplications of BASE. They are laid out by mul_base in
+/// rounds of their own (JubjubBaseSumNew), so that the layout of a round does not
+/// depend on whether the point of the round is BASE.
+pub struct AltJubBaseChip(pub AltJubChip<Fr>);
+
+impl Chip<Fr> for AltJubBaseChip {
+    type Config = CommonGateConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.0.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl AltJubBaseChip {
+    pub fn new(config: CommonGateConfig) -> Self {
+        AltJubBaseChip(AltJubChip::new(config))
+    }
+
+    pub fn initialize(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &Region<Fr>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        self.0.initialize(config, region, offset)
+    }
+
+    /// One JubjubBaseSumNew round: same as assign_jubjub_sum for the point BASE
+    pub fn assign_base_sum(
+        &mut self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        scalar: &Limb<Fr>,
+        operand: &Limb<Fr>,
+        result: &Point<Fr>,
+    ) -> Result<(), Error> {
+        let reset = self.0.assign_restart(region, offset, operand)?;
+        let operand = self.0.mul_base(region, offset, scalar)?;
+        self.0.accumulate(region, offset, &operand, &reset, result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use halo2_proofs::pairing::bn256::Fr;
     use halo2_proofs::{circuit::floor_planner::FlatFloorPlanner, dev::MockProver};
 
     use crate::circuits::CommonGateConfig;
+    use crate::host::jubjub::BASE;
+    use crate::utils::field_to_bn;
     use crate::value_for_assign;
 
     use halo2_proofs::{
@@ -589,7 +901,7 @@ mod tests {
         plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
     };
 
-    use super::{AltJubChip, Limb, Point};
+    use super::{AltJubBaseChip, AltJubChip, Limb, Point};
 
     #[derive(Clone, Debug)]
     pub struct HelperChipConfig {
@@ -916,6 +1228,107 @@ mod tests {
         }
     }
 
+    #[derive(Clone, Debug, Default)]
+    struct BaseMulTestCircuit {
+        mul_result_x: Fr,
+        mul_result_y: Fr,
+        scalar: Fr,
+    }
+
+    impl Circuit<Fr> for BaseMulTestCircuit {
+        type Config = MulTestConfig;
+        type FloorPlanner = FlatFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            MulTestCircuit::configure(cs)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "test fixed base mul",
+                |region| {
+                    let altjubchip = AltJubChip::<Fr>::new(config.clone().altjubconfig);
+                    let helperchip = HelperChip::new(config.clone().helperconfig);
+                    let mut offset = 0;
+                    let p =
+                        altjubchip.mul_base(&region, &mut offset, &Limb::new(None, self.scalar))?;
+                    let (x, y) = helperchip.assign_addition_result(&region, &mut offset, &p)?;
+                    let (fixed_x, fixed_y) = helperchip.assign_known_val(
+                        &region,
+                        &mut offset,
+                        self.mul_result_x,
+                        self.mul_result_y,
+                    )?;
+                    region.constrain_equal(
+                        x.clone().cell.unwrap().cell(),
+                        fixed_x.clone().cell.unwrap().cell(),
+                    )?;
+                    region.constrain_equal(
+                        y.clone().cell.unwrap().cell(),
+                        fixed_y.clone().cell.unwrap().cell(),
+                    )?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct BaseSumTestCircuit {
+        // [restart, scalar, result.x, result.y] of each round
+        rounds: Vec<[Fr; 4]>,
+    }
+
+    impl Circuit<Fr> for BaseSumTestCircuit {
+        type Config = MulTestConfig;
+        type FloorPlanner = FlatFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            MulTestCircuit::configure(cs)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "test fixed base sum",
+                |region| {
+                    let mut basechip = AltJubBaseChip::new(config.clone().altjubconfig);
+                    let helperchip = HelperChip::new(config.clone().helperconfig);
+                    let mut offset = 0;
+                    basechip.initialize(&config.altjubconfig, &region, &mut offset)?;
+                    for [restart, scalar, x, y] in self.rounds.iter() {
+                        // the restart operand and the scalar
+                        let args = helperchip.assign_p1(&region, &mut offset, *restart, *scalar)?;
+                        let result = helperchip.assign_p2(&region, &mut offset, *x, *y)?;
+                        basechip.assign_base_sum(
+                            &region,
+                            &mut offset,
+                            &args.y,
+                            &args.x,
+                            &result,
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
     use ff::PrimeField;
     #[test]
     fn test_circuit_add() {
@@ -987,4 +1400,40 @@ mod tests {
         let prover_2 = MockProver::run(18, &test_circuit_2, vec![]).unwrap();
         assert_eq!(prover_2.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_circuit_mul_base() {
+        let scalar = -Fr::from(32195221423877958);
+        let expected = BASE.mul_scalar(&field_to_bn(&scalar));
+        let test_circuit = BaseMulTestCircuit {
+            mul_result_x: expected.x,
+            mul_result_y: expected.y,
+            scalar,
+        };
+
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_circuit_base_sum() {
+        let scalars = [Fr::from(3u64), -Fr::from(32195221423877958)];
+        let first = BASE.mul_scalar(&field_to_bn(&scalars[0]));
+        let second = first.add(&BASE.mul_scalar(&field_to_bn(&scalars[1])));
+        let mut rounds = vec![
+            [Fr::one(), scalars[0], first.x, first.y],
+            [Fr::zero(), scalars[1], second.x, second.y],
+        ];
+        let test_circuit = BaseSumTestCircuit {
+            rounds: rounds.clone(),
+        };
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the second round restarts the sum so its result is not the sum of both
+        rounds[1][0] = Fr::one();
+        let test_circuit = BaseSumTestCircuit { rounds };
+        let prover = MockProver::run(18, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
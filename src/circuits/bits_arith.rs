@@ -1,5 +1,5 @@
-use crate::circuits::{LookupAssistChip, LookupAssistConfig};
-use crate::utils::{GateCell, Limb};
+use crate::circuits::{CommonGateConfig, LookupAssistChip, LookupAssistConfig};
+use crate::utils::{field_to_u64, GateCell, Limb};
 use crate::{
     customized_circuits, customized_circuits_expand, item_count, table_item, value_for_assign,
 };
@@ -16,6 +16,7 @@ pub const BIT_AND: u8 = 2;
 pub const BIT_NOT_AND: u8 = 3;
 pub const BIT_ROTATE_LEFT: u8 = 4; // 4 + 7, max 11 ---- total 2^4
 pub const BIT_ROTATE_RIGHT: u8 = 12; // 12 + 7, max 21 -- total 2^5
+pub const BIT_OR: u8 = 22; // after the range reserved by the rotate right entries
 
 // a0 a1 a2 a3
 // a4 a5 a6 a7
@@ -126,6 +127,7 @@ impl<F: FieldExt> BitsArithChip<F> {
         self.assign_table_entries(region, |x, y| x ^ y, BIT_XOR, offset)?;
         self.assign_table_entries(region, |x, y| x & y, BIT_AND, offset)?;
         self.assign_table_entries(region, |x, y| (!x) & y, BIT_NOT_AND, offset)?;
+        self.assign_table_entries(region, |x, y| x | y, BIT_OR, offset)?;
         for i in 0..8 {
             self.assign_table_entries(
                 region,
@@ -143,3 +145,393 @@ impl<F: FieldExt> BitsArithChip<F> {
         Ok(())
     }
 }
+
+/* Operations of 32 or 64 bit words built on the bits arith lookup.
+ * A lookup line at row r checks the bytes at row r, r + 2 and r + 4 as (lhs, rhs, res),
+ * so an operation lays out the byte lines of lhs, rhs and res with the op as the hint of
+ * the lines of lhs. Shifts and rotations take the rotate left entries on pairs of adjacent
+ * bytes and the modular addition only needs the bytes of its result to be in the table.
+ */
+impl CommonGateConfig {
+    fn word_bitwise<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        lhs: &Limb<F>,
+        rhs: &Limb<F>,
+        op: u8,
+        f: impl Fn(u64, u64) -> u64,
+    ) -> Result<Limb<F>, Error> {
+        let res = Limb::new(
+            None,
+            F::from(f(field_to_u64(&lhs.value), field_to_u64(&rhs.value))),
+        );
+        self.decompose_bytes(region, offset, lhs, 0, op as u64)?;
+        self.decompose_bytes(region, offset, rhs, 0, 0)?;
+        let (res, _) = self.decompose_bytes(region, offset, &res, 0, 0)?;
+        Ok(res)
+    }
+
+    /// lhs ^ rhs of two words of the same size
+    pub fn word_xor<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        lhs: &Limb<F>,
+        rhs: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        self.word_bitwise(region, offset, lhs, rhs, BIT_XOR, |x, y| x ^ y)
+    }
+
+    /// lhs & rhs of two words of the same size
+    pub fn word_and<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        lhs: &Limb<F>,
+        rhs: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        self.word_bitwise(region, offset, lhs, rhs, BIT_AND, |x, y| x & y)
+    }
+
+    /// lhs | rhs of two words of the same size
+    pub fn word_or<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        lhs: &Limb<F>,
+        rhs: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        self.word_bitwise(region, offset, lhs, rhs, BIT_OR, |x, y| x | y)
+    }
+
+    /// !lhs & rhs of two words of the same size
+    pub fn word_not_and<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        lhs: &Limb<F>,
+        rhs: &Limb<F>,
+    ) -> Result<Limb<F>, Error> {
+        self.word_bitwise(region, offset, lhs, rhs, BIT_NOT_AND, |x, y| (!x) & y)
+    }
+
+    /// !input of a word of `bits` bits
+    pub fn word_not<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        input: &Limb<F>,
+        bits: usize,
+    ) -> Result<Limb<F>, Error> {
+        self.sum_with_constant(
+            region,
+            &mut (),
+            offset,
+            vec![(input, -F::one())],
+            Some(F::from(word_mask(bits))),
+        )
+    }
+
+    /// lhs + rhs mod 2^bits
+    pub fn word_add<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        lhs: &Limb<F>,
+        rhs: &Limb<F>,
+        bits: usize,
+    ) -> Result<Limb<F>, Error> {
+        assert!(bits == 32 || bits == 64);
+        let sum = field_to_u64(&lhs.value) as u128 + field_to_u64(&rhs.value) as u128;
+        let carry = Limb::new(None, F::from((sum >> bits) as u64));
+        let res = Limb::new(None, F::from(sum as u64 & word_mask(bits)));
+        let cells = self.assign_line(
+            region,
+            &mut (),
+            offset,
+            [
+                Some(lhs.clone()),
+                Some(rhs.clone()),
+                Some(res),
+                Some(carry),
+                None,
+                None,
+            ],
+            [
+                Some(F::one()),
+                Some(F::one()),
+                Some(-F::one()),
+                Some(-F::from_u128(1u128 << bits)),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            0,
+        )?;
+        let (res, carry) = (cells[2].clone(), cells[3].clone());
+
+        // carry * carry - carry = 0
+        self.assign_line(
+            region,
+            &mut (),
+            offset,
+            [Some(carry.clone()), None, None, Some(carry), None, None],
+            [
+                Some(-F::one()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(F::one()),
+                None,
+                None,
+            ],
+            0,
+        )?;
+
+        // res & res = res makes sure that the bytes of res are in the table
+        let (_, bytes) = self.decompose_bytes(region, offset, &res, 0, BIT_AND as u64)?;
+        self.decompose_bytes(region, offset, &res, 0, 0)?;
+        self.decompose_bytes(region, offset, &res, 0, 0)?;
+        if bits < 64 {
+            let zero = self.assign_constant(region, &mut (), offset, &F::zero())?;
+            for byte in bytes[bits / 8..].iter() {
+                region.constrain_equal(byte.get_the_cell().cell(), zero.get_the_cell().cell())?;
+            }
+        }
+        Ok(res)
+    }
+
+    /// input rotated right by n of a word of `bits` bits
+    pub fn word_rotate_right<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        input: &Limb<F>,
+        n: usize,
+        bits: usize,
+    ) -> Result<Limb<F>, Error> {
+        assert!(bits == 32 || bits == 64);
+        let n = n % bits;
+        let width = bits / 8;
+        let chunk = n / 8; // how many bytes we have to move
+        let rem = n % 8; // how many bits we have to move
+        let v = field_to_u64(&input.value);
+        let v = ((v >> n) | (v << ((bits - n) % bits))) & word_mask(bits);
+
+        let (_, bytes) = self.decompose_bytes(region, offset, input, 0, 0)?;
+        let zero = if width < 8 {
+            let zero = self.assign_constant(region, &mut (), offset, &F::zero())?;
+            for byte in bytes[width..].iter() {
+                region.constrain_equal(byte.get_the_cell().cell(), zero.get_the_cell().cell())?;
+            }
+            Some(zero)
+        } else {
+            None
+        };
+
+        // the byte i of the result is made of the bytes i + chunk and i + chunk + 1 of input
+        let byte = |i: usize| bytes[i % width].clone();
+        let (lhs, rhs): (Vec<_>, Vec<_>) = (0..8)
+            .map(|i| {
+                if i >= width {
+                    (zero.clone().unwrap(), zero.clone().unwrap())
+                } else if rem == 0 {
+                    (byte(i + chunk), byte(i + chunk))
+                } else {
+                    (byte(i + chunk + 1), byte(i + chunk))
+                }
+            })
+            .unzip();
+        let op = BIT_ROTATE_LEFT as u64 + ((8 - rem) % 8) as u64;
+        for (row, hint) in [(lhs, op), (rhs, 0)] {
+            for half in row.chunks(4) {
+                self.assign_witness(
+                    region,
+                    &mut (),
+                    offset,
+                    [
+                        Some(half[0].clone()),
+                        Some(half[1].clone()),
+                        Some(half[2].clone()),
+                        Some(half[3].clone()),
+                        None,
+                    ],
+                    hint,
+                )?;
+            }
+        }
+        let (res, _) = self.decompose_bytes(region, offset, &Limb::new(None, F::from(v)), 0, 0)?;
+        Ok(res)
+    }
+
+    /// input rotated left by n of a word of `bits` bits
+    pub fn word_rotate_left<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        input: &Limb<F>,
+        n: usize,
+        bits: usize,
+    ) -> Result<Limb<F>, Error> {
+        self.word_rotate_right(region, offset, input, bits - n % bits, bits)
+    }
+
+    /// input >> n of a word of `bits` bits, which is the rotation with the wrapped bits masked
+    pub fn word_shift_right<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        input: &Limb<F>,
+        n: usize,
+        bits: usize,
+    ) -> Result<Limb<F>, Error> {
+        assert!(n < bits);
+        let rotated = self.word_rotate_right(region, offset, input, n, bits)?;
+        let mask = F::from(word_mask(bits - n));
+        let mask = self.assign_constant(region, &mut (), offset, &mask)?;
+        self.word_and(region, offset, &rotated, &mask)
+    }
+
+    /// input << n mod 2^bits, which is the rotation with the wrapped bits masked
+    pub fn word_shift_left<F: FieldExt>(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        input: &Limb<F>,
+        n: usize,
+        bits: usize,
+    ) -> Result<Limb<F>, Error> {
+        assert!(n < bits);
+        let rotated = self.word_rotate_left(region, offset, input, n, bits)?;
+        let mask = F::from(word_mask(bits) & !word_mask(n));
+        let mask = self.assign_constant(region, &mut (), offset, &mask)?;
+        self.word_and(region, offset, &rotated, &mask)
+    }
+}
+
+/// 2^bits - 1 for bits no more than 64
+fn word_mask(bits: usize) -> u64 {
+    ((1u128 << bits) - 1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitsArithChip, BitsArithConfig};
+    use crate::circuits::CommonGateConfig;
+    use crate::utils::{field_to_u64, Limb};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::{
+        circuit::{floor_planner::FlatFloorPlanner, Layouter, Region},
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    #[derive(Clone, Debug, Default)]
+    struct WordOpsCircuit {
+        lhs: u64,
+        rhs: u64,
+        bits: usize,
+    }
+
+    impl WordOpsCircuit {
+        fn check(
+            &self,
+            config: &CommonGateConfig,
+            region: &Region<Fr>,
+            offset: &mut usize,
+        ) -> Result<(), Error> {
+            let bits = self.bits;
+            let mask = ((1u128 << bits) - 1) as u64;
+            let rotate = |x: u64, n: usize| ((x >> n) | (x << (bits - n))) & mask;
+            let cells = config.assign_witness(
+                region,
+                &mut (),
+                offset,
+                [
+                    Some(Limb::new(None, Fr::from(self.lhs))),
+                    Some(Limb::new(None, Fr::from(self.rhs))),
+                    None,
+                    None,
+                    None,
+                ],
+                0,
+            )?;
+            let (a, b) = (&cells[0], &cells[1]);
+            let (x, y) = (self.lhs, self.rhs);
+            let expect = |res: Limb<Fr>, v: u64| assert_eq!(field_to_u64(&res.value), v);
+            expect(config.word_xor(region, offset, a, b)?, x ^ y);
+            expect(config.word_and(region, offset, a, b)?, x & y);
+            expect(config.word_or(region, offset, a, b)?, x | y);
+            expect(config.word_not_and(region, offset, a, b)?, !x & y);
+            expect(config.word_not(region, offset, a, bits)?, !x & mask);
+            expect(
+                config.word_add(region, offset, a, b, bits)?,
+                x.wrapping_add(y) & mask,
+            );
+            for n in [1, 7, 8, 13, 16, 24, 31] {
+                expect(
+                    config.word_rotate_right(region, offset, a, n, bits)?,
+                    rotate(x, n),
+                );
+                expect(
+                    config.word_rotate_left(region, offset, a, n, bits)?,
+                    rotate(x, bits - n),
+                );
+                expect(config.word_shift_right(region, offset, a, n, bits)?, x >> n);
+                expect(
+                    config.word_shift_left(region, offset, a, n, bits)?,
+                    (x << n) & mask,
+                );
+            }
+            Ok(())
+        }
+    }
+
+    impl Circuit<Fr> for WordOpsCircuit {
+        type Config = (CommonGateConfig, BitsArithConfig);
+        type FloorPlanner = FlatFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let witness = (0..5).map(|_| cs.advice_column()).collect::<Vec<_>>();
+            let arith = BitsArithChip::configure(cs);
+            (CommonGateConfig::configure(cs, &arith, &witness), arith)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "test word ops",
+                |region| {
+                    BitsArithChip::new(config.1.clone()).initialize(&region, &mut 1)?;
+                    self.check(&config.0, &region, &mut 0)
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_word_ops() {
+        for (lhs, rhs, bits) in [
+            (0x89abcdef, 0x76543210, 32),
+            (0xffffffff, 1, 32),
+            (0x0123456789abcdef, 0xfedcba9876543211, 64),
+        ] {
+            let circuit = WordOpsCircuit { lhs, rhs, bits };
+            let prover = MockProver::run(20, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+}
@@ -188,6 +188,24 @@ fn enable_fq12_permute(
     Ok(())
 }
 
+/// Assign the identity flag 1 - active * (1 - z) of a pair of a multi pairing,
+/// where z is a boolean cell bound to the flag limb of the input point
+fn assign_pair_flag(
+    ctx: &mut NativeScalarEccContext<G1Affine>,
+    region: &Region<Fr>,
+    active: &AssignedValue<Fr>,
+    flag: &Limb<Fr>,
+) -> Result<AssignedValue<Fr>, Error> {
+    let z = ctx.plonk_region_context().assign_bit(flag.value)?;
+    region.constrain_equal(flag.get_the_cell().cell(), z.cell())?;
+    let not_z = ctx
+        .plonk_region_context()
+        .sum_with_constant(&[(&z, -Fr::one())], Some(Fr::one()))?;
+    let t = ctx.plonk_region_context().mul(active, &not_z)?;
+    ctx.plonk_region_context()
+        .sum_with_constant(&[(&t, -Fr::one())], Some(Fr::one()))
+}
+
 impl Bn256PairChip<Fr> {
     pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
         Self {
@@ -202,11 +220,12 @@ impl Bn256PairChip<Fr> {
         }
     }
 
+    /// Lays out the pairings e(a, b) = ab of all the rounds in one region, where
+    /// a is G1 (3 * 2 + 1), b is G2 (3 * 4 + 1) and ab is Fq_12 (3 * 12) limbs.
+    /// The lookup cells are finalized once after the last pairing.
     pub fn load_bn256_pair_circuit(
         &self,
-        a: &Vec<Limb<Fr>>,  //G1 (3 * 2 + 1)
-        b: &Vec<Limb<Fr>>,  //G2 (3 * 4 + 1)
-        ab: &Vec<Limb<Fr>>, // Fq_12 (3 * 12)
+        pairs: &Vec<(Vec<Limb<Fr>>, Vec<Limb<Fr>>, Vec<Limb<Fr>>)>, // (a, b, ab)
         layouter: &impl Layouter<Fr>,
     ) -> Result<(), Error> {
         layouter.assign_region(
@@ -215,13 +234,129 @@ impl Bn256PairChip<Fr> {
                 let timer = start_timer!(|| "assign");
 
                 let mut ctx = self.config.ecc_chip_config.to_context(region);
-                let a_g1 = assign_point_g1(&mut ctx, a);
-                let b_g2 = assign_point_g2(&mut ctx, b);
-                let ab_fq12_raw = ctx.pairing(&[(&a_g1, &b_g2)])?;
+                for (a, b, ab) in pairs.iter() {
+                    let a_g1 = assign_point_g1(&mut ctx, a);
+                    let b_g2 = assign_point_g2(&mut ctx, b);
+                    let ab_fq12_raw = ctx.pairing(&[(&a_g1, &b_g2)])?;
+                    let ab_fq12 = ctx.fq12_reduce(&ab_fq12_raw)?;
+
+                    enable_g1affine_permute(region, &a_g1, a)?;
+                    enable_g2affine_permute(region, &b_g2, b)?;
+                    enable_fq12_permute(region, &ab_fq12, ab)?;
+                }
+                end_timer!(timer);
+
+                let timer = start_timer!(|| "finalize int mul");
+                ctx.integer_context().finalize_int_mul()?;
+                end_timer!(timer);
+
+                ctx.get_range_region_context().init()?;
+                let timer = start_timer!(|| "finalize compact cells");
+                ctx.get_range_region_context().finalize_compact_cells()?;
+                end_timer!(timer);
+
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+/// Proves the product of the pairings e(a_1, b_1) * ... * e(a_n, b_n) of a
+/// Bn254MultiPair call, where the miller loops of the pairs share one final
+/// exponentiation.
+pub struct Bn256MultiPairChip<N: FieldExt> {
+    config: Bn256ChipConfig,
+    _marker: PhantomData<N>,
+}
+
+impl<N: FieldExt> Chip<N> for Bn256MultiPairChip<N> {
+    type Config = Bn256ChipConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl Bn256MultiPairChip<Fr> {
+    pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
+        Self {
+            config: config.clone(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(cs: &mut ConstraintSystem<Fr>) -> <Self as Chip<Fr>>::Config {
+        Bn256ChipConfig {
+            ecc_chip_config: NativeScalarEccConfig::configure::<G1Affine>(cs),
+        }
+    }
+
+    /// Lays out a multi pairing of exactly `pairs.len()` pairs so that the region
+    /// does not depend on the witness. The pair i is active iff i < count, where
+    /// active_0 = 1, active_{i+1} = active_i * bit_{i+1} and count = \sum active_i.
+    /// Inactive pairs are paired as identity points thus the product only
+    /// consists of the first count pairs.
+    pub fn load_bn256_multi_pair_circuit(
+        &self,
+        count: &Limb<Fr>,                            // number of active pairs
+        pairs: &Vec<(Vec<Limb<Fr>>, Vec<Limb<Fr>>)>, // (G1 (3 * 2 + 1), G2 (3 * 4 + 1))
+        ab: &Vec<Limb<Fr>>,                          // Fq_12 (3 * 12)
+        layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "base",
+            |region| {
+                let timer = start_timer!(|| "assign");
+
+                let mut ctx = self.config.ecc_chip_config.to_context(region);
+                let n = count.value.get_lower_128() as usize;
+                let mut actives = vec![ctx.plonk_region_context().assign_constant(Fr::one())?];
+                for i in 1..pairs.len() {
+                    let bit = ctx.plonk_region_context().assign_bit(if i < n {
+                        Fr::one()
+                    } else {
+                        Fr::zero()
+                    })?;
+                    let active = ctx
+                        .plonk_region_context()
+                        .mul(actives.last().unwrap(), &bit)?;
+                    actives.push(active);
+                }
+                let total = ctx.plonk_region_context().sum_with_constant(
+                    &actives.iter().map(|x| (x, Fr::one())).collect::<Vec<_>>(),
+                    None,
+                )?;
+                region.constrain_equal(count.get_the_cell().cell(), total.cell())?;
+
+                let mut terms = vec![];
+                for ((a, b), active) in pairs.iter().zip(actives.iter()) {
+                    let a_g1 = assign_point_g1(&mut ctx, a);
+                    let a_z = assign_pair_flag(&mut ctx, region, active, &a[6])?;
+                    let a_g1 = AssignedPoint::new(a_g1.x, a_g1.y, a_z.into());
+                    let b_g2 = assign_point_g2(&mut ctx, b);
+                    let b_z = assign_pair_flag(&mut ctx, region, active, &b[12])?;
+                    let b_g2 = AssignedG2Affine::new(b_g2.x, b_g2.y, b_z.into());
+
+                    let mut inputs = a.chunks(3);
+                    for fq in [&a_g1.x, &a_g1.y] {
+                        enable_fq_permute(region, fq, &inputs.next().unwrap().to_vec())?;
+                    }
+                    let mut inputs = b.chunks(3);
+                    for fq in [&b_g2.x.0, &b_g2.x.1, &b_g2.y.0, &b_g2.y.1] {
+                        enable_fq_permute(region, fq, &inputs.next().unwrap().to_vec())?;
+                    }
+                    terms.push((a_g1, b_g2));
+                }
+                let terms = terms.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+                let ab_fq12_raw = ctx.pairing(&terms)?;
                 let ab_fq12 = ctx.fq12_reduce(&ab_fq12_raw)?;
 
-                enable_g1affine_permute(region, &a_g1, a)?;
-                enable_g2affine_permute(region, &b_g2, b)?;
                 enable_fq12_permute(region, &ab_fq12, ab)?;
                 end_timer!(timer);
 
@@ -284,18 +419,19 @@ impl Bn256SumChip<Fr> {
                 let timer = start_timer!(|| "assign");
                 let mut ctx = self.config.ecc_chip_config.to_context(region);
 
+                let mut resets = vec![];
                 let mut ais = vec![];
                 let mut g1s = vec![];
                 let mut sums = vec![];
                 let identity = ctx.assign_identity()?;
                 let mut sum = identity.clone();
                 for group in ls.chunks_exact(16) {
-                    // using constraint to fix if to reset
-                    let lhs = if group.get(0).unwrap().value != Fr::zero() {
-                        identity.clone()
-                    } else {
-                        sum
-                    };
+                    // the reset flag is a boolean cell which selects the identity
+                    // instead of the running sum as the accumulator
+                    let reset = ctx
+                        .plonk_region_context()
+                        .assign_bit(group.get(0).unwrap().value)?;
+                    let lhs = ctx.bisec_point(&reset, &identity, &sum)?;
                     let a = assign_scalar(&mut ctx, group.get(1).unwrap().value);
                     let g = assign_point_g1(&mut ctx, &group.get(2..9).unwrap().to_vec());
                     let rhs = ctx.ecc_mul(&g, a);
@@ -303,11 +439,17 @@ impl Bn256SumChip<Fr> {
                     let sum_ret = ctx.ecc_reduce(&sum_ret)?;
 
                     sum = sum_ret.clone();
+                    resets.push(reset);
                     ais.push(a);
                     g1s.push(g);
                     sums.push(sum_ret);
                 }
 
+                resets.iter().enumerate().for_each(|(i, x)| {
+                    region
+                        .constrain_equal(ls[16 * i].get_the_cell().cell(), x.cell())
+                        .unwrap()
+                });
                 ais.iter().enumerate().for_each(|(i, x)| {
                     enable_fr_permute(&mut region, x, &ls[16 * i + 1..16 * i + 2].to_vec()).unwrap()
                 });
@@ -336,3 +478,118 @@ impl Bn256SumChip<Fr> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Bn256ChipConfig, Bn256SumChip};
+    use crate::utils::{bn_to_field, field_to_bn, Limb};
+    use crate::value_for_assign;
+    use halo2_proofs::circuit::floor_planner::FlatFloorPlanner;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::{Fr, G1Affine, G1};
+    use halo2_proofs::pairing::group::Group;
+    use halo2_proofs::{
+        circuit::Layouter,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+    };
+    use num_bigint::BigUint;
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        bn256config: Bn256ChipConfig,
+        limb: Column<Advice>,
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        // n * (reset, scalar, g1 (3 * 2 + 1), sum (3 * 2 + 1))
+        limbs: Vec<Fr>,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = FlatFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let limb = cs.advice_column();
+            cs.enable_equality(limb);
+            TestConfig {
+                bn256config: Bn256SumChip::<Fr>::configure(cs),
+                limb,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let limbs = layouter.assign_region(
+                || "assign inputs",
+                |region| {
+                    let mut limbs = vec![];
+                    for (offset, v) in self.limbs.iter().enumerate() {
+                        let c = region.assign_advice(
+                            || "assign input",
+                            config.limb,
+                            offset,
+                            || value_for_assign!(v.clone()),
+                        )?;
+                        limbs.push(Limb::new(Some(c), v.clone()));
+                    }
+                    Ok(limbs)
+                },
+            )?;
+            let chip = Bn256SumChip::construct(config.bn256config);
+            chip.load_bn256_sum_circuit(&limbs, &layouter)?;
+            Ok(())
+        }
+    }
+
+    fn g1_to_limbs(p: &G1) -> Vec<Fr> {
+        let is_identity = p.is_identity().unwrap_u8();
+        let p = G1Affine::from(*p);
+        let mut r = vec![];
+        for c in [p.x, p.y] {
+            let bn = field_to_bn(&c);
+            for i in 0..3 {
+                let limb = (&bn >> (108 * i)) % (BigUint::from(1u64) << 108);
+                r.push(bn_to_field(&limb));
+            }
+        }
+        r.push(Fr::from(is_identity as u64));
+        r
+    }
+
+    fn sum_round(reset: Fr, scalar: u64, g: &G1, sum: &G1) -> Vec<Fr> {
+        let mut r = vec![reset, Fr::from(scalar)];
+        r.append(&mut g1_to_limbs(g));
+        r.append(&mut g1_to_limbs(sum));
+        r
+    }
+
+    #[test]
+    fn test_bn256_sum_reset() {
+        let g = G1::generator();
+        let mut limbs = sum_round(Fr::one(), 2, &g, &(g * Fr::from(2)));
+        limbs.append(&mut sum_round(Fr::zero(), 3, &g, &(g * Fr::from(5))));
+        let test_circuit = TestCircuit { limbs };
+        let prover = MockProver::run(22, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_bn256_sum_forged_reset() {
+        // a flag of 2 used to reset the accumulator as any non zero value did
+        let g = G1::generator();
+        let mut limbs = sum_round(Fr::one(), 2, &g, &(g * Fr::from(2)));
+        limbs.append(&mut sum_round(Fr::from(2), 3, &g, &(g * Fr::from(3))));
+        let test_circuit = TestCircuit { limbs };
+        let prover = MockProver::run(22, &test_circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
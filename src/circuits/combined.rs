@@ -0,0 +1,42 @@
+use crate::circuits::babyjub::AltJubChip;
+use crate::circuits::merkle::MerkleChip;
+use crate::circuits::poseidon::PoseidonChip;
+use crate::circuits::poseidon::PoseidonGateConfig;
+use crate::circuits::CommonGateConfig;
+use crate::host::poseidon::POSEIDON_HASHER_SPEC;
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem};
+
+/// Hosts the poseidon, merkle and jubjub msm chips in one circuit.
+/// All of them are built on the same CommonGateConfig and PoseidonGateConfig,
+/// so the gates are only configured once.
+pub struct CombinedChip<const DEPTH: usize> {
+    pub poseidon_chip: PoseidonChip<Fr, 9, 8>,
+    pub merkle_chip: MerkleChip<Fr, DEPTH>,
+    pub jubjub_chip: AltJubChip<Fr>,
+    /// rounds of the poseidon, merkle and jubjub chips, which are known
+    /// after synthesize
+    pub rounds: [usize; 3],
+}
+
+impl<const DEPTH: usize> CombinedChip<DEPTH> {
+    pub fn new(config: CommonGateConfig, extend: PoseidonGateConfig) -> Self {
+        CombinedChip {
+            poseidon_chip: PoseidonChip::construct(
+                config.clone(),
+                extend.clone(),
+                POSEIDON_HASHER_SPEC.clone(),
+            ),
+            merkle_chip: MerkleChip::new(config.clone(), extend),
+            jubjub_chip: AltJubChip::new(config),
+            rounds: [0; 3],
+        }
+    }
+
+    pub fn configure(
+        cs: &mut ConstraintSystem<Fr>,
+        shared_advices: &Vec<Column<Advice>>,
+    ) -> (CommonGateConfig, PoseidonGateConfig) {
+        MerkleChip::<Fr, DEPTH>::configure(cs, shared_advices)
+    }
+}
@@ -1,4 +1,6 @@
 use super::Limb;
+use crate::host::ExternalHostCallEntry;
+use crate::utils::{bytes_to_u64, field_to_bytes, field_to_u64};
 use crate::{adaptor::get_selected_entries, constant, utils::GateCell};
 use ff::Field;
 use halo2_proofs::pairing::bn256::Fr;
@@ -11,20 +13,131 @@ use crate::{
 use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{Chip, Layouter, Region},
-    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector, VirtualCells},
+    plonk::{
+        Advice, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector,
+        VirtualCells,
+    },
     poly::Rotation,
 };
 
 use crate::constant_from;
 
 #[rustfmt::skip]
-customized_circuits!(HostOpConfig, 2, 11, 3, 0,
-    | shared_operand | shared_opcode | shared_index   | ops | inv | p1  | filtered_opcode  | filtered_index   | filtered_operand | merged_op   | enable   | indicator | sel_shared | sel
-    | nil            | nil           | shared_index_n | nil | nil | nil | nil              | filtered_index_n | nil              | merged_op_n | enable_n | nil       | sel_shared_n | sel_n
+customized_circuits!(HostOpConfig, 2, 20, 7, 0,
+    | shared_operand | shared_opcode | shared_index   | ops | inv | p1  | filtered_opcode  | filtered_index   | filtered_operand | merged_op   | enable   | borrow   | diff_0 | diff_1 | diff_2 | diff_3 | operand_0 | operand_1 | operand_2 | operand_3 | indicator | sel_shared   | sel   | canonical | modulus_limb | borrow_shift | u16_table
+    | nil            | nil           | shared_index_n | nil | nil | nil | nil              | filtered_index_n | nil              | merged_op_n | enable_n | borrow_n | nil    | nil    | nil    | nil    | nil       | nil       | nil       | nil       | nil       | sel_shared_n | sel_n | nil       | nil          | nil          | nil
 );
 
+/* Optional u64 check of the filtered operands:
+ * filtered_operand = \sum_j operand_j * 2^{16j} with each operand_j in the u16 table
+ * at every row where sel is active.
+ */
+
+/* Canonical check of the field elements merged from four u64 limbs l_0..l_3 (little endian):
+ * at the row of l_i, modulus_limb - filtered_operand - borrow + borrow_n * borrow_shift = diff
+ * where modulus_limb is the i-th u64 limb of p - 1, borrow_shift is 2^64 except for the top limb
+ * and diff = \sum_j diff_j * 2^{16j} with each diff_j in the u16 table.
+ * Summing up the rows gives p - 1 - \sum_i l_i * 2^{64i} - borrow_0 = \sum_i diff_i * 2^{64i} >= 0
+ * thus the merged value is less than p as long as the limbs are u64.
+ */
+
+/* Optional commitment of the filtered entries:
+ * acc = acc_n * challenge + enable * (opcode * 2^64 + operand)
+ * so that acc at the first filtered row equals \sum_i (opcode_i * 2^64 + operand_i) * challenge^i
+ * and count at the first row is a copy of the number of filtered entries.
+ */
+#[rustfmt::skip]
+customized_circuits!(HostCommitConfig, 2, 3, 0, 0,
+    | acc   | challenge   | count
+    | acc_n | challenge_n | nil
+);
+
+/// Fold the selected entries of a host call table into the commitment
+/// that `HostCommitConfig` computes in circuit.
+pub fn host_op_commitment(selected_entries: &Vec<((Fr, Fr), Fr)>, challenge: Fr) -> Fr {
+    selected_entries
+        .iter()
+        .rev()
+        .fold(Fr::zero(), |acc, ((operand, opcode), _)| {
+            acc * challenge + *opcode * Fr::from_u128(1u128 << 64) + *operand
+        })
+}
+
+impl HostCommitConfig {
+    pub fn configure<F: FieldExt>(
+        &self,
+        cs: &mut ConstraintSystem<F>,
+        host_config: &HostOpConfig,
+        instance: Column<Instance>,
+    ) {
+        cs.enable_equality(instance);
+        cs.create_gate("commit filtered entries", |meta| {
+            let acc = self.get_expr(meta, HostCommitConfig::acc());
+            let acc_n = self.get_expr(meta, HostCommitConfig::acc_n());
+            let challenge = self.get_expr(meta, HostCommitConfig::challenge());
+            let challenge_n = self.get_expr(meta, HostCommitConfig::challenge_n());
+            let sel = host_config.get_expr(meta, HostOpConfig::sel());
+            let sel_n = host_config.get_expr(meta, HostOpConfig::sel_n());
+            let enable = host_config.get_expr(meta, HostOpConfig::enable());
+            let opcode = host_config.get_expr(meta, HostOpConfig::filtered_opcode());
+            let operand = host_config.get_expr(meta, HostOpConfig::filtered_operand());
+            let packed = opcode * constant!(F::from_u128(1u128 << 64)) + operand;
+            vec![
+                sel.clone() * (acc - (acc_n.clone() * challenge.clone() + enable * packed)),
+                sel.clone() * (challenge_n - challenge),
+                sel * (constant_from!(1 as u64) - sel_n) * acc_n,
+            ]
+        });
+    }
+
+    /// Assign the commitment for the filtered rows `0..rows` and copy the
+    /// count of filtered entries, returns the challenge, the commitment and
+    /// the count cells of the first row.
+    pub fn assign(
+        &self,
+        region: &Region<Fr>,
+        rows: usize,
+        selected_entries: &Vec<((Fr, Fr), Fr)>,
+        challenge: Fr,
+        selected_total: &Limb<Fr>,
+    ) -> Result<(Limb<Fr>, Limb<Fr>, Limb<Fr>), Error> {
+        let mut accs = vec![Fr::zero(); rows + 1];
+        for i in (0..selected_entries.len()).rev() {
+            let ((operand, opcode), _) = selected_entries[i];
+            accs[i] = accs[i + 1] * challenge + opcode * Fr::from_u128(1u128 << 64) + operand;
+        }
+        let mut first = None;
+        for (offset, acc) in accs.into_iter().enumerate() {
+            let acc = self.assign_cell(region, offset, &HostCommitConfig::acc(), acc)?;
+            let challenge =
+                self.assign_cell(region, offset, &HostCommitConfig::challenge(), challenge)?;
+            if offset == 0 {
+                first = Some((challenge, acc));
+            }
+        }
+        let count =
+            self.assign_cell(region, 0, &HostCommitConfig::count(), selected_total.value)?;
+        region.constrain_equal(
+            count.get_the_cell().cell(),
+            selected_total.get_the_cell().cell(),
+        )?;
+        let (challenge, acc) = first.unwrap();
+        Ok((challenge, acc, count))
+    }
+}
+
 impl HostOpConfig {
-    pub fn configure<F: FieldExt>(&self, cs: &mut ConstraintSystem<F>, opcodes: &Vec<F>) {
+    pub fn configure<F: FieldExt>(
+        &self,
+        cs: &mut ConstraintSystem<F>,
+        opcodes: &Vec<F>,
+        ops_chain: &Vec<Column<Advice>>,
+        check_u64_operands: bool,
+    ) {
+        assert_eq!(ops_chain.len(), ops_chain_size(opcodes.len()));
+        if check_u64_operands {
+            self.configure_u64_operands(cs);
+        }
         cs.lookup_any("filter-shared-ops", |meta| {
             let sopc = self.get_expr(meta, HostOpConfig::shared_opcode());
             let soper = self.get_expr(meta, HostOpConfig::shared_operand());
@@ -39,9 +152,6 @@ impl HostOpConfig {
                 (fopc * enable.clone(), sopc),
             ]
         });
-        /* we would like to restrict the degree to 4 */
-        assert!(opcodes.len() <= 5);
-
         cs.create_gate("filt ops", |meta| {
             let shared_index = self.get_expr(meta, HostOpConfig::shared_index());
             let shared_index_n = self.get_expr(meta, HostOpConfig::shared_index_n());
@@ -61,10 +171,26 @@ impl HostOpConfig {
                 }
             }
 
+            //  when there are more than 5 opcodes, each chained column
+            //  ops_chain[j] = ops_chain[j-1] * \pi (next three sopc - opcode[i])
+            //  and the last chained column is the full_ops_mult
+            let mut chain_constraints = vec![];
+            let mut prev = ops.clone();
+            if opcodes.len() > 5 {
+                for (j, group) in opcodes[4..].chunks(3).enumerate() {
+                    let chain = meta.query_advice(ops_chain[j], Rotation::cur());
+                    let mult = group.iter().fold(prev, |acc, opcode| {
+                        acc * (sopc.clone() - constant!(opcode.clone()))
+                    });
+                    chain_constraints.push(sel.clone() * (chain.clone() - mult));
+                    prev = chain;
+                }
+            }
+
             let full_ops_mult = if opcodes.len() == 5 {
                 ops.clone() * (sopc.clone() - constant!(opcodes[4].clone()))
             } else {
-                ops.clone()
+                prev
             };
 
             let picked = shared_index.clone() - shared_index_n.clone();
@@ -78,7 +204,11 @@ impl HostOpConfig {
 
             // make sure ops trackes the intermediate multipilication
             let ops_captures_four = sel.clone() * (ops - cache_ops);
-            vec![expr, either_zero, ops_captures_four]
+            vec![
+                vec![expr, either_zero, ops_captures_four],
+                chain_constraints,
+            ]
+            .concat()
         });
 
         cs.create_gate("shared_index decrease", |meta| {
@@ -119,6 +249,45 @@ impl HostOpConfig {
             // merged_op_n * indicator + cur_op == merged_op ???
         });
 
+        cs.create_gate("canonical merged operands", |meta| {
+            let canonical = self.get_expr(meta, HostOpConfig::canonical());
+            let modulus_limb = self.get_expr(meta, HostOpConfig::modulus_limb());
+            let borrow_shift = self.get_expr(meta, HostOpConfig::borrow_shift());
+            let cur_op = self.get_expr(meta, HostOpConfig::filtered_operand());
+            let borrow = self.get_expr(meta, HostOpConfig::borrow());
+            let borrow_n = self.get_expr(meta, HostOpConfig::borrow_n());
+            let diff = [
+                HostOpConfig::diff_0(),
+                HostOpConfig::diff_1(),
+                HostOpConfig::diff_2(),
+                HostOpConfig::diff_3(),
+            ]
+            .into_iter()
+            .enumerate()
+            .fold(constant_from!(0), |acc, (i, cell)| {
+                acc + self.get_expr(meta, cell) * constant_from!(1u64 << (16 * i))
+            });
+            vec![
+                canonical.clone()
+                    * (modulus_limb - cur_op - borrow.clone() + borrow_n * borrow_shift - diff),
+                canonical * borrow.clone() * (constant_from!(1) - borrow),
+            ]
+        });
+
+        for cell in [
+            HostOpConfig::diff_0(),
+            HostOpConfig::diff_1(),
+            HostOpConfig::diff_2(),
+            HostOpConfig::diff_3(),
+        ] {
+            cs.lookup_any("canonical diff in u16", |meta| {
+                let canonical = self.get_expr(meta, HostOpConfig::canonical());
+                let diff = self.get_expr(meta, cell);
+                let table = self.get_expr(meta, HostOpConfig::u16_table());
+                vec![(canonical * diff, table)]
+            });
+        }
+
         /* enable is continuous with pattern 1,1,1,1,1,0,0,0,0 when sel is active */
         cs.create_gate("enable consistant", |meta| {
             let enable = self.get_expr(meta, HostOpConfig::enable());
@@ -146,6 +315,58 @@ impl HostOpConfig {
         });
     }
 
+    fn configure_u64_operands<F: FieldExt>(&self, cs: &mut ConstraintSystem<F>) {
+        cs.create_gate("filtered operands in u64", |meta| {
+            let sel = self.get_expr(meta, HostOpConfig::sel());
+            let cur_op = self.get_expr(meta, HostOpConfig::filtered_operand());
+            let pieces = HostOpConfig::operand_pieces()
+                .into_iter()
+                .enumerate()
+                .fold(constant_from!(0), |acc, (i, cell)| {
+                    acc + self.get_expr(meta, cell) * constant_from!(1u64 << (16 * i))
+                });
+            vec![sel * (cur_op - pieces)]
+        });
+
+        for cell in HostOpConfig::operand_pieces() {
+            cs.lookup_any("filtered operand piece in u16", |meta| {
+                let sel = self.get_expr(meta, HostOpConfig::sel());
+                let piece = self.get_expr(meta, cell);
+                let table = self.get_expr(meta, HostOpConfig::u16_table());
+                vec![(sel * piece, table)]
+            });
+        }
+    }
+
+    fn operand_pieces() -> [GateCell; 4] {
+        [
+            HostOpConfig::operand_0(),
+            HostOpConfig::operand_1(),
+            HostOpConfig::operand_2(),
+            HostOpConfig::operand_3(),
+        ]
+    }
+
+    /// Assign the u16 pieces of a filtered operand, which are only constrained
+    /// when the selector checks that its operands are u64.
+    fn assign_operand_pieces(
+        &self,
+        region: &Region<Fr>,
+        offset: usize,
+        operand: &Fr,
+    ) -> Result<(), Error> {
+        let operand = field_to_u64(operand);
+        for (j, cell) in HostOpConfig::operand_pieces().iter().enumerate() {
+            self.assign_cell(
+                region,
+                offset,
+                cell,
+                Fr::from((operand >> (16 * j)) & 0xffff),
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn assign_merged_operands(
         &self,
         region: &Region<Fr>,
@@ -170,6 +391,7 @@ impl HostOpConfig {
             values.into_iter().zip(merged_ops).enumerate()
         {
             self.assign_cell(region, *offset, &HostOpConfig::filtered_operand(), *operand)?;
+            self.assign_operand_pieces(region, *offset, operand)?;
             let opc =
                 self.assign_cell(region, *offset, &HostOpConfig::filtered_opcode(), *opcode)?;
             self.assign_cell(region, *offset, &HostOpConfig::filtered_index(), *index)?;
@@ -195,6 +417,59 @@ impl HostOpConfig {
         Ok((ret.unwrap(), op.unwrap()))
     }
 
+    /// Assign a field element merged from four u64 limbs and constrain that
+    /// the merged value is less than the modulus of Fr, so that each field
+    /// element has exactly one encoding as operands.
+    pub fn assign_merged_fr_operands(
+        &self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        values: Vec<&((Fr, Fr), Fr)>, //(operand, opcode), index
+        enable: bool,
+    ) -> Result<(Limb<Fr>, Limb<Fr>), Error> {
+        assert_eq!(values.len(), 4);
+        let limbs = values
+            .iter()
+            .map(|((operand, _), _)| field_to_u64(operand))
+            .collect::<Vec<_>>();
+        let modulus = bytes_to_u64(&field_to_bytes(&-Fr::one()));
+        let (diffs, _) = canonical_diffs(&limbs);
+        for (i, (borrow, diff)) in diffs.into_iter().enumerate() {
+            let row = *offset + i;
+            self.assign_cell(region, row, &HostOpConfig::canonical(), Fr::one())?;
+            self.assign_cell(
+                region,
+                row,
+                &HostOpConfig::modulus_limb(),
+                Fr::from(modulus[i]),
+            )?;
+            let borrow_shift = if i == 3 {
+                Fr::zero()
+            } else {
+                Fr::from_u128(1u128 << 64)
+            };
+            self.assign_cell(region, row, &HostOpConfig::borrow_shift(), borrow_shift)?;
+            self.assign_cell(
+                region,
+                row,
+                &HostOpConfig::borrow(),
+                Fr::from(borrow as u64),
+            )?;
+            for (j, cell) in [
+                HostOpConfig::diff_0(),
+                HostOpConfig::diff_1(),
+                HostOpConfig::diff_2(),
+                HostOpConfig::diff_3(),
+            ]
+            .iter()
+            .enumerate()
+            {
+                self.assign_cell(region, row, cell, Fr::from((diff >> (16 * j)) & 0xffff))?;
+            }
+        }
+        self.assign_merged_operands(region, offset, values, Fr::from_u128(1u128 << 64), enable)
+    }
+
     pub fn assign_one_line(
         &self,
         region: &Region<Fr>,
@@ -207,6 +482,7 @@ impl HostOpConfig {
         enable: bool,
     ) -> Result<(Limb<Fr>, Limb<Fr>), Error> {
         let r = self.assign_cell(region, *offset, &HostOpConfig::filtered_operand(), operand)?;
+        self.assign_operand_pieces(region, *offset, &operand)?;
         let op = self.assign_cell(region, *offset, &HostOpConfig::filtered_opcode(), opcode)?;
         self.assign_cell(region, *offset, &HostOpConfig::filtered_index(), index)?;
         self.assign_cell(region, *offset, &HostOpConfig::indicator(), ind)?;
@@ -223,6 +499,25 @@ impl HostOpConfig {
     }
 }
 
+/// The borrow into and the difference at each limb of (p - 1) - \sum_i limbs[i] * 2^{64i}
+/// together with whether the subtraction underflows, i.e. the limbs encode a value >= p.
+pub(crate) fn canonical_diffs(limbs: &[u64]) -> (Vec<(bool, u64)>, bool) {
+    let modulus = bytes_to_u64(&field_to_bytes(&-Fr::one()));
+    let mut borrow = false;
+    let diffs = limbs
+        .iter()
+        .zip(modulus.iter())
+        .map(|(l, m)| {
+            let (diff, b0) = m.overflowing_sub(*l);
+            let (diff, b1) = diff.overflowing_sub(borrow as u64);
+            let r = (borrow, diff);
+            borrow = b0 || b1;
+            r
+        })
+        .collect();
+    (diffs, borrow)
+}
+
 pub trait HostOpSelector {
     type Config: Clone + std::fmt::Debug;
     type Helper: Clone + Default;
@@ -232,7 +527,30 @@ pub trait HostOpSelector {
     ) -> Self::Config;
     fn construct(c: Self::Config) -> Self;
     fn opcodes() -> Vec<Fr>;
+    /// whether the filtered operands are constrained to be u64, which can be
+    /// turned off by the feature unchecked-host-operands when the shared
+    /// operands are already checked by zkWasm
+    fn check_u64_operands() -> bool {
+        !cfg!(feature = "unchecked-host-operands")
+    }
     fn max_rounds(k: usize) -> usize;
+    /// number of selected entries consumed by one round of the host op, only
+    /// meaningful for host ops whose rounds are all of the same size
+    fn chunk_size() -> usize;
+    /// whether a round does not depend on the state left by its previous round,
+    /// so that a split circuit is allowed to start with it
+    fn is_fresh_round(_round: &[ExternalHostCallEntry]) -> bool {
+        true
+    }
+    /// number of selected entries of the round that starts at `entries[0]`,
+    /// none if no round of the host op starts with that entry
+    fn round_size(_entries: &[ExternalHostCallEntry]) -> Option<usize> {
+        Some(Self::chunk_size())
+    }
+    /// whether the rounds can be proved in one circuit of size k
+    fn fits_in_circuit(rounds: &[&[ExternalHostCallEntry]], k: usize) -> bool {
+        rounds.len() <= Self::max_rounds(k)
+    }
     fn assign(
         region: &Region<Fr>,
         k: usize,
@@ -255,14 +573,29 @@ pub trait HostOpSelector {
     ) -> Result<(), Error>;
 }
 
+/// Number of chained product columns needed to filter `n` opcodes
+pub fn ops_chain_size(n: usize) -> usize {
+    if n <= 5 {
+        0
+    } else {
+        (n - 4 + 2) / 3
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HostOpChipConfig {
+    pub host: HostOpConfig,
+    pub ops_chain: Vec<Column<Advice>>,
+}
+
 pub struct HostOpChip<F: FieldExt, S: HostOpSelector> {
-    pub config: HostOpConfig,
+    pub config: HostOpChipConfig,
     pub selector_chip_config: S::Config,
     _marker: PhantomData<(F, S)>,
 }
 
 impl<F: FieldExt, S: HostOpSelector> Chip<F> for HostOpChip<F, S> {
-    type Config = HostOpConfig;
+    type Config = HostOpChipConfig;
     type Loaded = ();
 
     fn config(&self) -> &Self::Config {
@@ -299,15 +632,48 @@ impl<S: HostOpSelector> HostOpChip<Fr, S> {
             shared_advices[3].clone(),
             shared_advices[4].clone(),
             shared_advices[5].clone(),
+            cs.advice_column(),
+            cs.advice_column(),
+            cs.advice_column(),
+            cs.advice_column(),
+            cs.advice_column(),
+            cs.advice_column(),
+            cs.advice_column(),
+            cs.advice_column(),
+            cs.advice_column(),
         ];
         witness.map(|x| cs.enable_equality(x));
-        let fixed = [cs.fixed_column(), cs.fixed_column(), cs.fixed_column()];
+        let fixed = [0; 7].map(|_| cs.fixed_column());
         fixed.map(|x| cs.enable_equality(x));
         let selector = [];
 
+        let ops_chain = (0..ops_chain_size(S::opcodes().len()))
+            .map(|_| cs.advice_column())
+            .collect::<Vec<_>>();
+
         let config = HostOpConfig::new(witness, fixed, selector);
-        config.configure(cs, &S::opcodes());
-        config
+        config.configure(cs, &S::opcodes(), &ops_chain, S::check_u64_operands());
+        HostOpChipConfig {
+            host: config,
+            ops_chain,
+        }
+    }
+
+    fn assign_ops_chain(
+        &self,
+        region: &Region<Fr>,
+        offset: usize,
+        chain: &Vec<Fr>,
+    ) -> Result<(), Error> {
+        for (col, value) in self.config.ops_chain.iter().zip(chain.iter()) {
+            region.assign_advice(
+                || "ops chain",
+                col.clone(),
+                offset,
+                || value_for_assign!(value.clone()),
+            )?;
+        }
+        Ok(())
     }
 
     pub fn assign(
@@ -317,7 +683,7 @@ impl<S: HostOpSelector> HostOpChip<Fr, S> {
         arg_offset: &mut usize,
         shared_operands: &Vec<Fr>,
         shared_opcodes: &Vec<Fr>,
-    ) -> Result<Vec<Limb<Fr>>, Error> {
+    ) -> Result<(Vec<Limb<Fr>>, Limb<Fr>), Error> {
         let selected_length =
             get_selected_entries(shared_operands, shared_opcodes, &S::opcodes()).len();
         let get_ops = |op| {
@@ -326,41 +692,63 @@ impl<S: HostOpSelector> HostOpChip<Fr, S> {
                 mult = mult * (op - i)
             }
 
+            let mut chain = vec![];
+            if S::opcodes().len() > 5 {
+                let mut acc = mult.clone();
+                for group in S::opcodes()[4..].chunks(3) {
+                    acc = group.iter().fold(acc, |acc, x| acc * (op - x));
+                    chain.push(acc);
+                }
+            }
+
             let inv: Option<Fr> = S::opcodes()
                 .iter()
                 .skip(4)
                 .fold(mult.clone(), |acc, x| acc * (op - x))
                 .invert()
                 .into();
-            (mult, inv.unwrap_or(Fr::one()))
+            (mult, chain, inv.unwrap_or(Fr::one()))
         };
 
-        let (default_mult, default_inv) = get_ops(Fr::zero());
+        let (default_mult, default_chain, default_inv) = get_ops(Fr::zero());
         let mut offset = 0;
         let mut index = selected_length;
         self.config
+            .host
             .assign_cell(region, offset, &HostOpConfig::shared_opcode(), Fr::zero())?;
 
-        self.config
-            .assign_cell(region, offset, &HostOpConfig::shared_operand(), Fr::zero())?;
+        self.config.host.assign_cell(
+            region,
+            offset,
+            &HostOpConfig::shared_operand(),
+            Fr::zero(),
+        )?;
 
-        self.config
-            .assign_cell(region, offset, &HostOpConfig::shared_operand(), Fr::zero())?;
+        self.config.host.assign_cell(
+            region,
+            offset,
+            &HostOpConfig::shared_operand(),
+            Fr::zero(),
+        )?;
 
         self.config
+            .host
             .assign_cell(region, offset, &HostOpConfig::ops(), default_mult)?;
 
         self.config
+            .host
             .assign_cell(region, offset, &HostOpConfig::inv(), default_inv)?;
 
-        let active_total_index = self.config.assign_cell(
+        self.assign_ops_chain(region, offset, &default_chain)?;
+
+        let active_total_index = self.config.host.assign_cell(
             region,
             offset,
             &HostOpConfig::shared_index(),
             Fr::from(index as u64),
         )?;
 
-        let selected_total_index = self.config.assign_cell(
+        let selected_total_index = self.config.host.assign_cell(
             region,
             offset,
             &HostOpConfig::filtered_index(),
@@ -375,50 +763,67 @@ impl<S: HostOpSelector> HostOpChip<Fr, S> {
 
         offset += 1;
         for opcode in shared_opcodes {
-            self.config.assign_cell(
+            self.config.host.assign_cell(
                 region,
                 offset,
                 &HostOpConfig::shared_opcode(),
                 opcode.clone(),
             )?;
-            self.config.assign_cell(
+            self.config.host.assign_cell(
                 region,
                 offset,
                 &HostOpConfig::shared_operand(),
                 shared_operands[offset - 1],
             )?;
-            self.config.assign_cell(
+            self.config.host.assign_cell(
                 region,
                 offset,
                 &HostOpConfig::shared_index(),
                 Fr::from(index as u64),
             )?;
-            let (mult, inv) = get_ops(opcode.clone());
+            let (mult, chain, inv) = get_ops(opcode.clone());
 
             self.config
+                .host
                 .assign_cell(region, offset, &HostOpConfig::ops(), mult)?;
 
             self.config
+                .host
                 .assign_cell(region, offset, &HostOpConfig::inv(), inv)?;
 
+            self.assign_ops_chain(region, offset, &chain)?;
+
             offset += 1;
             if S::opcodes().contains(&opcode) {
                 index -= 1;
             }
         }
 
-        let last_row = (1 << 22) - 1000;
+        let last_row = (1 << usize::min(k, 22)) - 1000;
 
         // Set the max sel for shared_ops
         for i in 0..last_row {
             self.config
+                .host
                 .assign_cell(region, i, &HostOpConfig::sel_shared(), Fr::one())?;
+            if i < 1 << 16 {
+                self.config.host.assign_cell(
+                    region,
+                    i,
+                    &HostOpConfig::u16_table(),
+                    Fr::from(i as u64),
+                )?;
+            }
             if i >= offset {
                 self.config
+                    .host
                     .assign_cell(region, i, &HostOpConfig::ops(), default_mult)?;
 
                 self.config
+                    .host
                     .assign_cell(region, i, &HostOpConfig::inv(), default_inv)?;
+
+                self.assign_ops_chain(region, i, &default_chain)?;
             }
         }
 
@@ -429,9 +834,212 @@ impl<S: HostOpSelector> HostOpChip<Fr, S> {
             &mut local_offset,
             shared_operands,
             shared_opcodes,
-            &self.config,
+            &self.config.host,
         )?;
         *arg_offset = local_offset;
-        Ok(arg_cells)
+        Ok((arg_cells, selected_total_index))
+    }
+}
+
+/// The filtered rows of the rounds of a host op selector, so that
+/// `TestSelector` can fill the host op chip without the selector chip.
+#[cfg(test)]
+pub(crate) trait TestRounds: HostOpSelector {
+    /// the selected entries of the round used to pad the unused rounds
+    fn default_round() -> Vec<((Fr, Fr), Fr)>;
+    fn assign_round(
+        region: &Region<Fr>,
+        offset: &mut usize,
+        group: &[((Fr, Fr), Fr)],
+        config: &HostOpConfig,
+        enable: bool,
+    ) -> Result<Vec<Limb<Fr>>, Error>;
+}
+
+/// A selector with the opcodes and the filtered rows of `S` but without its
+/// chip, which fits `ROUNDS` rounds into a small k for MockProver tests.
+#[cfg(test)]
+pub(crate) struct TestSelector<S: TestRounds, const ROUNDS: usize, const CHECK_U64: bool = true>(
+    PhantomData<S>,
+);
+
+#[cfg(test)]
+impl<S: TestRounds, const ROUNDS: usize, const CHECK_U64: bool> HostOpSelector
+    for TestSelector<S, ROUNDS, CHECK_U64>
+{
+    type Config = ();
+    type Helper = ();
+    fn configure(
+        _meta: &mut ConstraintSystem<Fr>,
+        _shared_advice: &Vec<Column<Advice>>,
+    ) -> Self::Config {
+        ()
+    }
+
+    fn construct(_c: Self::Config) -> Self {
+        TestSelector(PhantomData)
+    }
+
+    fn opcodes() -> Vec<Fr> {
+        S::opcodes()
+    }
+
+    fn check_u64_operands() -> bool {
+        CHECK_U64
+    }
+
+    fn max_rounds(_k: usize) -> usize {
+        ROUNDS
+    }
+
+    fn chunk_size() -> usize {
+        S::chunk_size()
+    }
+
+    fn assign(
+        region: &Region<Fr>,
+        _k: usize,
+        offset: &mut usize,
+        shared_operands: &Vec<Fr>,
+        shared_opcodes: &Vec<Fr>,
+        config: &HostOpConfig,
+    ) -> Result<Vec<Limb<Fr>>, Error> {
+        let selected_entries = get_selected_entries(shared_operands, shared_opcodes, &S::opcodes());
+        let total_used_instructions = selected_entries.len() / S::chunk_size();
+        assert!(total_used_instructions <= ROUNDS);
+
+        let mut r = vec![];
+        for group in selected_entries.chunks_exact(S::chunk_size()) {
+            r.append(&mut S::assign_round(region, offset, group, config, true)?);
+        }
+        let default_entries = S::default_round();
+        for _ in total_used_instructions..ROUNDS {
+            r.append(&mut S::assign_round(
+                region,
+                offset,
+                &default_entries,
+                config,
+                false,
+            )?);
+        }
+        Ok(r)
+    }
+
+    fn synthesize(
+        &mut self,
+        _offset: &mut usize,
+        _arg_cells: &Vec<Limb<Fr>>,
+        _region: &Region<Fr>,
+        _helper: &Self::Helper,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn synthesize_separate(
+        &mut self,
+        _arg_cells: &Vec<Limb<Fr>>,
+        _layouter: &impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical_diffs, ops_chain_size, HostOpSelector, TestSelector};
+    use crate::circuits::merkle::MerkleChip;
+    use crate::circuits::poseidon::PoseidonChip;
+    use crate::host::ForeignInst::{
+        Log, MerkleAddress, MerkleDelete, MerkleGet, MerkleGetRoot, MerkleSet, MerkleSetRoot,
+        PoseidonFinalize, PoseidonNew, PoseidonPush,
+    };
+    use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable, ForeignInst};
+    use crate::proof::build_host_circuit;
+    use crate::utils::{bytes_to_u64, field_to_bytes};
+    use ff::Field;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::Fr;
+
+    fn entry(op: ForeignInst, value: u64) -> ExternalHostCallEntry {
+        ExternalHostCallEntry {
+            op: op as usize,
+            value,
+            is_ret: false,
+        }
+    }
+
+    /// run the host circuit of a test selector, which fits the filter into k = 18
+    fn mock_host_circuit<S: HostOpSelector<Helper = ()>>(
+        entries: Vec<ExternalHostCallEntry>,
+    ) -> MockProver<Fr> {
+        let circuit = build_host_circuit::<S>(&ExternalHostCallEntryTable(entries), 18, ());
+        MockProver::run(18, &circuit, vec![]).unwrap()
+    }
+
+    fn merkle_round(op: ForeignInst) -> Vec<ExternalHostCallEntry> {
+        let mut r = vec![entry(MerkleAddress, 3)];
+        r.append(&mut vec![entry(MerkleSetRoot, 1); 4]);
+        r.append(&mut vec![entry(op, 2); 4]);
+        r.append(&mut vec![entry(MerkleGetRoot, 1); 4]);
+        r
+    }
+
+    #[test]
+    fn test_canonical_diffs() {
+        let max = bytes_to_u64(&field_to_bytes(&-Fr::one()));
+        let (diffs, overflow) = canonical_diffs(&max);
+        assert!(!overflow);
+        assert!(diffs.iter().all(|(borrow, diff)| !borrow && *diff == 0));
+
+        let (diffs, overflow) = canonical_diffs(&[1, 0, 0, 0]);
+        assert!(!overflow);
+        assert_eq!(diffs[0], (false, max[0] - 1));
+
+        let (diffs, overflow) = canonical_diffs(&[max[0] + 1, 0, 0, 0]);
+        assert!(!overflow);
+        assert_eq!(diffs[0], (false, u64::MAX));
+        assert_eq!(diffs[1], (true, max[1] - 1));
+
+        // the modulus itself and the largest u64 limbs both alias a canonical value
+        let modulus = [max[0] + 1, max[1], max[2], max[3]];
+        assert!(canonical_diffs(&modulus).1);
+        assert!(canonical_diffs(&[u64::MAX; 4]).1);
+    }
+
+    #[test]
+    fn test_filter_chained_opcodes() {
+        type Selector = TestSelector<MerkleChip<Fr, 32>, 4>;
+        assert_eq!(Selector::opcodes().len(), 6);
+        assert!(ops_chain_size(Selector::opcodes().len()) > 0);
+
+        // every merkle opcode interleaved with opcodes that are filtered out
+        let mut entries = vec![entry(Log, 1)];
+        for op in [MerkleGet, MerkleSet, MerkleDelete] {
+            entries.append(&mut merkle_round(op));
+            entries.push(entry(PoseidonPush, 5));
+        }
+        let prover = mock_host_circuit::<Selector>(entries);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_aliased_field_operands() {
+        type Selector = TestSelector<PoseidonChip<Fr, 9, 8>, 1>;
+        let hash_round = |input: [u64; 4]| {
+            let mut r = vec![entry(PoseidonNew, 1)];
+            r.extend(input.map(|limb| entry(PoseidonPush, limb)));
+            r.append(&mut vec![entry(PoseidonPush, 0); 7 * 4]);
+            r.append(&mut vec![entry(PoseidonFinalize, 0); 4]);
+            r
+        };
+
+        let prover = mock_host_circuit::<Selector>(hash_round([1, 0, 0, 0]));
+        assert_eq!(prover.verify(), Ok(()));
+
+        // p + 1 in u64 limbs merges into the same field element 1
+        let max = bytes_to_u64(&field_to_bytes(&-Fr::one()));
+        let aliased = [max[0] + 2, max[1], max[2], max[3]];
+        let prover = mock_host_circuit::<Selector>(hash_round(aliased));
+        assert!(prover.verify().is_err());
     }
 }
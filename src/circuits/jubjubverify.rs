@@ -0,0 +1,334 @@
+use crate::circuits::babyjub::{AltJubChip, Point};
+use crate::circuits::poseidon::{PoseidonChip, PoseidonGateConfig};
+use crate::circuits::{CommonGateConfig, Limb};
+use crate::host::jubjub::{BASE, SUBGROUP_ORDER};
+use crate::host::poseidon::POSEIDON_HASHER_SPEC;
+use crate::host::poseidon::{PREFIX_CHALLENGE, PREFIX_POINT, PREFIX_SCALAR};
+use crate::utils::bn_to_field;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Chip, Region};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error};
+
+/* Verifies the eddsa signature (R, s) of msg by the public key A over the alt jubjub curve.
+ * The challenge h = poseidon(challenge, point, R, point, A, scalar, msg) is computed by two
+ * rounds of the width 9 hasher and the chip enforces R + h.A = s.B for the base point B
+ * and s < SUBGROUP_ORDER.
+ */
+
+pub struct JubjubVerifyChip<F: FieldExt> {
+    pub config: CommonGateConfig,
+    pub extend: PoseidonGateConfig,
+    jubjub_chip: AltJubChip<F>,
+    challenge_hasher_chip: PoseidonChip<F, 9, 8>,
+    base: Point<F>,
+    prefix: [Limb<F>; 3],
+    zero: Limb<F>,
+    one: Limb<F>,
+}
+
+impl<F: FieldExt> Chip<F> for JubjubVerifyChip<F> {
+    type Config = CommonGateConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl JubjubVerifyChip<Fr> {
+    pub fn new(config: CommonGateConfig, extend: PoseidonGateConfig) -> Self {
+        JubjubVerifyChip {
+            jubjub_chip: AltJubChip::new(config.clone()),
+            challenge_hasher_chip: PoseidonChip::construct(
+                config.clone(),
+                extend.clone(),
+                POSEIDON_HASHER_SPEC.clone(),
+            ),
+            config,
+            extend,
+            base: Point {
+                x: Limb::new(None, BASE.x),
+                y: Limb::new(None, BASE.y),
+            },
+            prefix: [PREFIX_CHALLENGE, PREFIX_POINT, PREFIX_SCALAR]
+                .map(|x| Limb::new(None, Fr::from(x))),
+            zero: Limb::new(None, Fr::zero()),
+            one: Limb::new(None, Fr::one()),
+        }
+    }
+
+    pub fn initialize(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &Region<Fr>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        self.jubjub_chip.initialize(config, region, offset)?;
+        self.challenge_hasher_chip
+            .initialize(config, region, offset)?;
+        self.base = Point {
+            x: config.assign_constant(region, &mut (), offset, &BASE.x)?,
+            y: config.assign_constant(region, &mut (), offset, &BASE.y)?,
+        };
+        let mut prefix = vec![];
+        for p in [PREFIX_CHALLENGE, PREFIX_POINT, PREFIX_SCALAR] {
+            prefix.push(config.assign_constant(region, &mut (), offset, &Fr::from(p))?);
+        }
+        self.prefix = prefix.try_into().unwrap();
+        self.zero = config.assign_constant(region, &mut (), offset, &Fr::zero())?;
+        self.one = config.assign_constant(region, &mut (), offset, &Fr::one())?;
+        Ok(())
+    }
+
+    pub fn configure(
+        cs: &mut ConstraintSystem<Fr>,
+        shared_advices: &Vec<Column<Advice>>,
+    ) -> (CommonGateConfig, PoseidonGateConfig) {
+        PoseidonChip::<Fr, 9, 8>::configure(cs, shared_advices)
+    }
+
+    fn assign_challenge(
+        &mut self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        pk: &Point<Fr>,
+        sig_r: &Point<Fr>,
+        msg: &Limb<Fr>,
+    ) -> Result<Limb<Fr>, Error> {
+        let [prefix_challenge, prefix_point, prefix_scalar] = self.prefix.clone();
+        let first = [
+            prefix_challenge,
+            prefix_point.clone(),
+            sig_r.x.clone(),
+            sig_r.y.clone(),
+            prefix_point,
+            pk.x.clone(),
+            pk.y.clone(),
+            prefix_scalar,
+        ];
+        let mut second = vec![msg.clone(), self.one.clone()];
+        second.resize_with(8, || self.zero.clone());
+        let (zero, one) = (self.zero.clone(), self.one.clone());
+        self.challenge_hasher_chip
+            .get_permute_result(region, offset, &first, &one)?;
+        self.challenge_hasher_chip.get_permute_result(
+            region,
+            offset,
+            &second.try_into().unwrap(),
+            &zero,
+        )
+    }
+
+    pub fn assign_verify(
+        &mut self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        pk: &Point<Fr>,
+        sig_r: &Point<Fr>,
+        sig_s: &Limb<Fr>,
+        msg: &Limb<Fr>,
+    ) -> Result<(), Error> {
+        for point in [pk, sig_r] {
+            self.jubjub_chip.assert_on_curve(region, offset, point)?;
+            if cfg!(feature = "jubjub-subgroup") {
+                self.jubjub_chip.assert_in_subgroup(region, offset, point)?;
+            }
+        }
+        // s is reduced so that (R, s + SUBGROUP_ORDER) is not another valid signature
+        self.config.less_than_constant(
+            region,
+            &mut (),
+            offset,
+            sig_s,
+            &bn_to_field(&SUBGROUP_ORDER),
+            256,
+        )?;
+        let h = self.assign_challenge(region, offset, pk, sig_r, msg)?;
+        let h_pk = self.jubjub_chip.mul_scalar(region, offset, &h, pk)?;
+        let lhs = self.jubjub_chip.add(region, offset, sig_r, &h_pk)?;
+        let rhs = self
+            .jubjub_chip
+            .mul_scalar(region, offset, sig_s, &self.base)?;
+        region.constrain_equal(lhs.x.get_the_cell().cell(), rhs.x.get_the_cell().cell())?;
+        region.constrain_equal(lhs.y.get_the_cell().cell(), rhs.y.get_the_cell().cell())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JubjubVerifyChip;
+    use crate::circuits::babyjub::Point;
+    use crate::circuits::poseidon::PoseidonGateConfig;
+    use crate::circuits::{CommonGateConfig, Limb};
+    use crate::host::jubjub::{
+        challenge, verify, verify_with_challenge, Point as HostPoint, BASE, SUBGROUP_ORDER,
+    };
+    use crate::utils::{bn_to_field, field_to_bn};
+    use crate::value_for_assign;
+    use halo2_proofs::circuit::floor_planner::FlatFloorPlanner;
+    use halo2_proofs::circuit::Layouter;
+    use halo2_proofs::dev::{MockProver, VerifyFailure};
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error};
+    use num_bigint::BigUint;
+
+    #[derive(Clone, Debug, Default)]
+    struct TestCircuit {
+        // pk.x, pk.y, sig_r.x, sig_r.y, sig_s, msg
+        inputs: [Fr; 6],
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        commonconfig: CommonGateConfig,
+        poseidonconfig: PoseidonGateConfig,
+        input: Column<Advice>,
+    }
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = FlatFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let witness = vec![
+                cs.advice_column(),
+                cs.advice_column(),
+                cs.advice_column(),
+                cs.advice_column(),
+                cs.advice_column(),
+            ];
+            let (commonconfig, poseidonconfig) = JubjubVerifyChip::<Fr>::configure(cs, &witness);
+            let input = cs.advice_column();
+            cs.enable_equality(input);
+            TestConfig {
+                commonconfig,
+                poseidonconfig,
+                input,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "assign jubjub verify test",
+                |region| {
+                    let mut chip = JubjubVerifyChip::<Fr>::new(
+                        config.commonconfig.clone(),
+                        config.poseidonconfig.clone(),
+                    );
+                    let mut inputs = vec![];
+                    for (i, v) in self.inputs.iter().enumerate() {
+                        let c = region.assign_advice(
+                            || format!("assign input"),
+                            config.input,
+                            i,
+                            || value_for_assign!(v.clone()),
+                        )?;
+                        inputs.push(Limb::new(Some(c), v.clone()));
+                    }
+                    let mut offset = 0;
+                    chip.initialize(&config.commonconfig, &region, &mut offset)?;
+                    chip.assign_verify(
+                        &region,
+                        &mut offset,
+                        &Point {
+                            x: inputs[0].clone(),
+                            y: inputs[1].clone(),
+                        },
+                        &Point {
+                            x: inputs[2].clone(),
+                            y: inputs[3].clone(),
+                        },
+                        &inputs[4],
+                        &inputs[5],
+                    )?;
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    const SK: u64 = 0x1234_5678_9abc_def0;
+
+    /// Sign msg by SK for the public key pk, which is SK.B up to a point of small order
+    fn sign(pk: &HostPoint, msg: &Fr) -> (HostPoint, BigUint) {
+        let nonce = BigUint::from(0x0fed_cba9_8765_4321u64);
+        let sig_r = BASE.mul_scalar(&nonce);
+        let h = field_to_bn(&challenge(&sig_r, pk, msg));
+        let sig_s = (nonce + h * SK) % &*SUBGROUP_ORDER;
+        (sig_r, sig_s)
+    }
+
+    fn verify_circuit(
+        pk: &HostPoint,
+        sig_r: &HostPoint,
+        sig_s: &BigUint,
+        msg: &Fr,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let circuit = TestCircuit {
+            inputs: [pk.x, pk.y, sig_r.x, sig_r.y, bn_to_field(sig_s), *msg],
+        };
+        MockProver::run(18, &circuit, vec![]).unwrap().verify()
+    }
+
+    #[test]
+    fn test_jubjub_verify_circuit() {
+        let msg = Fr::from(42u64);
+        let pk = BASE.mul_scalar(&BigUint::from(SK));
+        let (sig_r, sig_s) = sign(&pk, &msg);
+        assert!(verify_circuit(&pk, &sig_r, &sig_s, &msg).is_ok());
+
+        // forged signatures
+        assert!(verify_circuit(&pk, &sig_r, &sig_s, &Fr::from(43u64)).is_err());
+        assert!(verify_circuit(&pk, &sig_r, &(&sig_s + 1u64), &msg).is_err());
+
+        // R + h.A = s.B still holds but s is not reduced
+        assert!(verify_circuit(&pk, &sig_r, &(&sig_s + &*SUBGROUP_ORDER), &msg).is_err());
+    }
+
+    #[test]
+    fn test_jubjub_verify_off_curve() {
+        let msg = Fr::from(42u64);
+        let mut pk = BASE.mul_scalar(&BigUint::from(SK));
+        pk.y = pk.y + Fr::one();
+        assert!(!pk.is_on_curve());
+        let (sig_r, sig_s) = sign(&pk, &msg);
+        assert!(!verify(&pk, &sig_r, &sig_s, &msg));
+        assert!(verify_circuit(&pk, &sig_r, &sig_s, &msg).is_err());
+    }
+
+    #[test]
+    fn test_jubjub_verify_small_order() {
+        // A + T for the point T of order 2 acts as A when the challenge is even
+        let pk = BASE.mul_scalar(&BigUint::from(SK)).add(&HostPoint {
+            x: Fr::zero(),
+            y: -Fr::one(),
+        });
+        assert!(pk.is_on_curve() && !pk.is_in_subgroup());
+        let (msg, (sig_r, sig_s)) = (0u64..)
+            .map(|m| (Fr::from(m), sign(&pk, &Fr::from(m))))
+            .find(|(msg, (sig_r, _))| !field_to_bn(&challenge(sig_r, &pk, msg)).bit(0))
+            .unwrap();
+        let h = challenge(&sig_r, &pk, &msg);
+        assert!(verify_with_challenge(&pk, &sig_r, &sig_s, &h));
+
+        // the host and the circuit both reject it only with the subgroup check
+        let accepted = verify(&pk, &sig_r, &sig_s, &msg);
+        assert_eq!(accepted, !cfg!(feature = "jubjub-subgroup"));
+        assert_eq!(verify_circuit(&pk, &sig_r, &sig_s, &msg).is_ok(), accepted);
+    }
+}
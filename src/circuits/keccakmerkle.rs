@@ -0,0 +1,192 @@
+use crate::circuits::keccak256::{KeccakChip, KeccakGateConfig};
+use crate::circuits::Limb;
+use crate::host::keccak256::RATE_LANES;
+use crate::host::merkle::MerkleProof;
+use crate::host::ForeignInst::KeccakMerkleSet;
+use crate::utils::bytes_to_u64;
+use crate::utils::field_to_u64;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Chip, Region};
+use halo2_proofs::plonk::Advice;
+use halo2_proofs::plonk::Column;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::plonk::Error;
+
+/* The keccak variant of MerkleChip which agrees with the merkle trees of solidity contracts.
+ * A leaf hash is keccak256(data) of the 32 bytes data and a node hash is keccak256(left || right).
+ * Digests do not fit in one field element so the roots, the values and the assists
+ * are kept as four little endian u64 lanes.
+ */
+
+pub struct KeccakMerkleChip<F: FieldExt, const D: usize> {
+    pub config: KeccakGateConfig,
+    keccak_chip: KeccakChip<F>,
+    assist: [[Limb<F>; 4]; D],
+    zero: Limb<F>,
+    one: Limb<F>,
+    pad_end: Limb<F>,
+}
+
+impl<F: FieldExt, const D: usize> Chip<F> for KeccakMerkleChip<F, D> {
+    type Config = KeccakGateConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: FieldExt, const D: usize> KeccakMerkleChip<F, D> {
+    pub fn new(config: KeccakGateConfig) -> Self {
+        KeccakMerkleChip {
+            keccak_chip: KeccakChip::construct(config.clone()),
+            config,
+            assist: [0; D].map(|_| [0; 4].map(|_| Limb::new(None, F::zero()))),
+            zero: Limb::new(None, F::zero()),
+            one: Limb::new(None, F::one()),
+            pad_end: Limb::new(None, F::from(1u64 << 63)),
+        }
+    }
+
+    pub fn proof_height() -> usize {
+        D
+    }
+
+    pub fn initialize(
+        &mut self,
+        config: &KeccakGateConfig,
+        region: &Region<F>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        self.keccak_chip.initialize(config, region, offset)?;
+        self.zero = config
+            .common
+            .assign_constant(region, &mut (), offset, &F::zero())?;
+        self.one = config
+            .common
+            .assign_constant(region, &mut (), offset, &F::one())?;
+        self.pad_end =
+            config
+                .common
+                .assign_constant(region, &mut (), offset, &F::from(1u64 << 63))?;
+        Ok(())
+    }
+
+    pub fn configure(
+        cs: &mut ConstraintSystem<F>,
+        shared_advices: &Vec<Column<Advice>>,
+    ) -> KeccakGateConfig {
+        KeccakChip::configure(cs, shared_advices)
+    }
+
+    /// keccak256 of the lanes of a message with less than 17 lanes,
+    /// the padding lanes of the single block are constants.
+    fn hash_lanes(
+        &mut self,
+        region: &Region<F>,
+        offset: &mut usize,
+        lanes: &[Limb<F>],
+    ) -> Result<[Limb<F>; 4], Error> {
+        assert!(lanes.len() < RATE_LANES);
+        let mut block = lanes.to_vec();
+        block.push(self.one.clone());
+        block.resize_with(RATE_LANES - 1, || self.zero.clone());
+        block.push(self.pad_end.clone());
+        let one = self.one.clone();
+        self.keccak_chip
+            .get_permute_result(region, offset, &block.try_into().unwrap(), &one)
+    }
+
+    fn select_lanes(
+        &self,
+        region: &Region<F>,
+        offset: &mut usize,
+        cond: &Limb<F>,
+        f: &[Limb<F>; 4],
+        t: &[Limb<F>; 4],
+    ) -> Result<[Limb<F>; 4], Error> {
+        let mut lanes = vec![];
+        for (f, t) in f.iter().zip(t.iter()) {
+            lanes.push(
+                self.config
+                    .common
+                    .select(region, &mut (), offset, cond, f, t, 0)?,
+            );
+        }
+        Ok(lanes.try_into().unwrap())
+    }
+
+    pub fn assign_proof(
+        &mut self,
+        region: &Region<F>,
+        offset: &mut usize,
+        proof: &MerkleProof<[u8; 32], D>,
+        opcode: &Limb<F>,
+        address: &Limb<F>,
+        root: &[Limb<F>; 4],
+        new_root: &[Limb<F>; 4],
+        value: &[Limb<F>; 4],
+    ) -> Result<(), Error> {
+        let is_set = self.config.common.eq_constant(
+            region,
+            &mut (),
+            offset,
+            opcode,
+            &F::from(KeccakMerkleSet as u64),
+        )?;
+
+        let mut new_assist = vec![];
+        for hash in proof.assist.iter() {
+            let [a, b, c, d] = bytes_to_u64(hash).map(|x| Some(Limb::new(None, F::from(x))));
+            let lanes = self.config.common.assign_witness(
+                region,
+                &mut (),
+                offset,
+                [a, b, c, d, None],
+                0,
+            )?;
+            new_assist.push(lanes.try_into().unwrap());
+        }
+
+        // a set must use the same assists as the get before it
+        let mut compare_assist = vec![];
+        for (old, new) in self.assist.iter().zip(new_assist.iter()) {
+            let lanes = self.select_lanes(region, offset, &is_set, new, old)?;
+            for (a, b) in lanes.iter().zip(new.iter()) {
+                region.constrain_equal(a.get_the_cell().cell(), b.get_the_cell().cell())?;
+            }
+            compare_assist.push(lanes);
+        }
+        self.assist = compare_assist.clone().try_into().unwrap();
+
+        let mut positions = vec![];
+        self.config
+            .common
+            .decompose_limb(region, &mut (), offset, &address, &mut positions, D)?;
+
+        let initial_hash = self.hash_lanes(region, offset, value)?;
+        assert_eq!(
+            initial_hash.clone().map(|x| field_to_u64(&x.value)),
+            bytes_to_u64(&proof.source)
+        );
+
+        // position = 0 means assist is at right else assist is at left
+        let mut acc = initial_hash;
+        for (position, assist) in positions.iter().rev().zip(compare_assist.iter().rev()) {
+            let left = self.select_lanes(region, offset, position, &acc, assist)?;
+            let right = self.select_lanes(region, offset, position, assist, &acc)?;
+            acc = self.hash_lanes(region, offset, &[left, right].concat())?;
+        }
+
+        let desired_root = self.select_lanes(region, offset, &is_set, root, new_root)?;
+        for (a, b) in desired_root.iter().zip(acc.iter()) {
+            assert_eq!(a.value, b.value);
+            region.constrain_equal(a.get_the_cell().cell(), b.get_the_cell().cell())?;
+        }
+        Ok(())
+    }
+}
@@ -18,7 +18,7 @@ use crate::host::poseidon::MERKLE_HASHER_SPEC;
 use crate::host::poseidon::MERKLE_LEAF_HASHER_SPEC;
 type MerkleDataHasherChip<F> = PoseidonChip<F, 3, 2>;
 
-use crate::host::ForeignInst::MerkleSet;
+use crate::host::ForeignInst::{MerkleDelete, MerkleSet};
 use halo2_proofs::pairing::bn256::Fr;
 
 /* Given a merkel tree eg1 with height=3:
@@ -104,7 +104,9 @@ impl<const D: usize> MerkleChip<Fr, D> {
         offset: &mut usize,
     ) -> Result<(), Error> {
         self.merkle_hasher_chip.initialize(config, region, offset)?;
-        self.data_hasher_chip.initialize(config, region, offset)
+        self.data_hasher_chip.initialize(config, region, offset)?;
+        self.state.zero = config.assign_constant(region, &mut (), offset, &Fr::zero())?;
+        Ok(())
     }
 
     pub fn configure(
@@ -134,6 +136,77 @@ impl<const D: usize> MerkleChip<Fr, D> {
             opcode,
             &Fr::from(MerkleSet as u64),
         )?;
+        let is_delete = self.config.eq_constant(
+            region,
+            &mut (),
+            offset,
+            opcode,
+            &Fr::from(MerkleDelete as u64),
+        )?;
+        // a delete updates the root as a set of the default leaf of zero values
+        let is_update = self.config.sum_with_constant(
+            region,
+            &mut (),
+            offset,
+            vec![(&is_set, Fr::one()), (&is_delete, Fr::one())],
+            None,
+        )?;
+        let zero = self.state.zero.clone();
+        let value = value.map(|v| {
+            self.config
+                .select(region, &mut (), offset, &is_delete, v, &zero, 0)
+                .unwrap()
+        });
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature="complex-leaf")] {
+                let values =
+                    [
+                    value[0].clone(),
+                    value[1].clone(),
+                    self.state.one.clone(),
+                    self.state.zero.clone(),
+                    self.state.zero.clone(),
+                    self.state.zero.clone(),
+                    self.state.zero.clone(),
+                    self.state.zero.clone(),
+                    ];
+            } else {
+                let values =
+                    [
+                    value[0].clone(),
+                    value[1].clone(),
+                    ];
+            }
+        };
+
+        let leaf_hash = self.data_hasher_chip.get_permute_result(
+            region,
+            offset,
+            &values,
+            &self.state.one.clone(),
+        )?;
+
+        self.assign_path(
+            region, offset, proof, &is_update, address, root, new_root, leaf_hash,
+        )
+    }
+
+    /// Constrain the path of the proof from the leaf hash to the root, or to
+    /// the new root when is_set holds. The assist of a set is constrained to
+    /// be the assist of the last proof.
+    pub fn assign_path(
+        &mut self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        proof: &MerkleProof<[u8; 32], D>,
+        is_set: &Limb<Fr>,
+        address: &Limb<Fr>,
+        root: &Limb<Fr>,
+        new_root: &Limb<Fr>,
+        leaf_hash: Limb<Fr>,
+    ) -> Result<(), Error> {
+        assert_eq!(field_to_bytes(&leaf_hash.value), proof.source);
 
         let fills = proof
             .assist
@@ -163,7 +236,7 @@ impl<const D: usize> MerkleChip<Fr, D> {
             .zip(new_assist.iter())
             .map(|(old, new)| {
                 self.config
-                    .select(region, &mut (), offset, &is_set, &new, &old, 0)
+                    .select(region, &mut (), offset, is_set, &new, &old, 0)
                     .unwrap()
             })
             .collect::<Vec<_>>()
@@ -179,41 +252,11 @@ impl<const D: usize> MerkleChip<Fr, D> {
             .decompose_limb(region, &mut (), offset, &address, &mut positions, D)?;
 
         // position = 0 means assist is at right else assist is at left
-        cfg_if::cfg_if! {
-            if #[cfg(feature="complex-leaf")] {
-                let values =
-                    [
-                    value[0].clone(),
-                    value[1].clone(),
-                    self.state.one.clone(),
-                    self.state.zero.clone(),
-                    self.state.zero.clone(),
-                    self.state.zero.clone(),
-                    self.state.zero.clone(),
-                    self.state.zero.clone(),
-                    ];
-            } else {
-                let values =
-                    [
-                    value[0].clone(),
-                    value[1].clone(),
-                    ];
-            }
-        };
-
-        let initial_hash = self.data_hasher_chip.get_permute_result(
-            region,
-            offset,
-            &values,
-            &self.state.one.clone(),
-        )?;
-        assert_eq!(field_to_bytes(&initial_hash.value), proof.source);
-
         let final_hash = positions
             .iter()
             .rev()
             .zip(compare_assist.iter().rev())
-            .fold(initial_hash, |acc, (position, assist)| {
+            .fold(leaf_hash, |acc, (position, assist)| {
                 let left = self
                     .config
                     .select(region, &mut (), offset, &position, &acc, &assist, 0)
@@ -230,10 +273,11 @@ impl<const D: usize> MerkleChip<Fr, D> {
                 hash
             });
 
+        // a root which differs from the path fails the copy constraint instead of
+        // panicking, e.g. a delete that claims a new root other than the default one
         let desired_root =
             self.config
-                .select(region, &mut (), offset, &is_set, root, new_root, 0)?;
-        assert_eq!(desired_root.value, final_hash.value);
+                .select(region, &mut (), offset, is_set, root, new_root, 0)?;
         region.constrain_equal(
             desired_root.cell.as_ref().unwrap().cell(),
             final_hash.cell.as_ref().unwrap().cell(),
@@ -0,0 +1,133 @@
+use crate::circuits::merkle::MerkleChip;
+use crate::circuits::poseidon::PoseidonChip;
+use crate::circuits::poseidon::PoseidonGateConfig;
+use crate::circuits::CommonGateConfig;
+use crate::circuits::Limb;
+use crate::host::merkle::MerkleProof;
+use crate::host::poseidon::POSEIDON_HASHER_SPEC;
+use crate::host::ForeignInst::MerkleWideSet;
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::circuit::{Chip, Region};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::Advice;
+use halo2_proofs::plonk::Column;
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::plonk::Error;
+
+/* The variant of MerkleChip whose leaves are N field elements.
+ * A leaf hash is the width 9 poseidon sponge over the N words of the leaf,
+ * the path from the leaf to the root is constrained as in MerkleChip.
+ */
+
+const RATE: usize = 8;
+
+pub struct MerkleWideChip<F: FieldExt, const D: usize, const N: usize> {
+    pub merkle_chip: MerkleChip<F, D>,
+    leaf_hasher_chip: PoseidonChip<F, 9, RATE>,
+    zero: Limb<F>,
+    one: Limb<F>,
+}
+
+impl<F: FieldExt, const D: usize, const N: usize> Chip<F> for MerkleWideChip<F, D, N> {
+    type Config = CommonGateConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.merkle_chip.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<const D: usize, const N: usize> MerkleWideChip<Fr, D, N> {
+    pub fn new(config: CommonGateConfig, extend: PoseidonGateConfig) -> Self {
+        MerkleWideChip {
+            leaf_hasher_chip: PoseidonChip::construct(
+                config.clone(),
+                extend.clone(),
+                POSEIDON_HASHER_SPEC.clone(),
+            ),
+            merkle_chip: MerkleChip::new(config, extend),
+            zero: Limb::new(None, Fr::zero()),
+            one: Limb::new(None, Fr::one()),
+        }
+    }
+
+    pub fn proof_height() -> usize {
+        D
+    }
+
+    pub fn initialize(
+        &mut self,
+        config: &CommonGateConfig,
+        region: &Region<Fr>,
+        offset: &mut usize,
+    ) -> Result<(), Error> {
+        self.merkle_chip.initialize(config, region, offset)?;
+        self.leaf_hasher_chip.initialize(config, region, offset)?;
+        self.zero = config.assign_constant(region, &mut (), offset, &Fr::zero())?;
+        self.one = config.assign_constant(region, &mut (), offset, &Fr::one())?;
+        Ok(())
+    }
+
+    pub fn configure(
+        cs: &mut ConstraintSystem<Fr>,
+        shared_advices: &Vec<Column<Advice>>,
+    ) -> (CommonGateConfig, PoseidonGateConfig) {
+        PoseidonChip::<Fr, 9, RATE>::configure(cs, shared_advices)
+    }
+
+    /// The poseidon sponge hash of the words of a leaf, the words are padded
+    /// by one and zeros to full blocks of RATE as in the host hasher.
+    fn hash_leaf(
+        &mut self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        values: &[Limb<Fr>; N],
+    ) -> Result<Limb<Fr>, Error> {
+        let mut words = values.to_vec();
+        words.push(self.one.clone());
+        words.resize_with((words.len() + RATE - 1) / RATE * RATE, || self.zero.clone());
+        let mut hash = None;
+        for (i, block) in words.chunks_exact(RATE).enumerate() {
+            let reset = if i == 0 {
+                self.one.clone()
+            } else {
+                self.zero.clone()
+            };
+            hash = Some(self.leaf_hasher_chip.get_permute_result(
+                region,
+                offset,
+                &block.to_vec().try_into().unwrap(),
+                &reset,
+            )?);
+        }
+        Ok(hash.unwrap())
+    }
+
+    pub fn assign_wide_proof(
+        &mut self,
+        region: &Region<Fr>,
+        offset: &mut usize,
+        proof: &MerkleProof<[u8; 32], D>,
+        opcode: &Limb<Fr>,
+        address: &Limb<Fr>,
+        root: &Limb<Fr>,
+        new_root: &Limb<Fr>,
+        values: &[Limb<Fr>; N],
+    ) -> Result<(), Error> {
+        let is_set = self.merkle_chip.config.eq_constant(
+            region,
+            &mut (),
+            offset,
+            opcode,
+            &Fr::from(MerkleWideSet as u64),
+        )?;
+        let leaf_hash = self.hash_leaf(region, offset, values)?;
+        self.merkle_chip.assign_path(
+            region, offset, proof, &is_set, address, root, new_root, leaf_hash,
+        )
+    }
+}
@@ -3,9 +3,13 @@ pub mod babyjub;
 pub mod bits_arith;
 // pub mod bls;
 pub mod bn256;
+pub mod combined;
 pub mod host;
+pub mod jubjubverify;
 pub mod keccak256;
+pub mod keccakmerkle;
 pub mod merkle;
+pub mod merklewide;
 pub mod poseidon;
 pub mod range;
 //pub(crate) mod keccak_arith_table;
@@ -207,6 +211,104 @@ impl CommonGateConfig {
         Ok(l[4].clone())
     }
 
+    /// Constrain limb < constant without the range lookup: scan the big endian
+    /// bits of the limb with the flags eq (the bits so far equal the constant)
+    /// and lt (the bits so far are below the constant). The bits are the unique
+    /// decomposition of the limb since they are below a constant less than the modulus.
+    pub fn less_than_constant<F: FieldExt, LC: LookupAssistChip<F>>(
+        &self,
+        region: &Region<F>,
+        lookup_assist_chip: &mut LC,
+        offset: &mut usize,
+        limb: &Limb<F>,
+        constant: &F,
+        limb_size: usize,
+    ) -> Result<(), Error> {
+        let mut bits = vec![];
+        self.decompose_limb(
+            region,
+            lookup_assist_chip,
+            offset,
+            limb,
+            &mut bits,
+            limb_size,
+        )?;
+        let mut constant_bits = field_to_bn(constant).to_radix_le(2);
+        assert!(constant_bits.len() <= limb_size);
+        constant_bits.resize_with(limb_size, || 0);
+        constant_bits.reverse();
+
+        let one = self.assign_constant(region, lookup_assist_chip, offset, &F::one())?;
+        let mut eq = one.clone();
+        let mut lt = self.assign_constant(region, lookup_assist_chip, offset, &F::zero())?;
+        for (bit, c) in bits.iter().zip(constant_bits.into_iter()) {
+            // eq_next = eq * bit if c = 1 else eq - eq * bit
+            let eq_next = if c == 0 {
+                eq.value - eq.value * bit.value
+            } else {
+                eq.value * bit.value
+            };
+            let l = self.assign_line(
+                region,
+                lookup_assist_chip,
+                offset,
+                [
+                    Some(eq.clone()),
+                    Some(bit.clone()),
+                    Some(eq.clone()),
+                    None,
+                    Some(Limb::new(None, eq_next)),
+                    None,
+                ],
+                [
+                    if c == 0 { Some(F::one()) } else { None },
+                    None,
+                    None,
+                    None,
+                    Some(-F::one()),
+                    None,
+                    None,
+                    Some(if c == 0 { -F::one() } else { F::one() }),
+                    None,
+                ],
+                0,
+            )?;
+            let eq_next = l[3].clone();
+            if c == 1 {
+                // the bit is below the constant bit: lt_next = lt + eq - eq_next
+                let l = self.assign_line(
+                    region,
+                    lookup_assist_chip,
+                    offset,
+                    [
+                        Some(lt.clone()),
+                        Some(eq.clone()),
+                        Some(eq_next.clone()),
+                        Some(Limb::new(None, lt.value + eq.value - eq_next.value)),
+                        None,
+                        None,
+                    ],
+                    [
+                        Some(F::one()),
+                        Some(F::one()),
+                        Some(-F::one()),
+                        Some(-F::one()),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                    ],
+                    0,
+                )?;
+                lt = l[3].clone();
+            }
+            eq = eq_next;
+        }
+        region.constrain_equal(lt.get_the_cell().cell(), one.get_the_cell().cell())?;
+        Ok(())
+    }
+
     ///
     /// decompose a limb into binary cells, in big endian
     /// limbsize needs to be a multiple of 4
@@ -701,6 +803,58 @@ impl CommonGateConfig {
         Ok(l[0].clone())
     }
 
+    /// Constrain limb to be one of the constants by copying the product
+    /// \prod_i (limb - constants[i]) to a zero constant
+    pub fn one_of_constants<F: FieldExt, LC: LookupAssistChip<F>>(
+        &self,
+        region: &Region<F>,
+        lookup_assist_chip: &mut LC,
+        offset: &mut usize,
+        limb: &Limb<F>,
+        constants: &[F],
+    ) -> Result<(), Error> {
+        let zero = self.assign_constant(region, lookup_assist_chip, offset, &F::zero())?;
+        let mut product = self.sum_with_constant(
+            region,
+            lookup_assist_chip,
+            offset,
+            vec![(limb, F::one())],
+            Some(-constants[0]),
+        )?;
+        for constant in constants.iter().skip(1) {
+            // product * limb - constant * product - product_next = 0
+            let next = Limb::new(None, product.value * (limb.value - constant));
+            let l = self.assign_line(
+                region,
+                lookup_assist_chip,
+                offset,
+                [
+                    Some(product.clone()),
+                    Some(next),
+                    None,
+                    Some(limb.clone()),
+                    None,
+                    None,
+                ],
+                [
+                    Some(-*constant),
+                    Some(-F::one()),
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(F::one()),
+                    None,
+                    None,
+                ],
+                0,
+            )?;
+            product = l[1].clone();
+        }
+        region.constrain_equal(product.get_the_cell().cell(), zero.get_the_cell().cell())?;
+        Ok(())
+    }
+
     // Support to return inputs' cells and add eq_constraints for res
     fn sum_with_constant_ext<F: FieldExt, LC: LookupAssistChip<F>, LB>(
         &self,
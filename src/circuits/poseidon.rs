@@ -596,7 +596,7 @@ impl<F: FieldExt, const T: usize> PoseidonState<F, T> {
 mod tests {
     use crate::circuits::poseidon::PoseidonGateConfig;
     use crate::circuits::CommonGateConfig;
-    use crate::host::poseidon::POSEIDON_HASHER_SPEC;
+    use crate::host::poseidon::{poseidon_hasher, poseidon_spec};
     use crate::value_for_assign;
     use halo2_proofs::circuit::floor_planner::FlatFloorPlanner;
     use halo2_proofs::dev::MockProver;
@@ -702,7 +702,7 @@ mod tests {
     }
 
     #[derive(Clone, Debug, Default)]
-    struct TestCircuit {
+    struct TestCircuit<const T: usize, const RATE: usize> {
         inputs: Vec<Fr>,
         result: Fr,
     }
@@ -714,23 +714,14 @@ mod tests {
         helperconfig: HelperChipConfig,
     }
 
-    impl Circuit<Fr> for TestCircuit {
+    impl<const T: usize, const RATE: usize> Circuit<Fr> for TestCircuit<T, RATE> {
         type Config = TestConfig;
         type FloorPlanner = FlatFloorPlanner;
 
         fn without_witnesses(&self) -> Self {
-            let mut hasher = crate::host::poseidon::POSEIDON_HASHER.clone();
-            let result = hasher.squeeze();
-            let inputs = vec![
-                Fr::one(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-                Fr::zero(),
-            ];
+            let result = poseidon_hasher::<T, RATE>().squeeze();
+            let mut inputs = vec![Fr::zero(); RATE];
+            inputs[0] = Fr::one();
             TestCircuit { inputs, result }
         }
 
@@ -743,7 +734,8 @@ mod tests {
                 cs.advice_column(),
             ];
 
-            let (commonconfig, poseidonconfig) = PoseidonChip::<Fr, 9, 8>::configure(cs, &witness);
+            let (commonconfig, poseidonconfig) =
+                PoseidonChip::<Fr, T, RATE>::configure(cs, &witness);
             Self::Config {
                 commonconfig,
                 poseidonconfig,
@@ -759,10 +751,10 @@ mod tests {
             layouter.assign_region(
                 || "assign poseidon test",
                 |region| {
-                    let mut poseidonchip = PoseidonChip::<Fr, 9, 8>::construct(
+                    let mut poseidonchip = PoseidonChip::<Fr, T, RATE>::construct(
                         config.clone().commonconfig,
                         config.clone().poseidonconfig,
-                        POSEIDON_HASHER_SPEC.clone(),
+                        poseidon_spec::<T, RATE>(),
                     );
                     let helperchip = HelperChip::new(config.clone().helperconfig);
 
@@ -805,9 +797,28 @@ mod tests {
             Fr::zero(),
             Fr::zero(),
         ];
-        let test_circuit = TestCircuit { inputs, result };
+        let test_circuit = TestCircuit::<9, 8> { inputs, result };
         println!("result is {:?}", result);
         let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_poseidon_circuit_t3_t5() {
+        let inputs = vec![Fr::from(3u64), Fr::from(5u64)];
+        let result = crate::host::poseidon::POSEIDON_T3_HASHER
+            .clone()
+            .update_exact(&inputs.clone().try_into().unwrap());
+        let test_circuit = TestCircuit::<3, 2> { inputs, result };
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let inputs = vec![Fr::from(3u64), Fr::from(5u64), Fr::zero(), Fr::one()];
+        let result = crate::host::poseidon::POSEIDON_T5_HASHER
+            .clone()
+            .update_exact(&inputs.clone().try_into().unwrap());
+        let test_circuit = TestCircuit::<5, 4> { inputs, result };
+        let prover = MockProver::run(16, &test_circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
 }
@@ -1,10 +1,20 @@
+use crate::host::poseidon::POSEIDON_HASHER;
+use crate::host::poseidon::{PREFIX_CHALLENGE, PREFIX_POINT, PREFIX_SCALAR};
 use crate::utils::bn_to_field;
+use crate::utils::field_to_bn;
 use ff::Field;
 use halo2_proofs::pairing::bn256::Fr;
 use lazy_static::lazy_static;
 use num_bigint::BigUint;
+use std::error::Error;
+use std::fmt;
 use std::ops::{AddAssign, MulAssign, SubAssign};
 
+/// The bits of a window of the fixed base multiplication
+pub const BASE_WINDOW_BITS: usize = 4;
+/// The windows of a 256 bits scalar
+pub const BASE_WINDOWS: usize = 64;
+
 lazy_static! {
     static ref D_BIG: BigUint = BigUint::parse_bytes(
         b"12181644023421730124874158521699555681764249180949974110617291017600649128846",
@@ -23,8 +33,93 @@ lazy_static! {
         10
     )
     .unwrap();
+    /// The order of the prime subgroup generated by BASE
+    pub static ref SUBGROUP_ORDER: BigUint = BigUint::parse_bytes(
+        b"2736030358979909402780800718157159386076813972158567259200215660948447373041",
+        10
+    )
+    .unwrap();
+    /// The inverse of the cofactor 8 modulo SUBGROUP_ORDER
+    static ref COFACTOR_INV: BigUint = BigUint::from(8u64).modpow(
+        &(&*SUBGROUP_ORDER - BigUint::from(2u64)),
+        &SUBGROUP_ORDER
+    );
+    /// The base point of the signatures
+    pub static ref BASE: Point = Point {
+        x: bn_to_field(
+            &BigUint::parse_bytes(
+                b"2ef3f9b423a2c8c74e9803958f6c320e854a1c1c06cd5cc8fd221dc052d76df7",
+                16
+            )
+            .unwrap()
+        ),
+        y: bn_to_field(
+            &BigUint::parse_bytes(
+                b"05a01167ea785d3f784224644a68e4067532c815f5f6d57d984b5c0e9c6c94b7",
+                16
+            )
+            .unwrap()
+        ),
+    };
+    /// FIXED_BASE_TABLE[i][j] = j.16^i.BASE
+    pub static ref FIXED_BASE_TABLE: Vec<Vec<Point>> = fixed_base_table();
+}
+
+fn fixed_base_table() -> Vec<Vec<Point>> {
+    let mut table = vec![];
+    let mut base = BASE.projective();
+    for _ in 0..BASE_WINDOWS {
+        let mut window = vec![Point::identity()];
+        let mut acc = Point::identity().projective();
+        for _ in 1..(1 << BASE_WINDOW_BITS) {
+            acc = acc.add(&base);
+            window.push(acc.affine());
+        }
+        base = acc.add(&base);
+        table.push(window);
+    }
+    table
+}
+
+/// n.BASE by adding one entry of FIXED_BASE_TABLE per window instead of double and add
+pub fn mul_base(n: &BigUint) -> Point {
+    assert!(n.bits() <= (BASE_WINDOWS * BASE_WINDOW_BITS) as u64);
+    let mut r = Point::identity().projective();
+    for (window, digit) in FIXED_BASE_TABLE
+        .iter()
+        .zip(n.to_radix_le(1 << BASE_WINDOW_BITS))
+    {
+        r = r.add(&window[digit as usize].projective());
+    }
+    r.affine()
 }
 
+#[derive(Debug)]
+pub enum PointErrorCode {
+    NotOnCurve,
+    NotInSubgroup,
+}
+
+#[derive(Debug)]
+pub struct PointError {
+    point: Point,
+    code: PointErrorCode,
+}
+
+impl PointError {
+    pub fn new(point: Point, code: PointErrorCode) -> Self {
+        PointError { point, code }
+    }
+}
+
+impl fmt::Display for PointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PointError {:?} {:?}", self.point, self.code)
+    }
+}
+
+impl Error for PointError {}
+
 #[derive(Clone, Debug)]
 pub struct PointProjective {
     pub x: Fr,
@@ -187,15 +282,88 @@ impl Point {
         }
         r.affine()
     }
+
+    /// Whether a.x^2 + y^2 = 1 + d.x^2.y^2
+    pub fn is_on_curve(&self) -> bool {
+        let x2 = self.x.square();
+        let y2 = self.y.square();
+        *A * x2 + y2 == Fr::one() + *D * x2 * y2
+    }
+
+    /// Whether the point is in the prime order subgroup generated by BASE
+    pub fn is_in_subgroup(&self) -> bool {
+        self.mul_scalar(&SUBGROUP_ORDER) == Point::identity()
+    }
+
+    /// The point Q of the prime order subgroup with 8.Q = self, which is the
+    /// witness of the cofactor cleared subgroup check of the circuits.
+    pub fn cofactor_root(&self) -> Point {
+        self.mul_scalar(&COFACTOR_INV)
+    }
+
+    /// Reject the points that are not on the curve and, when `check_subgroup`
+    /// is set, the points with a small order component.
+    pub fn validate(&self, check_subgroup: bool) -> Result<(), PointError> {
+        if !self.is_on_curve() {
+            Err(PointError::new(self.clone(), PointErrorCode::NotOnCurve))
+        } else if check_subgroup && !self.is_in_subgroup() {
+            Err(PointError::new(self.clone(), PointErrorCode::NotInSubgroup))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 pub fn test_bit(b: &[u8], i: usize) -> bool {
     b[i / 8] & (1 << (i % 8)) != 0
 }
 
+/// The eddsa challenge h = poseidon(challenge, point, R, point, A, scalar, msg) of the
+/// signature R on msg by the public key A. The nine inputs take two rounds of the
+/// width 9 hasher and the second round is [msg, 1, 0, ...] after padding.
+pub fn challenge(sig_r: &Point, pk: &Point, msg: &Fr) -> Fr {
+    let mut hasher = POSEIDON_HASHER.clone();
+    hasher.update(&[
+        Fr::from(PREFIX_CHALLENGE),
+        Fr::from(PREFIX_POINT),
+        sig_r.x,
+        sig_r.y,
+        Fr::from(PREFIX_POINT),
+        pk.x,
+        pk.y,
+        Fr::from(PREFIX_SCALAR),
+        *msg,
+    ]);
+    hasher.squeeze()
+}
+
+/// Check R + h.A = s.B for a given challenge h
+pub fn verify_with_challenge(pk: &Point, sig_r: &Point, sig_s: &BigUint, h: &Fr) -> bool {
+    let lhs = sig_r.add(&pk.mul_scalar(&field_to_bn(h)));
+    let rhs = BASE.mul_scalar(sig_s);
+    lhs == rhs
+}
+
+/// Verify the eddsa signature (R, s) of msg by the public key A with s < SUBGROUP_ORDER.
+/// The points are checked to be in the subgroup only with the feature `jubjub-subgroup`,
+/// which is when JubjubVerifyChip checks it.
+pub fn verify(pk: &Point, sig_r: &Point, sig_s: &BigUint, msg: &Fr) -> bool {
+    let check_subgroup = cfg!(feature = "jubjub-subgroup");
+    if sig_s >= &*SUBGROUP_ORDER
+        || pk.validate(check_subgroup).is_err()
+        || sig_r.validate(check_subgroup).is_err()
+    {
+        return false;
+    }
+    verify_with_challenge(pk, sig_r, sig_s, &challenge(sig_r, pk, msg))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Point;
+    use super::{
+        challenge, mul_base, verify, verify_with_challenge, Point, PointErrorCode, BASE,
+        SUBGROUP_ORDER,
+    };
     use crate::utils::bn_to_field;
     use crate::utils::field_to_bn;
     use halo2_proofs::pairing::bn256::Fr;
@@ -301,5 +469,75 @@ mod tests {
         println!("third round {:?}", rst);
         // assert_eq!(lhs,rhs)
         assert_eq!(Point::identity(), rst);
+        assert!(verify_with_challenge(&pk, &sig_r, &sig_s, &bn_to_field(&c)));
+    }
+
+    #[test]
+    pub fn verify_signature() {
+        let sk = BigUint::from(0x1234_5678_9abc_def0u64);
+        let nonce = BigUint::from(0x0fed_cba9_8765_4321u64);
+        let msg = Fr::from(42u64);
+
+        let pk = BASE.mul_scalar(&sk);
+        let sig_r = BASE.mul_scalar(&nonce);
+        let h = field_to_bn(&challenge(&sig_r, &pk, &msg));
+        let sig_s = (nonce + h * sk) % &*SUBGROUP_ORDER;
+
+        assert!(verify(&pk, &sig_r, &sig_s, &msg));
+        assert!(!verify(&pk, &sig_r, &sig_s, &Fr::from(43u64)));
+        assert!(!verify(&pk, &sig_r, &(&sig_s + 1u64), &msg));
+        // the same point s.B but s is not reduced
+        assert!(verify_with_challenge(
+            &pk,
+            &sig_r,
+            &(&sig_s + &*SUBGROUP_ORDER),
+            &challenge(&sig_r, &pk, &msg)
+        ));
+        assert!(!verify(&pk, &sig_r, &(sig_s + &*SUBGROUP_ORDER), &msg));
+        assert!(BASE.mul_scalar(&SUBGROUP_ORDER) == Point::identity());
+    }
+
+    #[test]
+    pub fn fixed_base_mul() {
+        for n in [
+            BigUint::from(0u64),
+            BigUint::from(15u64),
+            BigUint::from(0x1234_5678_9abc_def0u64),
+            &*SUBGROUP_ORDER - BigUint::from(1u64),
+            field_to_bn(&-Fr::one()),
+        ] {
+            assert_eq!(mul_base(&n), BASE.mul_scalar(&n));
+        }
+    }
+
+    #[test]
+    pub fn validate_points() {
+        assert!(BASE.validate(true).is_ok());
+        assert!(Point::identity().validate(true).is_ok());
+
+        // the point of order 2 is on the curve but not in the subgroup
+        let p = Point {
+            x: Fr::zero(),
+            y: -Fr::one(),
+        };
+        assert!(p.validate(false).is_ok());
+        assert!(matches!(
+            p.validate(true).unwrap_err().code,
+            PointErrorCode::NotInSubgroup
+        ));
+        assert!(!BASE.add(&p).is_in_subgroup());
+
+        let p = Point {
+            x: Fr::one(),
+            y: Fr::one(),
+        };
+        assert!(matches!(
+            p.validate(false).unwrap_err().code,
+            PointErrorCode::NotOnCurve
+        ));
+
+        let q = BASE.cofactor_root();
+        assert!(q.validate(true).is_ok());
+        assert_eq!(q.mul_scalar(&BigUint::from(8u64)), *BASE);
     }
 }
@@ -215,6 +215,78 @@ impl Keccak {
     }
 }
 
+/// Number of message bytes absorbed by one block
+pub const RATE_BYTES: usize = RATE * 8;
+
+/// The operand of Keccak256New: bit 0 restarts the sponge, bit 1 marks the
+/// last block of a message and the remaining bits hold the number of message
+/// bytes in that block, whose pad10*1 padding is then applied by the circuit.
+/// A round without bit 1 absorbs its lanes as they are.
+pub fn keccak_new_operand(restart: bool, last_block_len: Option<usize>) -> u64 {
+    match last_block_len {
+        Some(len) => {
+            assert!(len < RATE_BYTES);
+            restart as u64 + 2 + ((len as u64) << 2)
+        }
+        None => restart as u64,
+    }
+}
+
+/// Pack bytes into lanes in little endian, missing bytes are zero
+fn bytes_to_lanes(bytes: &[u8]) -> [u64; RATE] {
+    assert!(bytes.len() <= RATE_BYTES);
+    let mut lanes = [0u64; RATE];
+    for (i, b) in bytes.iter().enumerate() {
+        lanes[i / 8] |= (*b as u64) << (8 * (i % 8));
+    }
+    lanes
+}
+
+/// Split a message into unpadded blocks. The last block always exists, even
+/// when it is empty, and comes with the number of its message bytes.
+pub fn bytes_to_blocks(input: &[u8]) -> Vec<([u64; RATE], Option<usize>)> {
+    let mut blocks = input
+        .chunks_exact(RATE_BYTES)
+        .map(|chunk| (bytes_to_lanes(chunk), None))
+        .collect::<Vec<_>>();
+    let rest = &input[input.len() - input.len() % RATE_BYTES..];
+    blocks.push((bytes_to_lanes(rest), Some(rest.len())));
+    blocks
+}
+
+/// Apply the pad10*1 rule to a block that holds `len` message bytes
+pub fn pad_block(block: &[u64; RATE], len: usize) -> [u64; RATE] {
+    assert!(len < RATE_BYTES);
+    let mut padded = block.clone();
+    padded[len / 8] ^= 1u64 << (8 * (len % 8));
+    padded[RATE - 1] ^= 1u64 << 63;
+    padded
+}
+
+impl Keccak {
+    /// Absorb a whole byte message together with its padding
+    pub fn hash_bytes(&mut self, input: &[u8]) -> [u64; 4] {
+        assert_eq!(self.absorbing.len(), 0);
+        for (block, last_block_len) in bytes_to_blocks(input) {
+            match last_block_len {
+                Some(len) => self.state.absorb(&pad_block(&block, len)),
+                None => self.state.absorb(&block),
+            }
+        }
+        self.state.result()
+    }
+}
+
+/// Keccak-256 digest of a byte message
+pub fn keccak256(input: &[u8]) -> [u8; 32] {
+    let lanes = KECCAK_HASHER.clone().hash_bytes(input);
+    let mut digest = [0u8; 32];
+    for (i, lane) in lanes.iter().enumerate() {
+        digest[i * 8..(i + 1) * 8].copy_from_slice(&lane.to_le_bytes());
+    }
+    digest
+}
+
 lazy_static::lazy_static! {
     pub static ref KECCAK_HASHER: Keccak = Keccak::new();
 }
@@ -343,4 +415,45 @@ mod tests {
 
     #[test]
     fn keccak_run() {}
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|x| format!("{:02x}", x)).join("")
+    }
+
+    #[test]
+    fn keccak256_bytes_reference() {
+        assert_eq!(
+            hex(&super::keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex(&super::keccak256(b"abc")),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+
+    #[test]
+    fn keccak256_bytes_match_lanes() {
+        // messages of whole lanes can be hashed by both apis
+        for lanes in [0, 1, 16, 17, 18, 34] {
+            let inputs = (0..lanes).map(|_| OsRng.next_u64()).collect::<Vec<u64>>();
+            let bytes = inputs
+                .iter()
+                .flat_map(|x| x.to_le_bytes())
+                .collect::<Vec<u8>>();
+            let mut keccak = KECCAK_HASHER.clone();
+            keccak.update(&inputs[..]);
+            assert_eq!(keccak.squeeze(), KECCAK_HASHER.clone().hash_bytes(&bytes));
+        }
+    }
+
+    #[test]
+    fn keccak256_bytes_blocks() {
+        for (len, blocks, last) in [(0, 1, 0), (135, 1, 135), (136, 2, 0), (137, 2, 1)] {
+            let r = super::bytes_to_blocks(&vec![0xffu8; len]);
+            assert_eq!(r.len(), blocks);
+            assert_eq!(r.last().unwrap().1, Some(last));
+            assert!(r[..blocks - 1].iter().all(|(_, l)| l.is_none()));
+        }
+    }
 }
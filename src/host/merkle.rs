@@ -1,8 +1,12 @@
 use crate::host::db::TreeDB;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::error::Error;
 use std::fmt;
 use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
 use std::rc::Rc;
 
 /*
@@ -57,14 +61,85 @@ pub trait MerkleNode<H: Debug + Clone + PartialEq> {
     fn right(&self) -> Option<H>; // hash of right child
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MerkleProof<H: Debug + Clone + PartialEq, const D: usize> {
     pub source: H, // hash of the leaf data
     pub root: H,   // last is root
+    #[serde(with = "assist_serde")]
     pub assist: [H; D],
     pub index: u64,
 }
 
+/// The assist of a proof is (de)serialized as a sequence of length D
+mod assist_serde {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, H: Serialize, const D: usize>(
+        assist: &[H; D],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(assist.iter())
+    }
+
+    pub fn deserialize<'de, De: Deserializer<'de>, H: Deserialize<'de>, const D: usize>(
+        deserializer: De,
+    ) -> Result<[H; D], De::Error> {
+        let assist = Vec::<H>::deserialize(deserializer)?;
+        let len = assist.len();
+        assist
+            .try_into()
+            .map_err(|_| De::Error::invalid_length(len, &"the depth of the merkle tree"))
+    }
+}
+
+/// The merkle proofs of the merkle host calls of a trace, one for each
+/// MerkleAddress group. For a get the proof is taken against the root of the
+/// call and for a set or delete against its new root, so that the calls can
+/// be proved without the tree db. The prover looks the proofs up by their
+/// leaf index and root, thus their order does not matter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MerkleWitness<const D: usize>(pub Vec<MerkleProof<[u8; 32], D>>);
+
+impl<const D: usize> MerkleWitness<D> {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        serde_json::to_writer(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+
+    /// Record the proof returned by a get, set or delete while the trace is built
+    pub fn record(&mut self, proof: MerkleProof<[u8; 32], D>) {
+        self.0.push(proof);
+    }
+}
+
+/// The hash of a merkle tree whose nodes are 32 bytes digests
+pub trait MerkleHasher {
+    /// size in bytes of the data of a leaf
+    const LEAF_SIZE: usize = 32;
+    /// hash of the LEAF_SIZE bytes data of a leaf
+    fn hash_leaf(data: &[u8]) -> [u8; 32];
+    /// hash of a node from the hashes of its left and right child
+    fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32];
+    /// default hashes from the empty leaf (index 0) up to the root of an empty tree
+    fn default_hashes() -> &'static Vec<[u8; 32]>;
+}
+
+/// Hashes of the empty trees of height 0 up to height, which starts with the hash of an empty leaf.
+pub fn default_hashes<H: MerkleHasher>(height: usize) -> Vec<[u8; 32]> {
+    let mut hash = H::hash_leaf(&vec![0; H::LEAF_SIZE]);
+    let mut hashes = vec![hash];
+    for _ in 0..height {
+        hash = H::hash_node(&hash, &hash);
+        hashes.push(hash);
+    }
+    hashes
+}
+
 pub fn get_offset(index: u64) -> u64 {
     let height = (index + 1).ilog2();
     let full = (1u64 << height) - 1;
@@ -243,22 +318,29 @@ pub trait MerkleTree<H: Debug + Clone + PartialEq, const D: usize> {
     }
 
     fn verify_proof(&self, proof: &MerkleProof<H, D>) -> Result<bool, MerkleError> {
-        let init = proof.source.clone();
-        let mut p = get_offset(proof.index);
-        let mut assist = proof.assist.clone();
-        assist.reverse();
-
-        let hash = assist.to_vec().iter().fold(init, |acc, x| {
-            let (left, right) = if p % 2 == 1 { (x, &acc) } else { (&acc, x) };
-            //println!("verify hash is {:?} {}, assist {:?}", acc, p % 2 == 1, x);
-            p = p / 2;
-            Self::hash(left, right)
-        });
-        //println!("root {:?}", proof.root);
-        Ok(proof.root == hash)
+        Ok(verify_merkle_proof(proof, Self::hash))
     }
 }
 
+/// Check that hashing the source of a proof with its assist nodes leads to its
+/// root, which only needs the node hash and not the tree the proof comes from.
+pub fn verify_merkle_proof<H: Debug + Clone + PartialEq, const D: usize>(
+    proof: &MerkleProof<H, D>,
+    hash: impl Fn(&H, &H) -> H,
+) -> bool {
+    let init = proof.source.clone();
+    let mut p = get_offset(proof.index);
+    let mut assist = proof.assist.clone();
+    assist.reverse();
+
+    let root = assist.to_vec().iter().fold(init, |acc, x| {
+        let (left, right) = if p % 2 == 1 { (x, &acc) } else { (&acc, x) };
+        p = p / 2;
+        hash(left, right)
+    });
+    proof.root == root
+}
+
 #[cfg(test)]
 mod tests {
     use crate::host::db::TreeDB;
@@ -8,6 +8,7 @@ pub mod keccak256;
 pub mod merkle;
 pub mod mongomerkle;
 pub mod poseidon;
+pub mod trace;
 use num_derive::{FromPrimitive, ToPrimitive};
 
 use halo2_proofs::arithmetic::FieldExt;
@@ -16,7 +17,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ExternalHostCallEntryTable(pub Vec<ExternalHostCallEntry>);
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExternalHostCallEntry {
     pub op: usize,
     pub value: u64,
@@ -69,6 +70,33 @@ pub enum ForeignInst {
     WitnessSetIndex,
     WitnessIndexedPush,
     WitnessIndexedPop,
+    PoseidonT3New,
+    PoseidonT3Push,
+    PoseidonT3Finalize,
+    PoseidonT5New,
+    PoseidonT5Push,
+    PoseidonT5Finalize,
+    JubjubVerifyPk,
+    JubjubVerifySig,
+    JubjubVerifyMsg,
+    Bn254MultiPairNew,
+    Bn254MultiPairG1,
+    Bn254MultiPairG2,
+    Bn254MultiPairResult,
+    MerkleWideAddress,
+    MerkleWideSetRoot,
+    MerkleWideGetRoot,
+    MerkleWideSet,
+    MerkleWideGet,
+    MerkleDelete,
+    JubjubBaseSumNew,
+    JubjubBaseSumPush,
+    JubjubBaseSumResult,
+    KeccakMerkleAddress,
+    KeccakMerkleSetRoot,
+    KeccakMerkleGetRoot,
+    KeccakMerkleSet,
+    KeccakMerkleGet,
 }
 
 pub enum ReduceRule<F: FieldExt> {
@@ -1,5 +1,8 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::rc::Rc;
+use std::sync::Mutex;
 
 use ff::PrimeField;
 use halo2_proofs::pairing::bn256::Fr;
@@ -12,9 +15,13 @@ use serde::{
 };
 
 use crate::host::db::{MongoDB, TreeDB};
-use crate::host::merkle::{MerkleError, MerkleErrorCode, MerkleNode, MerkleProof, MerkleTree};
+use crate::host::keccak256::keccak256;
+use crate::host::merkle::{
+    default_hashes, MerkleError, MerkleErrorCode, MerkleHasher, MerkleNode, MerkleProof, MerkleTree,
+};
 use crate::host::poseidon::MERKLE_HASHER;
 use crate::host::poseidon::MERKLE_LEAF_HASHER;
+use crate::host::poseidon::POSEIDON_HASHER;
 
 fn deserialize_u256_as_binary<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
 where
@@ -69,11 +76,35 @@ where
     }
 }
 
+fn deserialize_option_vec_as_binary<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Bson::deserialize(deserializer) {
+        Ok(Bson::Binary(bytes)) => Ok(Some(bytes.bytes)),
+        Ok(Bson::Null) => Ok(None),
+        Ok(..) => Err(Error::invalid_value(Unexpected::Enum, &"Bson::Binary")),
+        Err(e) => Err(e),
+    }
+}
+
+fn serialize_option_vec_as_binary<S>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match bytes {
+        Some(bytes) => serialize_bytes_as_binary(bytes, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Merkle tree stored in a TreeDB whose leaves and nodes are hashed by H
 #[derive(Clone)]
-pub struct MongoMerkle<const DEPTH: usize> {
+pub struct MongoMerkle<const DEPTH: usize, H: MerkleHasher = PoseidonMerkleHasher> {
     root_hash: [u8; 32],
     default_hash: Vec<[u8; 32]>,
     db: Rc<RefCell<dyn TreeDB>>,
+    _hasher: PhantomData<H>,
 }
 
 impl PartialEq for MerkleRecord {
@@ -85,7 +116,7 @@ impl PartialEq for MerkleRecord {
     }
 }
 
-impl<const DEPTH: usize> MongoMerkle<DEPTH> {
+impl<const DEPTH: usize, H: MerkleHasher> MongoMerkle<DEPTH, H> {
     pub fn get_record(&self, hash: &[u8; 32]) -> Result<Option<MerkleRecord>, anyhow::Error> {
         self.db.borrow().get_merkle_record(hash)
     }
@@ -129,6 +160,7 @@ impl<const DEPTH: usize> MongoMerkle<DEPTH> {
             index,
             hash: default,
             data: None,
+            wide_data: None,
             left: child_hash,
             right: child_hash,
         })
@@ -180,6 +212,107 @@ impl<const DEPTH: usize> MongoMerkle<DEPTH> {
 
 pub type RocksMerkle<const DEPTH: usize> = MongoMerkle<DEPTH>;
 
+/// Merkle tree hashed by keccak256 which agrees with the trees of solidity contracts
+pub type KeccakMerkle<const DEPTH: usize> = MongoMerkle<DEPTH, KeccakMerkleHasher>;
+
+/// Poseidon hasher of MongoMerkle which is also used by MerkleChip
+#[derive(Clone, Debug)]
+pub struct PoseidonMerkleHasher;
+
+impl MerkleHasher for PoseidonMerkleHasher {
+    fn hash_leaf(data: &[u8]) -> [u8; 32] {
+        let mut hasher = MERKLE_LEAF_HASHER.clone();
+        let batchdata = data
+            .chunks(16)
+            .into_iter()
+            .map(|x| {
+                let mut v = x.to_vec();
+                v.extend_from_slice(&[0u8; 16]);
+                let f = v.try_into().unwrap();
+                Fr::from_repr(f).unwrap()
+            })
+            .collect::<Vec<Fr>>();
+        let values: [Fr; 2] = batchdata.try_into().unwrap();
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature="complex-leaf")] {
+                hasher.update(&values);
+                hasher.squeeze().to_repr()
+            } else {
+                hasher.update_exact(&values).to_repr()
+            }
+        }
+    }
+
+    fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = MERKLE_HASHER.clone();
+        let a = Fr::from_repr(*left).unwrap();
+        let b = Fr::from_repr(*right).unwrap();
+        hasher.update_exact(&[a, b]).to_repr()
+    }
+
+    fn default_hashes() -> &'static Vec<[u8; 32]> {
+        &DEFAULT_HASH_VEC
+    }
+}
+
+/// Keccak256 hasher where a leaf is keccak256(data) and a node is keccak256(left || right)
+#[derive(Clone, Debug)]
+pub struct KeccakMerkleHasher;
+
+impl MerkleHasher for KeccakMerkleHasher {
+    fn hash_leaf(data: &[u8]) -> [u8; 32] {
+        keccak256(data)
+    }
+
+    fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut bytes = left.to_vec();
+        bytes.extend_from_slice(right);
+        keccak256(&bytes)
+    }
+
+    fn default_hashes() -> &'static Vec<[u8; 32]> {
+        &KECCAK_DEFAULT_HASH_VEC
+    }
+}
+
+/// Poseidon hasher of the trees whose leaves are N field elements. A leaf is
+/// hashed by the width 9 sponge over its words and the nodes are hashed as
+/// in PoseidonMerkleHasher.
+#[derive(Clone, Debug)]
+pub struct PoseidonWideMerkleHasher<const N: usize>;
+
+impl<const N: usize> MerkleHasher for PoseidonWideMerkleHasher<N> {
+    const LEAF_SIZE: usize = 32 * N;
+
+    fn hash_leaf(data: &[u8]) -> [u8; 32] {
+        assert_eq!(data.len(), Self::LEAF_SIZE);
+        let words = data
+            .chunks(32)
+            .map(|x| Fr::from_repr(x.try_into().unwrap()).unwrap())
+            .collect::<Vec<Fr>>();
+        let mut hasher = POSEIDON_HASHER.clone();
+        hasher.update(&words);
+        hasher.squeeze().to_repr()
+    }
+
+    fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        PoseidonMerkleHasher::hash_node(left, right)
+    }
+
+    fn default_hashes() -> &'static Vec<[u8; 32]> {
+        // the default hashes of each leaf width are computed once
+        let mut hashes = WIDE_DEFAULT_HASH_VECS.lock().unwrap();
+        *hashes
+            .entry(N)
+            .or_insert_with(|| Box::leak(Box::new(default_hashes::<Self>(64))))
+    }
+}
+
+/// Merkle tree whose leaves are N field elements
+pub type WideMerkle<const DEPTH: usize, const N: usize> =
+    MongoMerkle<DEPTH, PoseidonWideMerkleHasher<N>>;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MerkleRecord {
     // The index will not to be stored in db.
@@ -210,6 +343,14 @@ pub struct MerkleRecord {
     )]
     #[serde(deserialize_with = "self::deserialize_option_u256_as_binary")]
     pub data: Option<[u8; 32]>,
+    // The data of a leaf which is longer than 32 bytes, e.g. the words of a WideMerkle leaf.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "self::serialize_option_vec_as_binary",
+        default
+    )]
+    #[serde(deserialize_with = "self::deserialize_option_vec_as_binary")]
+    pub wide_data: Option<Vec<u8>>,
 }
 
 impl MerkleRecord {
@@ -247,6 +388,13 @@ impl MerkleRecord {
             result.push(0);
         }
 
+        // wide_data (Option<Vec<u8>>) as its length (u32) and bytes, omitted if None
+        if let Some(wide_data) = self.wide_data.as_ref() {
+            result.push(1);
+            result.extend_from_slice(&(wide_data.len() as u32).to_le_bytes());
+            result.extend_from_slice(wide_data);
+        }
+
         result
     }
 
@@ -322,7 +470,7 @@ impl MerkleRecord {
         }
         let data = match slice[pos] {
             0 => {
-                // pos += 1;
+                pos += 1;
                 None
             },
             1 => {
@@ -332,17 +480,37 @@ impl MerkleRecord {
                 }
                 let mut data_val = [0u8; 32];
                 data_val.copy_from_slice(&slice[pos..pos+32]);
+                pos += 32;
                 Some(data_val)
             },
             _ => return Err(anyhow::anyhow!("Invalid data flag")),
         };
 
+        // wide_data, the records without it end here
+        let wide_data = if slice.len() == pos {
+            None
+        } else {
+            if slice[pos] != 1 || slice.len() < pos + 5 {
+                return Err(anyhow::anyhow!("Invalid wide data flag"));
+            }
+            pos += 1;
+            let mut len_bytes = [0u8; 4];
+            len_bytes.copy_from_slice(&slice[pos..pos + 4]);
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            pos += 4;
+            if slice.len() < pos + len {
+                return Err(anyhow::anyhow!("Slice too short for wide data"));
+            }
+            Some(slice[pos..pos + len].to_vec())
+        };
+
         Ok(MerkleRecord {
             index,
             hash,
             left,
             right,
             data,
+            wide_data,
         })
     }
 }
@@ -355,31 +523,9 @@ impl MerkleNode<[u8; 32]> for MerkleRecord {
     fn hash(&self) -> [u8; 32] {
         self.hash
     }
+    /// Hash the data with the poseidon leaf hasher, use set_with for the leaves of other trees.
     fn set(&mut self, data: &Vec<u8>) {
-        let mut hasher = MERKLE_LEAF_HASHER.clone();
-        self.data = Some(data.clone().try_into().unwrap());
-        let batchdata = data
-            .chunks(16)
-            .into_iter()
-            .map(|x| {
-                let mut v = x.to_vec();
-                v.extend_from_slice(&[0u8; 16]);
-                let f = v.try_into().unwrap();
-                Fr::from_repr(f).unwrap()
-            })
-            .collect::<Vec<Fr>>();
-        let values: [Fr; 2] = batchdata.try_into().unwrap();
-
-        cfg_if::cfg_if! {
-            if #[cfg(feature="complex-leaf")] {
-                hasher.update(&values);
-                self.hash = hasher.squeeze().to_repr();
-            } else {
-                self.hash = hasher.update_exact(&values).to_repr();
-            }
-        }
-        //println!("update with values {:?}", values);
-        //println!("update with new hash {:?}", self.hash);
+        self.set_with::<PoseidonMerkleHasher>(data)
     }
     fn right(&self) -> Option<[u8; 32]> {
         self.right
@@ -390,14 +536,16 @@ impl MerkleNode<[u8; 32]> for MerkleRecord {
 }
 
 impl MerkleRecord {
-    fn new(index: u64) -> Self {
-        MerkleRecord {
-            index,
-            hash: [0; 32],
-            data: None,
-            left: None,
-            right: None,
+    /// Set the data of the leaf of a tree hashed by H, the data of more than
+    /// 32 bytes is kept in wide_data
+    pub fn set_with<H: MerkleHasher>(&mut self, data: &Vec<u8>) {
+        assert_eq!(data.len(), H::LEAF_SIZE);
+        if H::LEAF_SIZE == 32 {
+            self.data = Some(data.clone().try_into().unwrap());
+        } else {
+            self.wide_data = Some(data.clone());
         }
+        self.hash = H::hash_leaf(data);
     }
 
     pub fn data_as_u64(&self) -> [u64; 4] {
@@ -411,15 +559,10 @@ impl MerkleRecord {
     }
 }
 
-impl<const DEPTH: usize> MongoMerkle<DEPTH> {
+impl<const DEPTH: usize, H: MerkleHasher> MongoMerkle<DEPTH, H> {
     pub fn height() -> usize {
         return DEPTH;
     }
-    fn empty_leaf(index: u64) -> MerkleRecord {
-        let mut leaf = MerkleRecord::new(index);
-        leaf.set(&[0; 32].to_vec());
-        leaf
-    }
     /// depth start from 0 up to Self::height(). Example 20 height MongoMerkle, root depth=0, leaf depth=20
     pub fn get_default_hash(&self, depth: usize) -> Result<[u8; 32], MerkleError> {
         if depth <= Self::height() {
@@ -433,6 +576,22 @@ impl<const DEPTH: usize> MongoMerkle<DEPTH> {
         }
     }
 
+    /// The proof of the default leaf at the last index of an empty tree, which
+    /// only depends on the default hashes of H and needs no tree db.
+    pub fn empty_tree_proof() -> MerkleProof<[u8; 32], DEPTH> {
+        let default_hash = H::default_hashes();
+        let mut assist = [[0; 32]; DEPTH];
+        for i in 0..DEPTH {
+            assist[i] = default_hash[DEPTH - i - 1];
+        }
+        MerkleProof {
+            source: default_hash[0],
+            root: default_hash[DEPTH],
+            assist,
+            index: (1_u64 << DEPTH) - 1,
+        }
+    }
+
     pub fn default_proof(&self) -> MerkleProof<[u8; 32], DEPTH> {
         let mut assist = [0; DEPTH];
         for i in 0..DEPTH {
@@ -445,6 +604,19 @@ impl<const DEPTH: usize> MongoMerkle<DEPTH> {
             index: (1_u64 << DEPTH) - 1,
         }
     }
+
+    /// Reset the leaf at index to the default leaf, which restores the default
+    /// hashes of the nodes along its path whose subtrees become empty. The
+    /// records of the old path are kept as they may be shared by other roots,
+    /// so a delete does not reclaim any space of the tree db.
+    pub fn delete_leaf_with_proof(
+        &mut self,
+        index: u64,
+    ) -> Result<MerkleProof<[u8; 32], DEPTH>, MerkleError> {
+        self.leaf_check(index)?;
+        let leaf = self.generate_default_node(index)?;
+        self.set_leaf_with_proof(&leaf)
+    }
 }
 
 // In default_hash vec, it is from leaf to root.
@@ -453,18 +625,12 @@ impl<const DEPTH: usize> MongoMerkle<DEPTH> {
 // DEFAULT_HASH_VEC[20] is root default hash.
 // It has 21 layers including the leaf layer and root layer.
 lazy_static::lazy_static! {
-    pub static ref DEFAULT_HASH_VEC: Vec<[u8; 32]> = {
-        let mut leaf_hash = MongoMerkle::<64>::empty_leaf(0).hash;
-        let mut default_hash = vec![leaf_hash];
-        for _ in 0..(MongoMerkle::<64>::height()) {
-            leaf_hash = MongoMerkle::<64>::hash(&leaf_hash, &leaf_hash);
-            default_hash.push(leaf_hash);
-        }
-        default_hash
-    };
+    pub static ref DEFAULT_HASH_VEC: Vec<[u8; 32]> = default_hashes::<PoseidonMerkleHasher>(64);
+    pub static ref KECCAK_DEFAULT_HASH_VEC: Vec<[u8; 32]> = default_hashes::<KeccakMerkleHasher>(64);
+    static ref WIDE_DEFAULT_HASH_VECS: Mutex<HashMap<usize, &'static Vec<[u8; 32]>>> = Mutex::new(HashMap::new());
 }
 
-impl<const DEPTH: usize> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH> {
+impl<const DEPTH: usize, H: MerkleHasher> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH, H> {
     type Id = [u8; 32];
     type Root = [u8; 32];
     type Node = MerkleRecord;
@@ -472,8 +638,9 @@ impl<const DEPTH: usize> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH> {
     fn construct(addr: Self::Id, root: Self::Root, db: Option<Rc<RefCell<dyn TreeDB>>>) -> Self {
         MongoMerkle {
             root_hash: root,
-            default_hash: DEFAULT_HASH_VEC.clone(),
+            default_hash: H::default_hashes().clone(),
             db: db.unwrap_or_else(|| Rc::new(RefCell::new(MongoDB::new(addr, None)))),
+            _hasher: PhantomData,
         }
     }
 
@@ -486,10 +653,7 @@ impl<const DEPTH: usize> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH> {
     }
 
     fn hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
-        let mut hasher = MERKLE_HASHER.clone();
-        let a = Fr::from_repr(*a).unwrap();
-        let b = Fr::from_repr(*b).unwrap();
-        hasher.update_exact(&[a, b]).to_repr()
+        H::hash_node(a, b)
     }
 
     fn set_parent(
@@ -503,6 +667,7 @@ impl<const DEPTH: usize> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH> {
         let record = MerkleRecord {
             index,
             data: None,
+            wide_data: None,
             left: Some(*left),
             right: Some(*right),
             hash: *hash,
@@ -527,6 +692,7 @@ impl<const DEPTH: usize> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH> {
                     Ok(default_hash) if hash != &default_hash => Some(MerkleRecord {
                         index: *index,
                         data: None,
+                        wide_data: None,
                         left: Some(*left),
                         right: Some(*right),
                         hash: *hash,
@@ -536,9 +702,15 @@ impl<const DEPTH: usize> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH> {
             })
             .collect();
 
-        records.push(leaf.clone());
-        self.update_records(&records)
-            .expect("Unexpected DB Error when update records.");
+        // A deleted leaf is a default node which is not set in db either.
+        if leaf.hash != self.get_default_hash(DEPTH)? {
+            records.push(leaf.clone());
+        }
+        // The path of a tree that becomes empty again has nothing to set.
+        if !records.is_empty() {
+            self.update_records(&records)
+                .expect("Unexpected DB Error when update records.");
+        }
 
         Ok(())
     }
@@ -590,23 +762,38 @@ impl<const DEPTH: usize> MerkleTree<[u8; 32], DEPTH> for MongoMerkle<DEPTH> {
             },
         ))
     }
+
+    fn update_leaf_data_with_proof(
+        &mut self,
+        index: u64,
+        data: &Vec<u8>,
+    ) -> Result<MerkleProof<[u8; 32], DEPTH>, MerkleError> {
+        let (mut leaf, _) = self.get_leaf_with_proof(index)?;
+        leaf.set_with::<H>(data);
+        self.set_leaf_with_proof(&leaf)
+    }
 }
 
-impl<const DEPTH: usize> MongoMerkle<DEPTH> {
+impl<const DEPTH: usize, H: MerkleHasher> MongoMerkle<DEPTH, H> {
     pub fn default() -> Self {
         let addr = [0u8; 32];
         MongoMerkle {
-            root_hash: DEFAULT_HASH_VEC[DEPTH],
-            default_hash: (*DEFAULT_HASH_VEC).clone(),
+            root_hash: H::default_hashes()[DEPTH],
+            default_hash: H::default_hashes().clone(),
             db: Rc::new(RefCell::new(MongoDB::new(addr, None))),
+            _hasher: PhantomData,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{MerkleRecord, MongoMerkle, DEFAULT_HASH_VEC, RocksMerkle};
+    use super::{
+        KeccakMerkle, MerkleRecord, MongoMerkle, RocksMerkle, WideMerkle, DEFAULT_HASH_VEC,
+        KECCAK_DEFAULT_HASH_VEC,
+    };
     use crate::host::db::{get_collection, get_collection_name, MongoDB, MONGODB_DATABASE, MONGODB_DATA_NAME_PREFIX, RocksDB};
+    use crate::host::keccak256::keccak256;
     use crate::host::merkle::{MerkleNode, MerkleTree};
     use crate::utils::{bytes_to_u64, field_to_bytes};
     use halo2_proofs::pairing::bn256::Fr;
@@ -1004,4 +1191,148 @@ mod tests {
         assert_eq!(leaf.data.unwrap(), LEAF1_DATA);
         assert_eq!(mt.verify_proof(&proof).unwrap(), true);
     }
+
+    #[test]
+    fn test_keccak_default_hashes() {
+        // keccak256(bytes32(0)) and keccak256(abi.encodePacked(h, h)) in solidity
+        assert_eq!(
+            hex::encode(KECCAK_DEFAULT_HASH_VEC[0]),
+            "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+        );
+        assert_eq!(
+            hex::encode(KECCAK_DEFAULT_HASH_VEC[1]),
+            "633dc4d7da7256660a892f8f1604a44b5432649cc8ec5cb3ced4c4e6ac94dd1d"
+        );
+    }
+
+    #[test]
+    /* Update two leaves of a 32 height keccak m tree and verify their proofs
+     * after loading the tree from DB by its root.
+     */
+    fn test_keccak_merkle_leaves_update() {
+        const DEPTH: usize = 32;
+        const INDEX1: u64 = 2_u64.pow(DEPTH as u32) - 1;
+        const INDEX2: u64 = 2_u64.pow(DEPTH as u32) + 5;
+        let dir = tempfile::tempdir().unwrap();
+        let rocks_db = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+
+        let mut mt = KeccakMerkle::<DEPTH>::construct(
+            [0; 32],
+            KECCAK_DEFAULT_HASH_VEC[DEPTH],
+            Some(rocks_db.clone()),
+        );
+        let proof = mt
+            .update_leaf_data_with_proof(INDEX1, &[1; 32].to_vec())
+            .unwrap();
+        assert!(mt.verify_proof(&proof).unwrap());
+        let proof = mt
+            .update_leaf_data_with_proof(INDEX2, &[2; 32].to_vec())
+            .unwrap();
+        assert!(mt.verify_proof(&proof).unwrap());
+        assert_ne!(mt.get_root_hash(), KECCAK_DEFAULT_HASH_VEC[DEPTH]);
+
+        let mt = KeccakMerkle::<DEPTH>::construct([0; 32], mt.get_root_hash(), Some(rocks_db));
+        let (leaf, proof) = mt.get_leaf_with_proof(INDEX1).unwrap();
+        assert_eq!(leaf.data.unwrap(), [1; 32]);
+        assert_eq!(proof.source, keccak256(&[1; 32]));
+        assert!(mt.verify_proof(&proof).unwrap());
+        let (leaf, proof) = mt.get_leaf_with_proof(INDEX2).unwrap();
+        assert_eq!(leaf.data.unwrap(), [2; 32]);
+        assert!(mt.verify_proof(&proof).unwrap());
+    }
+
+    #[test]
+    /* Update some leaves of a 32 height m tree, then delete them one by one
+     * and check that the tree returns to the default root.
+     */
+    fn test_mongo_merkle_delete_leaves() {
+        const DEPTH: usize = 32;
+        const FIRST: u64 = 2_u64.pow(DEPTH as u32) - 1;
+        const INDEXES: [u64; 4] = [FIRST, FIRST + 1, FIRST + 6, FIRST + (1 << 20)];
+        let dir = tempfile::tempdir().unwrap();
+        let rocks_db = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+
+        let mut mt = RocksMerkle::<DEPTH>::construct(
+            [0; 32],
+            DEFAULT_HASH_VEC[DEPTH],
+            Some(rocks_db.clone()),
+        );
+        for (i, index) in INDEXES.iter().enumerate() {
+            let data = field_to_bytes(&Fr::from(i as u64 + 1)).to_vec();
+            mt.update_leaf_data_with_proof(*index, &data).unwrap();
+        }
+        let full_root = mt.get_root_hash();
+
+        for (i, index) in INDEXES.iter().enumerate() {
+            assert_ne!(mt.get_root_hash(), DEFAULT_HASH_VEC[DEPTH]);
+            let proof = mt.delete_leaf_with_proof(*index).unwrap();
+            assert_eq!(proof.source, DEFAULT_HASH_VEC[0]);
+            assert!(mt.verify_proof(&proof).unwrap());
+            let (leaf, _) = mt.get_leaf_with_proof(*index).unwrap();
+            assert_eq!(leaf.data, None);
+            // the leaves that are not deleted yet are kept
+            for rest in INDEXES[i + 1..].iter() {
+                let (leaf, proof) = mt.get_leaf_with_proof(*rest).unwrap();
+                assert!(leaf.data.is_some());
+                assert!(mt.verify_proof(&proof).unwrap());
+            }
+        }
+        assert_eq!(mt.get_root_hash(), DEFAULT_HASH_VEC[DEPTH]);
+
+        // the old root is still readable after the deletes
+        let mt = RocksMerkle::<DEPTH>::construct([0; 32], full_root, Some(rocks_db));
+        let (leaf, proof) = mt.get_leaf_with_proof(INDEXES[0]).unwrap();
+        assert_eq!(leaf.data.unwrap(), field_to_bytes(&Fr::from(1u64)));
+        assert!(mt.verify_proof(&proof).unwrap());
+    }
+
+    #[test]
+    /* The default proof built without a tree db is the one of the default tree
+     */
+    fn test_empty_tree_proof() {
+        const DEPTH: usize = 32;
+        let dir = tempfile::tempdir().unwrap();
+        let rocks_db = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+        let mt = RocksMerkle::<DEPTH>::construct([0; 32], DEFAULT_HASH_VEC[DEPTH], Some(rocks_db));
+        let proof = RocksMerkle::<DEPTH>::empty_tree_proof();
+        let default_proof = mt.default_proof();
+        assert_eq!(proof.source, default_proof.source);
+        assert_eq!(proof.root, default_proof.root);
+        assert_eq!(proof.assist, default_proof.assist);
+        assert_eq!(proof.index, default_proof.index);
+        assert!(mt.verify_proof(&proof).unwrap());
+        let (_, leaf_proof) = mt.get_leaf_with_proof(proof.index).unwrap();
+        assert_eq!(leaf_proof.assist, proof.assist);
+    }
+
+    #[test]
+    /* Update a leaf of 8 words of a 32 height wide m tree, then load the tree
+     * from DB by its root and check the words and the proof of the leaf.
+     */
+    fn test_wide_merkle_leaf_update() {
+        const DEPTH: usize = 32;
+        const N: usize = 8;
+        const INDEX1: u64 = 2_u64.pow(DEPTH as u32) + 3;
+        let dir = tempfile::tempdir().unwrap();
+        let rocks_db = Rc::new(RefCell::new(RocksDB::new(dir.path()).unwrap()));
+
+        let default_root = WideMerkle::<DEPTH, N>::default().get_root_hash();
+        let mut mt =
+            WideMerkle::<DEPTH, N>::construct([0; 32], default_root, Some(rocks_db.clone()));
+        let data = (0..N as u64)
+            .flat_map(|x| field_to_bytes(&Fr::from(x + 1)))
+            .collect::<Vec<u8>>();
+        let proof = mt.update_leaf_data_with_proof(INDEX1, &data).unwrap();
+        assert!(mt.verify_proof(&proof).unwrap());
+        assert_ne!(mt.get_root_hash(), default_root);
+
+        let mt = WideMerkle::<DEPTH, N>::construct([0; 32], mt.get_root_hash(), Some(rocks_db));
+        let (leaf, proof) = mt.get_leaf_with_proof(INDEX1).unwrap();
+        assert_eq!(leaf.data, None);
+        assert_eq!(leaf.wide_data.as_ref().unwrap(), &data);
+        assert!(mt.verify_proof(&proof).unwrap());
+
+        let record = MerkleRecord::from_slice(&leaf.to_slice()).unwrap();
+        assert_eq!(record.wide_data, leaf.wide_data);
+    }
 }
@@ -6,14 +6,39 @@ pub const PREFIX_CHALLENGE: u64 = 0u64;
 pub const PREFIX_POINT: u64 = 1u64;
 pub const PREFIX_SCALAR: u64 = 2u64;
 
+/// The number of partial rounds of the poseidon hasher of width t
+pub fn partial_rounds(t: usize) -> usize {
+    match t {
+        3 => 57,
+        5 => 60,
+        9 => 63,
+        _ => unreachable!("unsupported poseidon width {}", t),
+    }
+}
+
+/// The sponge hasher of width T used by the poseidon host calls
+pub fn poseidon_hasher<const T: usize, const RATE: usize>() -> Poseidon<Fr, T, RATE> {
+    Poseidon::<Fr, T, RATE>::new(8, partial_rounds(T))
+}
+
+/// The spec of the sponge hasher of width T used by the poseidon host calls
+pub fn poseidon_spec<const T: usize, const RATE: usize>() -> Spec<Fr, T, RATE> {
+    Spec::new(8, partial_rounds(T))
+}
+
 // We have two hasher here
 // 1. MERKLE_HASHER that is used for non sponge hash for hash two merkle siblings
 // 2. POSEIDON_HASHER thas is use for poseidon hash of data
+//    with POSEIDON_T3_HASHER and POSEIDON_T5_HASHER for data of fewer elements
 lazy_static::lazy_static! {
     pub static ref POSEIDON_HASHER: poseidon::Poseidon<Fr, 9, 8> = Poseidon::<Fr, 9, 8>::new(8, 63);
+    pub static ref POSEIDON_T3_HASHER: poseidon::Poseidon<Fr, 3, 2> = poseidon_hasher::<3, 2>();
+    pub static ref POSEIDON_T5_HASHER: poseidon::Poseidon<Fr, 5, 4> = poseidon_hasher::<5, 4>();
     pub static ref MERKLE_HASHER: poseidon::Poseidon<Fr, 3, 2> = Poseidon::<Fr, 3, 2>::new(8, 57);
     pub static ref MERKLE_LEAF_HASHER: poseidon::Poseidon<Fr, 3, 2> = Poseidon::<Fr, 3, 2>::new(8, 57);
     pub static ref POSEIDON_HASHER_SPEC: poseidon::Spec<Fr, 9, 8> = Spec::new(8, 63);
+    pub static ref POSEIDON_T3_HASHER_SPEC: poseidon::Spec<Fr, 3, 2> = poseidon_spec::<3, 2>();
+    pub static ref POSEIDON_T5_HASHER_SPEC: poseidon::Spec<Fr, 5, 4> = poseidon_spec::<5, 4>();
     pub static ref MERKLE_HASHER_SPEC: poseidon::Spec<Fr, 3, 2> = Spec::new(8, 57);
     pub static ref MERKLE_LEAF_HASHER_SPEC: poseidon::Spec<Fr, 3, 2> = Spec::new(8, 57);
 }
@@ -31,4 +56,21 @@ mod tests {
         println!("hash result is {:?}", result);
         assert_eq!(result.to_string(), ZERO_HASHER_SQUEEZE);
     }
+
+    #[test]
+    fn test_poseidon_widths() {
+        // an empty squeeze hashes the padded block [1, 0, ...] like the default rounds
+        let t3 = super::POSEIDON_T3_HASHER
+            .clone()
+            .update_exact(&[Fr::one(), Fr::zero()]);
+        let t5 = super::POSEIDON_T5_HASHER.clone().update_exact(&[
+            Fr::one(),
+            Fr::zero(),
+            Fr::zero(),
+            Fr::zero(),
+        ]);
+        assert_ne!(t3, t5);
+        assert_eq!(super::POSEIDON_T3_HASHER.clone().squeeze(), t3);
+        assert_eq!(super::POSEIDON_T5_HASHER.clone().squeeze(), t5);
+    }
 }
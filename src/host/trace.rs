@@ -0,0 +1,299 @@
+use super::{ExternalHostCallEntry, ExternalHostCallEntryTable};
+use anyhow::anyhow;
+use serde::de::{Error as DeError, SeqAccess, Visitor};
+use serde::Deserializer;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+/* A binary trace starts with TRACE_MAGIC and is followed by one record per entry:
+ * op: unsigned LEB128 varint
+ * value: u64 in little endian
+ * flag: one byte, the lowest bit is is_ret
+ */
+pub const TRACE_MAGIC: [u8; 4] = *b"HCT\x01";
+
+const FLAG_IS_RET: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    Json,
+    Binary,
+}
+
+pub struct HostCallTraceWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> HostCallTraceWriter<W> {
+    pub fn new(mut writer: W) -> std::io::Result<Self> {
+        writer.write_all(&TRACE_MAGIC)?;
+        Ok(HostCallTraceWriter { writer })
+    }
+
+    pub fn write_entry(&mut self, entry: &ExternalHostCallEntry) -> std::io::Result<()> {
+        let mut op = entry.op as u64;
+        loop {
+            let byte = (op & 0x7f) as u8;
+            op >>= 7;
+            if op == 0 {
+                self.writer.write_all(&[byte])?;
+                break;
+            }
+            self.writer.write_all(&[byte | 0x80])?;
+        }
+        self.writer.write_all(&entry.value.to_le_bytes())?;
+        let flag = if entry.is_ret { FLAG_IS_RET } else { 0 };
+        self.writer.write_all(&[flag])
+    }
+
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Iterates the entries of a binary trace without loading the whole trace.
+pub struct HostCallTraceReader<R: Read> {
+    reader: R,
+    position: usize,
+}
+
+impl<R: Read> HostCallTraceReader<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != TRACE_MAGIC {
+            return Err(anyhow!("invalid binary trace header {:?}", magic));
+        }
+        Ok(HostCallTraceReader {
+            reader,
+            position: 0,
+        })
+    }
+
+    fn read_entry(&mut self) -> anyhow::Result<Option<ExternalHostCallEntry>> {
+        let mut byte = [0u8; 1];
+        // the stream may only end right before an entry
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut op = 0u64;
+        let mut shift = 0;
+        loop {
+            if shift >= 64 {
+                return Err(anyhow!("opcode varint overflow"));
+            }
+            op |= ((byte[0] & 0x7f) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            self.reader.read_exact(&mut byte)?;
+        }
+
+        let mut value = [0u8; 8];
+        self.reader.read_exact(&mut value)?;
+        self.reader.read_exact(&mut byte)?;
+        if byte[0] & !FLAG_IS_RET != 0 {
+            return Err(anyhow!("unknown entry flag {}", byte[0]));
+        }
+
+        self.position += 1;
+        Ok(Some(ExternalHostCallEntry {
+            op: op as usize,
+            value: u64::from_le_bytes(value),
+            is_ret: byte[0] & FLAG_IS_RET != 0,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for HostCallTraceReader<R> {
+    type Item = anyhow::Result<ExternalHostCallEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let position = self.position;
+        self.read_entry()
+            .map_err(|e| anyhow!("malformed trace entry {}: {}", position, e))
+            .transpose()
+    }
+}
+
+/// Peek the format of a trace without consuming any byte.
+pub fn detect_trace_format<R: BufRead>(reader: &mut R) -> std::io::Result<TraceFormat> {
+    if reader.fill_buf()?.starts_with(&TRACE_MAGIC) {
+        Ok(TraceFormat::Binary)
+    } else {
+        Ok(TraceFormat::Json)
+    }
+}
+
+pub fn load_host_call_table(path: &Path) -> anyhow::Result<ExternalHostCallEntryTable> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match detect_trace_format(&mut reader)? {
+        TraceFormat::Binary => Ok(ExternalHostCallEntryTable(
+            HostCallTraceReader::new(reader)?.collect::<anyhow::Result<Vec<_>>>()?,
+        )),
+        TraceFormat::Json => Ok(serde_json::from_reader(reader)?),
+    }
+}
+
+pub fn save_host_call_table(
+    table: &ExternalHostCallEntryTable,
+    path: &Path,
+    format: TraceFormat,
+) -> anyhow::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    match format {
+        TraceFormat::Binary => {
+            let mut trace_writer = HostCallTraceWriter::new(writer)?;
+            for entry in table.0.iter() {
+                trace_writer.write_entry(entry)?;
+            }
+            trace_writer.finish()?;
+        }
+        TraceFormat::Json => {
+            let mut writer = writer;
+            serde_json::to_writer(&mut writer, table)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+struct BinaryEntrySink<'a, W: Write>(&'a mut HostCallTraceWriter<W>);
+
+impl<'de, 'a, W: Write> Visitor<'de> for BinaryEntrySink<'a, W> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of host call entries")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let mut count = 0;
+        while let Some(entry) = seq.next_element::<ExternalHostCallEntry>()? {
+            self.0.write_entry(&entry).map_err(A::Error::custom)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Convert a trace into the other format entry by entry and return the
+/// number of converted entries together with the format of the output.
+pub fn convert_host_call_table(
+    input: &Path,
+    output: &Path,
+) -> anyhow::Result<(usize, TraceFormat)> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    match detect_trace_format(&mut reader)? {
+        TraceFormat::Json => {
+            let mut trace_writer = HostCallTraceWriter::new(writer)?;
+            let mut de = serde_json::Deserializer::from_reader(reader);
+            let count = de.deserialize_seq(BinaryEntrySink(&mut trace_writer))?;
+            de.end()?;
+            trace_writer.finish()?;
+            Ok((count, TraceFormat::Binary))
+        }
+        TraceFormat::Binary => {
+            let mut count = 0;
+            writer.write_all(b"[")?;
+            for entry in HostCallTraceReader::new(reader)? {
+                if count != 0 {
+                    writer.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut writer, &entry?)?;
+                count += 1;
+            }
+            writer.write_all(b"]")?;
+            writer.flush()?;
+            Ok((count, TraceFormat::Json))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        convert_host_call_table, load_host_call_table, save_host_call_table, HostCallTraceReader,
+        HostCallTraceWriter, TraceFormat,
+    };
+    use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable};
+    use std::io::Cursor;
+
+    fn sample_table() -> ExternalHostCallEntryTable {
+        ExternalHostCallEntryTable(
+            (0..1000)
+                .map(|i| ExternalHostCallEntry {
+                    op: i % 300,
+                    value: (i as u64).wrapping_mul(0x9e3779b97f4a7c15),
+                    is_ret: i % 3 == 0,
+                })
+                .collect(),
+        )
+    }
+
+    fn same_entries(a: &ExternalHostCallEntryTable, b: &ExternalHostCallEntryTable) -> bool {
+        a.0.len() == b.0.len()
+            && a.0
+                .iter()
+                .zip(b.0.iter())
+                .all(|(x, y)| x.op == y.op && x.value == y.value && x.is_ret == y.is_ret)
+    }
+
+    #[test]
+    fn test_binary_trace_round_trip() {
+        let table = sample_table();
+        let mut writer = HostCallTraceWriter::new(vec![]).unwrap();
+        for entry in table.0.iter() {
+            writer.write_entry(entry).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let entries = HostCallTraceReader::new(Cursor::new(bytes.clone()))
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert!(same_entries(&table, &ExternalHostCallEntryTable(entries)));
+
+        // a truncated trace must be rejected
+        let truncated = HostCallTraceReader::new(Cursor::new(bytes[..bytes.len() - 1].to_vec()))
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>();
+        assert!(truncated.is_err());
+    }
+
+    #[test]
+    fn test_trace_conversion() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("trace.json");
+        let binary = dir.path().join("trace.bin");
+        let json_back = dir.path().join("trace_back.json");
+        let table = sample_table();
+        save_host_call_table(&table, &json, TraceFormat::Json).unwrap();
+
+        let (count, format) = convert_host_call_table(&json, &binary).unwrap();
+        assert_eq!(count, table.0.len());
+        assert_eq!(format, TraceFormat::Binary);
+        assert!(same_entries(
+            &table,
+            &load_host_call_table(&binary).unwrap()
+        ));
+
+        let (count, format) = convert_host_call_table(&binary, &json_back).unwrap();
+        assert_eq!(count, table.0.len());
+        assert_eq!(format, TraceFormat::Json);
+        assert!(same_entries(
+            &table,
+            &load_host_call_table(&json_back).unwrap()
+        ));
+    }
+}
@@ -4,6 +4,7 @@ pub mod adaptor;
 pub mod circuits;
 pub mod host;
 pub mod proof;
+pub mod stats;
 pub mod utils;
 
 pub extern crate anyhow;
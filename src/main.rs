@@ -4,11 +4,23 @@ mod adaptor;
 pub mod circuits;
 pub mod host;
 pub mod proof;
+pub mod stats;
 pub mod utils;
 
-use crate::proof::{exec_create_host_proof, read_host_call_table, OpType};
+use crate::adaptor::merkleadaptor::merkle_witness_of_table;
+use crate::host::db::{MongoDB, RocksDB, TreeDB};
+use crate::host::merkle::MerkleWitness;
+use crate::host::trace::convert_host_call_table;
+use crate::proof::{
+    exec_aggregate_host_proofs, exec_create_host_proof, read_host_call_table, OpType, MERKLE_DEPTH,
+};
+use crate::stats::{exec_all_host_circuit_stats, exec_host_circuit_stats};
 use clap::{arg, value_parser, App, Arg, ArgMatches};
+use ff::PrimeField;
+use halo2_proofs::pairing::bn256::Fr;
+use std::cell::RefCell;
 use std::path::PathBuf;
+use std::rc::Rc;
 
 const DEFAULT_CIRCUITS_K: u32 = 22;
 
@@ -70,6 +82,50 @@ fn parse_input_file(matches: &ArgMatches) -> PathBuf {
         .clone()
 }
 
+fn converted_file<'a>() -> Arg<'a> {
+    arg!(-o --output<OUTPUT_FILE>... "Output file of the converted host function calls")
+        .max_values(1)
+        .value_parser(value_parser!(PathBuf))
+}
+
+fn parse_converted_file(matches: &ArgMatches) -> PathBuf {
+    matches
+        .get_one::<PathBuf>("output")
+        .expect("output file is required")
+        .clone()
+}
+
+fn stats_file<'a>() -> Arg<'a> {
+    arg!(-o --output<OUTPUT_FILE>... "Output json file of the circuit statistics")
+        .required(false)
+        .max_values(1)
+        .value_parser(value_parser!(PathBuf))
+}
+
+fn commit_challenge<'a>() -> Arg<'a> {
+    arg!(--challenge<COMMIT_CHALLENGE> "Expose the commitment of the host calls under the challenge")
+        .required(false)
+}
+
+fn parse_commit_challenge(matches: &ArgMatches) -> Option<Fr> {
+    matches
+        .get_one::<String>("challenge")
+        .map(|c| Fr::from_str_vartime(c).expect("challenge should be a decimal field element"))
+}
+
+fn manifest_files<'a>() -> Arg<'a> {
+    arg!(-i --input<MANIFEST_FILES>... "Proof generation manifests of the host proofs to aggregate")
+        .value_parser(value_parser!(PathBuf))
+}
+
+fn parse_manifest_files(matches: &ArgMatches) -> Vec<PathBuf> {
+    matches
+        .get_many::<PathBuf>("input")
+        .expect("manifest files are required")
+        .cloned()
+        .collect()
+}
+
 fn opname<'a>() -> Arg<'a> {
     arg!(-n --opname<OP_NAME>... "Operation name")
         .max_values(1)
@@ -83,28 +139,264 @@ fn parse_opname(matches: &ArgMatches) -> OpType {
         .clone()
 }
 
-#[allow(clippy::many_single_char_names)]
-fn main() {
-    let clap_app = App::new("hostcircuit")
+#[derive(clap::ArgEnum, Clone, Debug)]
+enum DBType {
+    Mongo,
+    Rocksdb,
+}
+
+fn tree_db_type<'a>() -> Arg<'a> {
+    arg!(--db<DB_TYPE> "Tree db of the merkle host calls, the default is the local mongodb")
+        .required(false)
+        .value_parser(value_parser!(DBType))
+}
+
+fn mongo_uri<'a>() -> Arg<'a> {
+    Arg::new("mongo-uri")
+        .long("mongo-uri")
+        .value_name("MONGO_URI")
+        .help("Uri of the mongodb tree db")
+        .required(false)
+        .takes_value(true)
+}
+
+fn collection_id<'a>() -> Arg<'a> {
+    Arg::new("collection-id")
+        .long("collection-id")
+        .value_name("HEX")
+        .help("Collection id of the mongodb tree db as 32 bytes in hex")
+        .required(false)
+        .takes_value(true)
+}
+
+fn rocks_path<'a>() -> Arg<'a> {
+    Arg::new("rocks-path")
+        .long("rocks-path")
+        .value_name("ROCKS_PATH")
+        .help("Path of the rocksdb tree db")
+        .required(false)
+        .value_parser(value_parser!(PathBuf))
+}
+
+fn parse_collection_id(matches: &ArgMatches) -> [u8; 32] {
+    matches
+        .get_one::<String>("collection-id")
+        .map_or([0u8; 32], |id| {
+            let bytes = hex::decode(id.trim_start_matches("0x"))
+                .expect("collection id should be hex encoded");
+            bytes.try_into().expect("collection id should be 32 bytes")
+        })
+}
+
+fn parse_tree_db(matches: &ArgMatches) -> Option<Rc<RefCell<dyn TreeDB>>> {
+    match matches.get_one::<DBType>("db")? {
+        DBType::Mongo => {
+            let uri = matches.get_one::<String>("mongo-uri").cloned();
+            let db = MongoDB::new(parse_collection_id(matches), uri);
+            Some(Rc::new(RefCell::new(db)))
+        }
+        DBType::Rocksdb => {
+            let path = matches
+                .get_one::<PathBuf>("rocks-path")
+                .expect("rocks path is required by the rocksdb tree db");
+            let db = RocksDB::new(path).unwrap_or_else(|e| {
+                eprintln!("open rocksdb error {:?}", e);
+                std::process::exit(1);
+            });
+            Some(Rc::new(RefCell::new(db)))
+        }
+    }
+}
+
+fn merkle_witness<'a>() -> Arg<'a> {
+    Arg::new("merkle-witness")
+        .long("merkle-witness")
+        .value_name("WITNESS_FILE")
+        .help("Merkle proofs of the merkle host calls which replace the tree db")
+        .required(false)
+        .value_parser(value_parser!(PathBuf))
+}
+
+fn witness_file<'a>() -> Arg<'a> {
+    arg!(-o --output<WITNESS_FILE>... "Output file of the merkle witness")
+        .max_values(1)
+        .value_parser(value_parser!(PathBuf))
+}
+
+fn parse_merkle_witness(matches: &ArgMatches) -> Option<MerkleWitness<MERKLE_DEPTH>> {
+    matches.get_one::<PathBuf>("merkle-witness").map(|path| {
+        MerkleWitness::load(path).unwrap_or_else(|e| {
+            eprintln!("load merkle witness error {:?}", e);
+            std::process::exit(1);
+        })
+    })
+}
+
+fn host_circuit_app<'a>() -> App<'a> {
+    App::new("hostcircuit")
+        // the arguments of proving are only required without a subcommand
+        .subcommand_negates_reqs(true)
         .arg(input_file())
         .arg(output_folder())
         .arg(param_folder())
         .arg(opname())
-        .arg(circuits_k());
+        .arg(circuits_k())
+        .arg(commit_challenge())
+        .arg(tree_db_type())
+        .arg(mongo_uri())
+        .arg(collection_id())
+        .arg(rocks_path())
+        .arg(merkle_witness())
+        .subcommand(
+            App::new("convert")
+                .about("Convert host function calls between the json and the binary format")
+                .arg(input_file())
+                .arg(converted_file()),
+        )
+        .subcommand(
+            App::new("stats")
+                .about("Report rows, capacity, columns and degree of the host circuits")
+                .arg(opname().required(false))
+                .arg(circuits_k())
+                .arg(stats_file()),
+        )
+        .subcommand(
+            App::new("merklewitness")
+                .about("Write the merkle witness of the merkle host calls by replaying them")
+                .arg(input_file())
+                .arg(witness_file())
+                .arg(tree_db_type())
+                .arg(mongo_uri())
+                .arg(collection_id())
+                .arg(rocks_path()),
+        )
+        .subcommand(
+            App::new("aggregate")
+                .about("Aggregate host proofs into one batch proof")
+                .arg(manifest_files())
+                .arg(output_folder())
+                .arg(param_folder())
+                .arg(circuits_k()),
+        )
+}
+
+#[allow(clippy::many_single_char_names)]
+fn main() {
+    let matches = host_circuit_app().get_matches();
+
+    if let Some(sub_matches) = matches.subcommand_matches("convert") {
+        let input_file = parse_input_file(sub_matches);
+        let output_file = parse_converted_file(sub_matches);
+        match convert_host_call_table(&input_file, &output_file) {
+            Ok((count, format)) => println!("converted {} entries into {:?}", count, format),
+            Err(e) => {
+                eprintln!("convert host call table error {:?}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("stats") {
+        let k = parse_circuits_k(sub_matches) as usize;
+        let stats = match sub_matches.get_one::<OpType>("opname") {
+            Some(opname) => exec_host_circuit_stats(opname.clone(), k).map(|s| vec![s]),
+            None => exec_all_host_circuit_stats(k),
+        }
+        .expect("synthesize host circuit error");
+        let json = serde_json::to_string_pretty(&stats).unwrap();
+        match sub_matches.get_one::<PathBuf>("output") {
+            Some(output) => std::fs::write(output, json).expect("can not write stats file"),
+            None => println!("{}", json),
+        }
+        return;
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("merklewitness") {
+        let table = read_host_call_table(parse_input_file(sub_matches)).unwrap_or_else(|e| {
+            eprintln!("load host call table error {:?}", e);
+            std::process::exit(1);
+        });
+        let witness = merkle_witness_of_table::<MERKLE_DEPTH>(&table, parse_tree_db(sub_matches));
+        let output_file = parse_converted_file(sub_matches);
+        witness.save(&output_file).unwrap_or_else(|e| {
+            eprintln!("save merkle witness error {:?}", e);
+            std::process::exit(1);
+        });
+        println!("wrote {} merkle proofs", witness.0.len());
+        return;
+    }
+
+    if let Some(sub_matches) = matches.subcommand_matches("aggregate") {
+        exec_aggregate_host_proofs(
+            "host.aggregate",
+            parse_circuits_k(sub_matches) as usize,
+            &parse_manifest_files(sub_matches),
+            &parse_output_folder(sub_matches),
+            &parse_param_folder(sub_matches),
+        );
+        return;
+    }
 
-    let matches = clap_app.get_matches();
     let input_file = parse_input_file(&matches);
     let cache_folder = parse_output_folder(&matches);
     let param_folder = parse_param_folder(&matches);
     let opname = parse_opname(&matches);
     let k = parse_circuits_k(&matches);
+    let challenge = parse_commit_challenge(&matches);
+    let tree_db = parse_tree_db(&matches);
+    let merkle_witness = parse_merkle_witness(&matches);
+
+    let table = read_host_call_table(input_file).unwrap_or_else(|e| {
+        eprintln!("load host call table error {:?}", e);
+        std::process::exit(1);
+    });
 
     exec_create_host_proof(
         "host",
         k as usize,
-        &read_host_call_table(input_file),
+        &table,
         opname,
         &cache_folder,
         &param_folder,
-    );
+        challenge,
+        tree_db,
+        merkle_witness,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("create host proof error {:?}", e);
+        std::process::exit(1);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::host_circuit_app;
+
+    fn parse(args: &str) -> Result<clap::ArgMatches, clap::Error> {
+        host_circuit_app().try_get_matches_from(args.split_whitespace())
+    }
+
+    #[test]
+    fn test_subcommands_without_proving_args() {
+        let matches = parse("hostcircuit convert -i a.json -o b.bin").unwrap();
+        assert!(matches.subcommand_matches("convert").is_some());
+
+        let matches = parse("hostcircuit stats -k 22").unwrap();
+        assert!(matches.subcommand_matches("stats").is_some());
+
+        let matches = parse("hostcircuit aggregate -i a.json -o output -p params").unwrap();
+        assert!(matches.subcommand_matches("aggregate").is_some());
+
+        let matches =
+            parse("hostcircuit merklewitness -i a.json -o w.json --db rocksdb --rocks-path db")
+                .unwrap();
+        assert!(matches.subcommand_matches("merklewitness").is_some());
+    }
+
+    #[test]
+    fn test_proving_args_required() {
+        assert!(parse("hostcircuit -k 22").is_err());
+        assert!(parse("hostcircuit -i a.json -o output -p params -n poseidonhash").is_ok());
+    }
 }
@@ -154,6 +154,7 @@ mod tests {
             left: Some([2u8; 32]),
             right: Some([3u8; 32]),
             data: None,
+            wide_data: None,
         };
 
         let merkle_record2 = MerkleRecord {
@@ -162,6 +163,7 @@ mod tests {
             left: Some([5u8; 32]),
             right: Some([6u8; 32]),
             data: None,
+            wide_data: None,
         };
 
         // Create test Data records
@@ -1,12 +1,20 @@
-use crate::circuits::babyjub::AltJubChip;
+use crate::adaptor::combinedadaptor::CombinedHelper;
+use crate::adaptor::get_selected_entries;
+use crate::adaptor::merkleadaptor::MerkleHelper;
+use crate::circuits::babyjub::{AltJubBaseChip, AltJubChip};
 use crate::circuits::{
     // bls::Bls381PairChip,
     // bls::Bls381SumChip,
+    bn256::Bn256MultiPairChip,
     bn256::Bn256PairChip,
     bn256::Bn256SumChip,
-    host::{HostOpChip, HostOpConfig, HostOpSelector},
+    combined::CombinedChip,
+    host::{host_op_commitment, HostCommitConfig, HostOpChip, HostOpChipConfig, HostOpSelector},
+    jubjubverify::JubjubVerifyChip,
     keccak256::KeccakChip,
+    keccakmerkle::KeccakMerkleChip,
     merkle::MerkleChip,
+    merklewide::MerkleWideChip,
     poseidon::PoseidonChip,
 };
 use halo2_proofs::circuit::floor_planner::FlatFloorPlanner;
@@ -15,18 +23,32 @@ use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::Layouter,
     pairing::bn256::Fr,
-    plonk::{Circuit, ConstraintSystem, Error},
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    marker::PhantomData,
+    path::PathBuf,
 };
-use std::{fs::File, io::BufReader, marker::PhantomData, path::PathBuf};
 
-use circuits_batcher::args::HashType::Poseidon;
+use circuits_batcher::args::HashType::{Poseidon, Sha};
 use circuits_batcher::args::OpenSchema;
-use circuits_batcher::proof::{ParamsCache, ProofGenerationInfo, ProofPieceInfo, ProvingKeyCache};
+use circuits_batcher::batch::BatchInfo;
+use circuits_batcher::proof::{
+    ParamsCache, ProofGenerationInfo, ProofInfo, ProofPieceInfo, ProvingKeyCache,
+};
 
-use crate::host::ExternalHostCallEntryTable;
+use crate::host::db::TreeDB;
+use crate::host::merkle::MerkleWitness;
+use crate::host::trace::load_host_call_table;
+use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable};
 use serde::{Deserialize, Serialize};
 
 pub const MERKLE_DEPTH: usize = 32;
+pub const MERKLE_WIDE_LEAF_WORDS: usize = 8;
 
 #[derive(clap::Parser)]
 struct ArgOpName {
@@ -38,29 +60,42 @@ pub enum OpType {
     // BLS381PAIR,
     // BLS381SUM,
     BN256PAIR,
+    BN256MULTIPAIR,
     BN256SUM,
     POSEIDONHASH,
+    POSEIDONT3HASH,
+    POSEIDONT5HASH,
     KECCAKHASH,
     MERKLE,
+    KECCAKMERKLE,
+    MERKLEWIDE,
     JUBJUBSUM,
+    JUBJUBBASESUM,
+    JUBJUBVERIFY,
+    COMBINED,
 }
 
+/// The host circuit of selector `S`. When `COMMIT` is enabled the circuit
+/// exposes the instances `[challenge, commitment, selected entries count]`,
+/// where the commitment is computed by `host_op_commitment`.
 #[derive(Clone)]
-pub struct HostOpCircuit<F: FieldExt, S: HostOpSelector> {
+pub struct HostOpCircuit<F: FieldExt, S: HostOpSelector, const COMMIT: bool = false> {
     shared_operands: Vec<F>,
     shared_opcodes: Vec<F>,
     helper: S::Helper,
     k: usize,
+    challenge: F,
     _marker: PhantomData<(F, S)>,
 }
 
-impl<F: FieldExt, S: HostOpSelector> Default for HostOpCircuit<F, S> {
+impl<F: FieldExt, S: HostOpSelector, const COMMIT: bool> Default for HostOpCircuit<F, S, COMMIT> {
     fn default() -> Self {
         HostOpCircuit {
             shared_operands: Vec::<F>::default(),
             shared_opcodes: Vec::<F>::default(),
             k: 22,
             helper: S::Helper::default(),
+            challenge: F::zero(),
             _marker: PhantomData,
         }
     }
@@ -68,11 +103,20 @@ impl<F: FieldExt, S: HostOpSelector> Default for HostOpCircuit<F, S> {
 
 #[derive(Clone)]
 pub struct HostCircuitConfig<C: Clone> {
-    hostconfig: HostOpConfig,
-    selectconfig: C,
+    pub(crate) hostconfig: HostOpChipConfig,
+    pub(crate) selectconfig: C,
+    commitconfig: Option<(HostCommitConfig, Column<Instance>)>,
 }
 
-impl<S: HostOpSelector> Circuit<Fr> for HostOpCircuit<Fr, S> {
+/// Indices of the advice columns of the commitment, the challenge and the
+/// count of `HostOpCircuit<Fr, S, true>`, whose first rows are copied to the
+/// instances `[challenge, commitment, count]`
+pub(crate) const HOST_COMMIT_COLUMNS: [usize; 3] = [0, 1, 2];
+
+/// Name of the region where the operands and opcodes are filtered
+pub(crate) const FILTER_REGION: &str = "filter operands and opcodes";
+
+impl<S: HostOpSelector, const COMMIT: bool> Circuit<Fr> for HostOpCircuit<Fr, S, COMMIT> {
     // Since we are using a single chip for everything, we can just reuse its config.
     type Config = HostCircuitConfig<S::Config>;
     type FloorPlanner = FlatFloorPlanner;
@@ -82,6 +126,16 @@ impl<S: HostOpSelector> Circuit<Fr> for HostOpCircuit<Fr, S> {
     }
 
     fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        // the commitment columns come first so that they are at HOST_COMMIT_COLUMNS
+        let commit_witness = if COMMIT {
+            Some([
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ])
+        } else {
+            None
+        };
         let shared_advices = vec![
             meta.advice_column(),
             meta.advice_column(),
@@ -90,50 +144,193 @@ impl<S: HostOpSelector> Circuit<Fr> for HostOpCircuit<Fr, S> {
             meta.advice_column(),
             meta.advice_column(),
         ];
+        let hostconfig = HostOpChip::<Fr, S>::configure(meta, &shared_advices);
+        let commitconfig = if let Some(witness) = commit_witness {
+            witness.map(|x| meta.enable_equality(x));
+            let instance = meta.instance_column();
+            let config = HostCommitConfig::new(witness, [], []);
+            config.configure(meta, &hostconfig.host, instance);
+            Some((config, instance))
+        } else {
+            None
+        };
         // We create the two advice columns that FieldChip uses for I/O.
         HostCircuitConfig {
-            hostconfig: HostOpChip::<Fr, S>::configure(meta, &shared_advices),
+            hostconfig,
             selectconfig: S::configure(meta, &shared_advices),
+            commitconfig,
         }
     }
 
     fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
         let host_op_chip =
             HostOpChip::<Fr, S>::construct(config.hostconfig.clone(), config.selectconfig.clone());
-        let (all_arg_cells, mut selector_chip) = layouter.assign_region(
-            || "filter operands and opcodes",
+        let (all_arg_cells, commitment, mut selector_chip) = layouter.assign_region(
+            || FILTER_REGION,
             |region| {
                 let mut offset = 0;
-                let all_arg_cells = host_op_chip.assign(
+                let (all_arg_cells, selected_total) = host_op_chip.assign(
                     &region,
                     self.k,
                     &mut offset,
                     &self.shared_operands,
                     &self.shared_opcodes,
                 )?;
+                let commitment = match config.commitconfig.as_ref() {
+                    Some((commitconfig, _)) => {
+                        let selected_entries = get_selected_entries(
+                            &self.shared_operands,
+                            &self.shared_opcodes,
+                            &S::opcodes(),
+                        );
+                        let (challenge, digest, count) = commitconfig.assign(
+                            &region,
+                            offset,
+                            &selected_entries,
+                            self.challenge,
+                            &selected_total,
+                        )?;
+                        Some([challenge, digest, count])
+                    }
+                    None => None,
+                };
                 let mut selector_chip = S::construct(config.selectconfig.clone());
 
                 println!("total arg cells: {:?}", all_arg_cells.len());
                 println!("selector offset start at: {:?}", offset);
                 selector_chip.synthesize(&mut offset, &all_arg_cells, &region, &self.helper)?;
-                Ok((all_arg_cells, selector_chip))
+                Ok((all_arg_cells, commitment, selector_chip))
             },
         )?;
+        if let (Some((_, instance)), Some(commitment)) = (config.commitconfig, commitment) {
+            for (row, limb) in commitment.iter().enumerate() {
+                layouter.constrain_instance(limb.get_the_cell().cell(), instance, row)?;
+            }
+        }
         selector_chip.synthesize_separate(&all_arg_cells, &layouter)?;
         Ok(())
     }
 }
 
-pub fn read_host_call_table(input_file: PathBuf) -> ExternalHostCallEntryTable {
-    let file = File::open(input_file).expect("File does not exist");
-    let v: ExternalHostCallEntryTable = match serde_json::from_reader(BufReader::new(file)) {
-        Err(e) => {
-            println!("load json error {:?}", e);
-            unreachable!();
+/// Load a host call table in either the json or the binary trace format.
+pub fn read_host_call_table(input_file: PathBuf) -> anyhow::Result<ExternalHostCallEntryTable> {
+    load_host_call_table(&input_file)
+}
+
+/// A piece of the selected host calls of a trace that fits into a single circuit.
+///
+/// `start..end` are positions among the `total` entries of the trace selected
+/// by the opcodes of the circuit, not positions in the trace. The circuit counts
+/// the shared index down from the number of selected entries, so its local
+/// shared index `i` is the shared index `total - end + i` of the selected
+/// entries of the full trace. `trace_start..trace_end` is the range of positions
+/// in the full trace from the first to past the last selected entry of the
+/// segment, where the entries of other opcodes in between are not proved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HostCallTableSegment {
+    pub start: usize,
+    pub end: usize,
+    pub total: usize,
+    pub trace_start: usize,
+    pub trace_end: usize,
+}
+
+/// Split the host calls selected by `S` into tables that each fit into a
+/// circuit of size `k`. Splits only happen on round boundaries where the next
+/// round does not depend on the state left by the previous one. A trace whose
+/// selected entries do not form complete rounds is rejected.
+pub fn split_host_call_table<S: HostOpSelector>(
+    v: &ExternalHostCallEntryTable,
+    k: usize,
+) -> anyhow::Result<Vec<(ExternalHostCallEntryTable, HostCallTableSegment)>> {
+    let opcodes = S::opcodes();
+    let (trace_positions, selected_entries): (Vec<usize>, Vec<ExternalHostCallEntry>) =
+        v.0.iter()
+            .enumerate()
+            .filter(|(_, x)| opcodes.contains(&Fr::from(x.op as u64)))
+            .map(|(position, x)| (position, x.clone()))
+            .unzip();
+    let total = selected_entries.len();
+    let mut rounds = vec![];
+    let mut round_starts = vec![];
+    let mut position = 0;
+    while position < total {
+        let size = match S::round_size(&selected_entries[position..]) {
+            Some(size) => size,
+            None => anyhow::bail!(
+                "host call round can not start with opcode {} at trace position {}",
+                selected_entries[position].op,
+                trace_positions[position]
+            ),
+        };
+        if position + size > total {
+            anyhow::bail!(
+                "incomplete host call round at trace position {}",
+                trace_positions[position]
+            );
         }
-        Ok(o) => o,
-    };
-    v
+        round_starts.push(position);
+        rounds.push(&selected_entries[position..position + size]);
+        position += size;
+    }
+    round_starts.push(total);
+
+    if S::max_rounds(k) == 0 {
+        anyhow::bail!("host circuits are not supported when k < 22");
+    }
+
+    let mut segments = vec![];
+    let mut start = 0;
+    while start < rounds.len() {
+        // the largest end such that rounds[start..end] fits into one circuit
+        let (mut low, mut high) = (start, rounds.len());
+        while low < high {
+            let mid = (low + high + 1) / 2;
+            if S::fits_in_circuit(&rounds[start..mid], k) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        let mut end = low;
+        if end < rounds.len() {
+            while end > start && !S::is_fresh_round(rounds[end]) {
+                end -= 1;
+            }
+        }
+        if end == start {
+            anyhow::bail!(
+                "host calls starting at round {} do not fit in a single circuit",
+                start
+            );
+        }
+        let segment = HostCallTableSegment {
+            start: round_starts[start],
+            end: round_starts[end],
+            total,
+            trace_start: trace_positions[round_starts[start]],
+            trace_end: trace_positions[round_starts[end] - 1] + 1,
+        };
+        let table =
+            ExternalHostCallEntryTable(selected_entries[segment.start..segment.end].to_vec());
+        segments.push((table, segment));
+        start = end;
+    }
+
+    // an empty trace still generates one proof with only padding rounds
+    if segments.is_empty() {
+        segments.push((
+            ExternalHostCallEntryTable::default(),
+            HostCallTableSegment {
+                start: 0,
+                end: 0,
+                total: 0,
+                trace_start: 0,
+                trace_end: 0,
+            },
+        ));
+    }
+    Ok(segments)
 }
 
 pub fn build_host_circuit<S: HostOpSelector>(
@@ -150,10 +347,78 @@ pub fn build_host_circuit<S: HostOpSelector>(
         shared_opcodes,
         k,
         helper,
+        challenge: Fr::zero(),
         _marker: PhantomData,
     }
 }
 
+pub fn build_committed_host_circuit<S: HostOpSelector>(
+    v: &ExternalHostCallEntryTable,
+    k: usize,
+    helper: S::Helper,
+    challenge: Fr,
+) -> HostOpCircuit<Fr, S, true> {
+    let shared_operands = v.0.iter().map(|x| Fr::from(x.value as u64)).collect();
+    let shared_opcodes = v.0.iter().map(|x| Fr::from(x.op as u64)).collect();
+
+    HostOpCircuit::<Fr, S, true> {
+        shared_operands,
+        shared_opcodes,
+        k,
+        helper,
+        challenge,
+        _marker: PhantomData,
+    }
+}
+
+/// The instances of `HostOpCircuit<Fr, S, true>` for a host call table.
+pub fn host_op_instances<S: HostOpSelector>(
+    v: &ExternalHostCallEntryTable,
+    challenge: Fr,
+) -> Vec<Fr> {
+    let shared_operands = v.0.iter().map(|x| Fr::from(x.value as u64)).collect();
+    let shared_opcodes = v.0.iter().map(|x| Fr::from(x.op as u64)).collect();
+    let selected_entries = get_selected_entries(&shared_operands, &shared_opcodes, &S::opcodes());
+    vec![
+        challenge,
+        host_op_commitment(&selected_entries, challenge),
+        Fr::from(selected_entries.len() as u64),
+    ]
+}
+
+fn save_host_call_segments(segments: &Vec<HostCallTableSegment>, output: PathBuf) {
+    let file = File::create(output).expect("can not create segments file");
+    serde_json::to_writer_pretty(BufWriter::new(file), segments)
+        .expect("can not write segments file");
+}
+
+/// The host specific part of the manifest of the proofs of a host circuit,
+/// saved as `<circuit_name>.host.json` next to its proof generation manifest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HostProofManifest {
+    pub circuit_name: String,
+    pub proofs: usize,
+    /// whether the proofs expose the instances `[challenge, commitment, count]`
+    pub committed: bool,
+}
+
+impl HostProofManifest {
+    fn file_name(circuit_name: &str) -> String {
+        format!("{}.host.json", circuit_name)
+    }
+
+    pub fn save(&self, folder: &PathBuf) -> anyhow::Result<()> {
+        let file = File::create(folder.join(Self::file_name(&self.circuit_name)))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn load(folder: &PathBuf, circuit_name: &str) -> anyhow::Result<Self> {
+        let file = File::open(folder.join(Self::file_name(circuit_name)))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
 pub fn exec_create_host_proof(
     name: &str,
     k: usize,
@@ -161,68 +426,617 @@ pub fn exec_create_host_proof(
     opname: OpType,
     cache_folder: &PathBuf,
     param_folder: &PathBuf,
-) {
+    challenge: Option<Fr>,
+    tree_db: Option<Rc<RefCell<dyn TreeDB>>>,
+    merkle_witness: Option<MerkleWitness<MERKLE_DEPTH>>,
+) -> anyhow::Result<()> {
+    // only the poseidon merkle calls of merkle and combined read a merkle witness
+    if merkle_witness.is_some() && !matches!(opname, OpType::MERKLE | OpType::COMBINED) {
+        anyhow::bail!("merkle witness is not supported by {:?}", opname);
+    }
+
     // Instantiate the circuit with the private inputs.
     // Given the correct public input, our circuit will verify.
 
     let mut params_cache = ParamsCache::<Bn256>::new(5, param_folder.clone());
     let mut pkey_cache = ProvingKeyCache::new(5, param_folder.clone());
+    // committed circuits have a different layout and are saved under another name
+    let circuit_name = match challenge {
+        Some(_) => format!("{}.{:?}.commit", name, opname),
+        None => format!("{}.{:?}", name, opname),
+    };
+    // the proofs of a merkle witness take the place of the tree db
+    let merkle_helper = match merkle_witness {
+        Some(witness) => MerkleHelper::from_witness(witness),
+        None => MerkleHelper::TreeDB(tree_db.clone()),
+    };
     macro_rules! gen_proof {
-        ($circuit: expr) => {
-            let prover: ProofPieceInfo =
-                ProofPieceInfo::new(format!("{}.{:?}", name, opname), 0, 0, None);
-            let mut proof_gen_info =
-                ProofGenerationInfo::new(format!("{}.{:?}", name, opname).as_str(), k, Poseidon);
-            let proof = prover.exec_create_proof(
-                &$circuit,
-                &vec![],
-                k,
-                &mut pkey_cache,
-                &mut params_cache,
-                Poseidon,
-                OpenSchema::Shplonk,
-            );
-            prover.save_proof_data::<Fr>(&vec![], &proof, cache_folder);
-            //prover.mock_proof(k as u32);
-            proof_gen_info.append_single_proof(prover);
+        ($selector: ty, $helper: expr) => {
+            let segments = split_host_call_table::<$selector>(v, k)?;
+            let mut proof_gen_info = ProofGenerationInfo::new(circuit_name.as_str(), k, Poseidon);
+            for (i, (table, segment)) in segments.iter().enumerate() {
+                println!("proving segment {}: {:?}", i, segment);
+                let instances = match challenge {
+                    Some(c) => vec![host_op_instances::<$selector>(table, c)],
+                    None => vec![],
+                };
+                let prover: ProofPieceInfo = ProofPieceInfo::new(
+                    circuit_name.clone(),
+                    i,
+                    if challenge.is_some() { 3 } else { 0 },
+                    None,
+                );
+                let proof = match challenge {
+                    Some(c) => prover.exec_create_proof(
+                        &build_committed_host_circuit::<$selector>(table, k, $helper, c),
+                        &instances,
+                        k,
+                        &mut pkey_cache,
+                        &mut params_cache,
+                        Poseidon,
+                        OpenSchema::Shplonk,
+                    ),
+                    None => prover.exec_create_proof(
+                        &build_host_circuit::<$selector>(table, k, $helper),
+                        &instances,
+                        k,
+                        &mut pkey_cache,
+                        &mut params_cache,
+                        Poseidon,
+                        OpenSchema::Shplonk,
+                    ),
+                };
+                prover.save_proof_data::<Fr>(&instances, &proof, cache_folder);
+                //prover.mock_proof(k as u32);
+                proof_gen_info.append_single_proof(prover);
+            }
             proof_gen_info.save(cache_folder);
+            HostProofManifest {
+                circuit_name: circuit_name.clone(),
+                proofs: segments.len(),
+                committed: challenge.is_some(),
+            }
+            .save(cache_folder)
+            .expect("can not write host proof manifest");
+            save_host_call_segments(
+                &segments.into_iter().map(|(_, s)| s).collect(),
+                cache_folder.join(format!("{}.segments.json", circuit_name)),
+            );
         };
     }
 
     match opname {
         // OpType::BLS381PAIR => {
-        //     let circuit = build_host_circuit::<Bls381PairChip<Fr>>(&v, k, ());
-        //     gen_proof!(circuit);
+        //     gen_proof!(Bls381PairChip<Fr>, ());
         // }
         // OpType::BLS381SUM => {
-        //     let circuit = build_host_circuit::<Bls381SumChip<Fr>>(&v, k, ());
-        //     gen_proof!(circuit);
+        //     gen_proof!(Bls381SumChip<Fr>, ());
         // }
         OpType::BN256PAIR => {
-            let circuit = build_host_circuit::<Bn256PairChip<Fr>>(&v, k, ());
-            gen_proof!(circuit);
+            gen_proof!(Bn256PairChip<Fr>, ());
+        }
+        OpType::BN256MULTIPAIR => {
+            gen_proof!(Bn256MultiPairChip<Fr>, ());
         }
         OpType::BN256SUM => {
-            let circuit = build_host_circuit::<Bn256SumChip<Fr>>(&v, k, ());
-            gen_proof!(circuit);
+            gen_proof!(Bn256SumChip<Fr>, ());
         }
         OpType::POSEIDONHASH => {
-            let circuit = build_host_circuit::<PoseidonChip<Fr, 9, 8>>(&v, k, ());
-            gen_proof!(circuit);
+            gen_proof!(PoseidonChip<Fr, 9, 8>, ());
+        }
+        OpType::POSEIDONT3HASH => {
+            gen_proof!(PoseidonChip<Fr, 3, 2>, ());
+        }
+        OpType::POSEIDONT5HASH => {
+            gen_proof!(PoseidonChip<Fr, 5, 4>, ());
         }
         OpType::MERKLE => {
-            let circuit = build_host_circuit::<MerkleChip<Fr, MERKLE_DEPTH>>(&v, k, None);
-            gen_proof!(circuit);
+            gen_proof!(MerkleChip<Fr, MERKLE_DEPTH>, merkle_helper.clone());
+        }
+        OpType::KECCAKMERKLE => {
+            gen_proof!(KeccakMerkleChip<Fr, MERKLE_DEPTH>, tree_db.clone());
+        }
+        OpType::MERKLEWIDE => {
+            gen_proof!(
+                MerkleWideChip<Fr, MERKLE_DEPTH, MERKLE_WIDE_LEAF_WORDS>,
+                tree_db.clone()
+            );
         }
         OpType::JUBJUBSUM => {
-            let circuit = build_host_circuit::<AltJubChip<Fr>>(&v, k, ());
-            gen_proof!(circuit);
+            gen_proof!(AltJubChip<Fr>, ());
+        }
+        OpType::JUBJUBBASESUM => {
+            gen_proof!(AltJubBaseChip, ());
+        }
+        OpType::JUBJUBVERIFY => {
+            gen_proof!(JubjubVerifyChip<Fr>, ());
         }
         OpType::KECCAKHASH => {
-            let circuit = build_host_circuit::<KeccakChip<Fr>>(&v, k, ());
-            gen_proof!(circuit);
+            gen_proof!(KeccakChip<Fr>, ());
+        }
+        OpType::COMBINED => {
+            gen_proof!(
+                CombinedChip<MERKLE_DEPTH>,
+                CombinedHelper::new(k, merkle_helper.clone())
+            );
         }
     };
 
     println!("Proof generated.");
+    Ok(())
+}
+
+/// The [proof index, column index] pairs of the commitment columns of the
+/// committed proofs among the proofs of `manifests` batched in order. Those
+/// columns hold the instances of the committed proofs in their first rows.
+fn exposed_commit_columns(manifests: &[HostProofManifest]) -> Vec<[usize; 2]> {
+    manifests
+        .iter()
+        .flat_map(|manifest| vec![manifest.committed; manifest.proofs])
+        .enumerate()
+        .filter(|(_, is_committed)| *is_committed)
+        .flat_map(|(i, _)| HOST_COMMIT_COLUMNS.map(|column| [i, column]))
+        .collect()
+}
+
+/// Aggregate the host proofs listed in the saved proof generation manifests
+/// of `exec_create_host_proof` into one final batch proof. The batch proof and
+/// its manifest are written to `cache_folder` while the verifier parameters of
+/// size `k` are kept in `param_folder`.
+pub fn exec_aggregate_host_proofs(
+    name: &str,
+    k: usize,
+    manifests: &Vec<PathBuf>,
+    cache_folder: &PathBuf,
+    param_folder: &PathBuf,
+) {
+    let mut params_cache = ParamsCache::<Bn256>::new(5, param_folder.clone());
+    let mut pkey_cache = ProvingKeyCache::new(5, param_folder.clone());
+
+    let mut proofs = vec![];
+    let mut host_manifests = vec![];
+    for manifest in manifests.iter() {
+        let proof_gen_info = ProofGenerationInfo::load(manifest);
+        println!(
+            "load {} proofs of {}",
+            proof_gen_info.proofs.len(),
+            proof_gen_info.circuit_name
+        );
+        let loaded = ProofInfo::<Bn256>::load_proof(cache_folder, param_folder, &proof_gen_info);
+        let folder = manifest.parent().unwrap().to_path_buf();
+        let host_manifest = HostProofManifest::load(&folder, &proof_gen_info.circuit_name)
+            .expect("can not read host proof manifest");
+        assert_eq!(host_manifest.proofs, loaded.len());
+        host_manifests.push(host_manifest);
+        proofs.extend(loaded);
+    }
+    assert!(!proofs.is_empty(), "no host proofs to aggregate");
+
+    // host proofs are independent, thus no commitments are shared between them
+    // while the commitments of the committed ones are exposed by the batch proof
+    let batch_info = BatchInfo::<Bn256> {
+        proofs,
+        target_k: k,
+        batch_k: k,
+        equivalents: vec![],
+        absorb: vec![],
+        expose: exposed_commit_columns(&host_manifests),
+        is_final: true,
+    };
+
+    // the final proof is verified on chain so it uses the sha transcript
+    let mut proof_gen_info = ProofGenerationInfo::new(name, k, Sha);
+    let batch_proof = batch_info.batch_proof(
+        name.to_string(),
+        cache_folder,
+        &mut params_cache,
+        &mut pkey_cache,
+        Sha,
+        OpenSchema::Shplonk,
+    );
+    proof_gen_info.append_single_proof(batch_proof);
+    proof_gen_info.save(cache_folder);
+
+    println!("Aggregated proof generated.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_committed_host_circuit, build_host_circuit, exec_aggregate_host_proofs,
+        exec_create_host_proof, exposed_commit_columns, host_op_instances, split_host_call_table,
+        HostOpCircuit, HostProofManifest, OpType, HOST_COMMIT_COLUMNS,
+    };
+    use crate::adaptor::hashadaptor::hash_to_host_call_table;
+    use crate::circuits::host::{HostCommitConfig, TestSelector};
+    use crate::circuits::poseidon::PoseidonChip;
+    use crate::host::merkle::MerkleWitness;
+    use crate::host::ForeignInst::{
+        Keccak256Push, Log, PoseidonFinalize, PoseidonNew, PoseidonPush,
+    };
+    use crate::host::{ExternalHostCallEntry, ExternalHostCallEntryTable, ForeignInst};
+    use halo2_proofs::arithmetic::FieldExt;
+    use halo2_proofs::circuit::{floor_planner::FlatFloorPlanner, FloorPlanner};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::Fr;
+    use halo2_proofs::plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+        Instance, Selector,
+    };
+
+    fn entry(op: ForeignInst, value: u64) -> ExternalHostCallEntry {
+        ExternalHostCallEntry {
+            op: op as usize,
+            value,
+            is_ret: false,
+        }
+    }
+
+    fn hash_round(restart: bool) -> Vec<ExternalHostCallEntry> {
+        let mut r = vec![entry(PoseidonNew, restart as u64)];
+        r.append(&mut vec![entry(PoseidonPush, 0); 8 * 4]);
+        r.append(&mut vec![entry(PoseidonFinalize, 0); 4]);
+        r
+    }
+
+    #[test]
+    fn test_split_on_fresh_rounds() {
+        let mut entries = vec![];
+        for _ in 0..2047 {
+            entries.append(&mut hash_round(true));
+            entries.push(entry(Log, 0));
+        }
+        // a hash of three rounds crosses the capacity of the first circuit
+        entries.append(&mut hash_round(true));
+        entries.append(&mut hash_round(false));
+        entries.append(&mut hash_round(false));
+        let table = ExternalHostCallEntryTable(entries);
+
+        let segments = split_host_call_table::<PoseidonChip<Fr, 9, 8>>(&table, 22).unwrap();
+        assert_eq!(segments.len(), 2);
+        let (first, s0) = &segments[0];
+        let (second, s1) = &segments[1];
+        assert_eq!(s0.start, 0);
+        assert_eq!(s0.end, 2047 * 37);
+        assert_eq!(s1.start, 2047 * 37);
+        assert_eq!(s1.end, 2050 * 37);
+        assert_eq!(s1.total, 2050 * 37);
+        assert_eq!(first.0.len(), 2047 * 37);
+        assert_eq!(second.0.len(), 3 * 37);
+        assert_eq!(second.0[0].value, 1);
+    }
+
+    #[test]
+    fn test_split_trace_positions() {
+        // entries of other opcodes before and between the hash rounds
+        let mut entries = vec![entry(Log, 0); 5];
+        for _ in 0..2049 {
+            entries.append(&mut hash_round(true));
+            entries.push(entry(Keccak256Push, 0));
+        }
+        let table = ExternalHostCallEntryTable(entries);
+
+        let segments = split_host_call_table::<PoseidonChip<Fr, 9, 8>>(&table, 22).unwrap();
+        assert_eq!(segments.len(), 2);
+        let (_, s0) = &segments[0];
+        let (second, s1) = &segments[1];
+        assert_eq!((s0.start, s0.end, s0.total), (0, 2048 * 37, 2049 * 37));
+        assert_eq!(
+            (s1.start, s1.end, s1.total),
+            (2048 * 37, 2049 * 37, 2049 * 37)
+        );
+        assert_eq!(s0.trace_start, 5);
+        assert_eq!(s0.trace_end, 5 + 2047 * 38 + 37);
+        assert_eq!(s1.trace_start, 5 + 2048 * 38);
+        assert_eq!(s1.trace_end, 5 + 2048 * 38 + 37);
+        assert_eq!(table.0[s1.trace_start].op, PoseidonNew as usize);
+        assert_eq!(table.0[s1.trace_end - 1].op, PoseidonFinalize as usize);
+        assert_eq!(second.0.len(), s1.trace_end - s1.trace_start);
+    }
+
+    #[test]
+    fn test_split_empty_trace() {
+        let table = ExternalHostCallEntryTable::default();
+        let segments = split_host_call_table::<PoseidonChip<Fr, 9, 8>>(&table, 22).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].1.total, 0);
+    }
+
+    #[test]
+    fn test_host_op_instances() {
+        let table = ExternalHostCallEntryTable(vec![
+            entry(PoseidonNew, 1),
+            entry(Log, 7),
+            entry(PoseidonPush, 3),
+        ]);
+        let challenge = Fr::from(5u64);
+        let instances = host_op_instances::<PoseidonChip<Fr, 9, 8>>(&table, challenge);
+        let shift = Fr::from_u128(1u128 << 64);
+        let first = Fr::from(PoseidonNew as u64) * shift + Fr::from(1u64);
+        let second = Fr::from(PoseidonPush as u64) * shift + Fr::from(3u64);
+        assert_eq!(instances[0], challenge);
+        assert_eq!(instances[1], first + second * challenge);
+        assert_eq!(instances[2], Fr::from(2u64));
+    }
+
+    #[test]
+    fn test_committed_host_circuit() {
+        type Selector = TestSelector<PoseidonChip<Fr, 9, 8>, 3>;
+        let mut entries = hash_round(true);
+        entries.push(entry(Log, 7));
+        entries.append(&mut hash_round(false));
+        let table = ExternalHostCallEntryTable(entries);
+        let challenge = Fr::from(5u64);
+        let circuit = build_committed_host_circuit::<Selector>(&table, 18, (), challenge);
+        let instances = host_op_instances::<Selector>(&table, challenge);
+        assert_eq!(instances[2], Fr::from(2 * 37u64));
+
+        let prover = MockProver::run(18, &circuit, vec![instances.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // a commitment, count or challenge other than the filtered entries fails
+        for row in 0..3 {
+            let mut wrong = instances.clone();
+            wrong[row] += Fr::one();
+            let prover = MockProver::run(18, &circuit, vec![wrong]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_host_commit_columns() {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        let config = HostOpCircuit::<Fr, PoseidonChip<Fr, 9, 8>, true>::configure(&mut cs);
+        let (commitconfig, _) = config.commitconfig.unwrap();
+        let columns = [
+            HostCommitConfig::acc(),
+            HostCommitConfig::challenge(),
+            HostCommitConfig::count(),
+        ]
+        .map(|cell| commitconfig.get_advice_column(cell).index());
+        assert_eq!(columns, HOST_COMMIT_COLUMNS);
+    }
+
+    /// An assignment which only records the copy constraints.
+    #[derive(Default)]
+    struct CopyRecorder(Vec<((Column<Any>, usize), (Column<Any>, usize))>);
+
+    impl Assignment<Fr> for CopyRecorder {
+        fn enter_region<NR, N>(&mut self, _name_fn: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+        }
+
+        fn exit_region(&mut self) {}
+
+        fn enable_selector<A, AR>(
+            &mut self,
+            _annotation: A,
+            _selector: &Selector,
+            _row: usize,
+        ) -> Result<(), Error>
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            Ok(())
+        }
+
+        fn query_instance(
+            &self,
+            _column: Column<Instance>,
+            _row: usize,
+        ) -> Result<Option<Fr>, Error> {
+            Ok(Some(Fr::zero()))
+        }
+
+        fn assign_advice<V, VR, A, AR>(
+            &mut self,
+            _annotation: A,
+            _column: Column<Advice>,
+            _row: usize,
+            _to: V,
+        ) -> Result<(), Error>
+        where
+            V: FnOnce() -> Result<VR, Error>,
+            VR: Into<Assigned<Fr>>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            Ok(())
+        }
+
+        fn assign_fixed<V, VR, A, AR>(
+            &mut self,
+            _annotation: A,
+            _column: Column<Fixed>,
+            _row: usize,
+            _to: V,
+        ) -> Result<(), Error>
+        where
+            V: FnOnce() -> Result<VR, Error>,
+            VR: Into<Assigned<Fr>>,
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            Ok(())
+        }
+
+        fn copy(
+            &mut self,
+            left_column: Column<Any>,
+            left_row: usize,
+            right_column: Column<Any>,
+            right_row: usize,
+        ) -> Result<(), Error> {
+            self.0
+                .push(((left_column, left_row), (right_column, right_row)));
+            Ok(())
+        }
+
+        fn fill_from_row(
+            &mut self,
+            _column: Column<Fixed>,
+            _row: usize,
+            _to: Option<Assigned<Fr>>,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn push_namespace<NR, N>(&mut self, _name_fn: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+        }
+
+        fn pop_namespace(&mut self, _gadget_name: Option<String>) {}
+    }
+
+    #[test]
+    fn test_instances_bound_to_commit_columns() {
+        type Selector = TestSelector<PoseidonChip<Fr, 9, 8>, 3>;
+        let table = ExternalHostCallEntryTable(hash_round(true));
+        let circuit = build_committed_host_circuit::<Selector>(&table, 18, (), Fr::from(5u64));
+        let mut cs = ConstraintSystem::default();
+        let config = HostOpCircuit::<Fr, Selector, true>::configure(&mut cs);
+        let (_, instance) = config.commitconfig.clone().unwrap();
+        let mut recorder = CopyRecorder::default();
+        FlatFloorPlanner::synthesize(&mut recorder, &circuit, config, cs.constants().clone())
+            .unwrap();
+
+        // the instances [challenge, commitment, count] are copied from the
+        // first row of the challenge, acc and count columns
+        let columns = [
+            HOST_COMMIT_COLUMNS[1],
+            HOST_COMMIT_COLUMNS[0],
+            HOST_COMMIT_COLUMNS[2],
+        ];
+        for (row, column) in columns.iter().enumerate() {
+            let bound = recorder.0.iter().any(|(left, right)| {
+                *right == (Column::<Any>::from(instance), row)
+                    && left.1 == 0
+                    && *left.0.column_type() == Any::Advice
+                    && left.0.index() == *column
+            });
+            assert!(bound, "instance {} is not bound to column {}", row, column);
+        }
+    }
+
+    #[test]
+    fn test_host_proof_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().to_path_buf();
+        let manifest = |name: &str, proofs, committed| HostProofManifest {
+            circuit_name: name.to_string(),
+            proofs,
+            committed,
+        };
+        let manifests = vec![
+            manifest("a.POSEIDONHASH.commit", 2, true),
+            manifest("b.MERKLE", 1, false),
+            manifest("c.KECCAKHASH.commit", 1, true),
+        ];
+        for m in manifests.iter() {
+            m.save(&folder).unwrap();
+            assert_eq!(
+                &HostProofManifest::load(&folder, &m.circuit_name).unwrap(),
+                m
+            );
+        }
+        assert_eq!(
+            exposed_commit_columns(&manifests),
+            vec![
+                [0, 0],
+                [0, 1],
+                [0, 2],
+                [1, 0],
+                [1, 1],
+                [1, 2],
+                [3, 0],
+                [3, 1],
+                [3, 2]
+            ]
+        );
+        // the flag of the manifest decides, not the name of the circuit
+        assert!(exposed_commit_columns(&[manifest("d.commit", 1, false)]).is_empty());
+    }
+
+    #[test]
+    fn test_merkle_witness_of_unsupported_op() {
+        let output = tempfile::tempdir().unwrap();
+        let params = tempfile::tempdir().unwrap();
+        for opname in [
+            OpType::KECCAKMERKLE,
+            OpType::MERKLEWIDE,
+            OpType::POSEIDONHASH,
+        ] {
+            let result = exec_create_host_proof(
+                "host",
+                22,
+                &ExternalHostCallEntryTable::default(),
+                opname,
+                &output.path().to_path_buf(),
+                &params.path().to_path_buf(),
+                None,
+                None,
+                Some(MerkleWitness::default()),
+            );
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_u64_operands() {
+        let table = ExternalHostCallEntryTable(hash_round(true));
+        let wide = Fr::from_u128(1u128 << 64) + Fr::one();
+
+        let mut circuit =
+            build_host_circuit::<TestSelector<PoseidonChip<Fr, 9, 8>, 1>>(&table, 18, ());
+        circuit.shared_operands[0] = wide;
+        let prover = MockProver::run(18, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // a selector that opts out accepts the same operand
+        let mut circuit =
+            build_host_circuit::<TestSelector<PoseidonChip<Fr, 9, 8>, 1, false>>(&table, 18, ());
+        circuit.shared_operands[0] = wide;
+        let prover = MockProver::run(18, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    /// proves two committed poseidon circuits at k = 22 and aggregates them
+    #[test]
+    #[ignore]
+    fn test_aggregate_committed_proofs() {
+        let output = tempfile::tempdir().unwrap();
+        let params = tempfile::tempdir().unwrap();
+        let output = output.path().to_path_buf();
+        let params = params.path().to_path_buf();
+        let mut manifests = vec![];
+        for (i, name) in ["first", "second"].iter().enumerate() {
+            let inputs = [Fr::from(i as u64); 8];
+            let result = crate::host::poseidon::POSEIDON_HASHER
+                .clone()
+                .update_exact(&inputs);
+            let table = hash_to_host_call_table(9, &inputs, result);
+            exec_create_host_proof(
+                name,
+                22,
+                &table,
+                OpType::POSEIDONHASH,
+                &output,
+                &params,
+                Some(Fr::from(5u64 + i as u64)),
+                None,
+                None,
+            )
+            .unwrap();
+            manifests.push(output.join(format!("{}.POSEIDONHASH.commit.loadinfo.json", name)));
+        }
+        exec_aggregate_host_proofs("aggregated", 22, &manifests, &output, &params);
+        assert!(output.join("aggregated.loadinfo.json").exists());
+    }
 }
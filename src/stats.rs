@@ -0,0 +1,405 @@
+use crate::adaptor::combinedadaptor::CombinedHelper;
+use crate::adaptor::merkleadaptor::MerkleHelper;
+use crate::circuits::babyjub::{AltJubBaseChip, AltJubChip};
+use crate::circuits::{
+    bn256::Bn256MultiPairChip, bn256::Bn256PairChip, bn256::Bn256SumChip, combined::CombinedChip,
+    host::HostOpChip, host::HostOpSelector, jubjubverify::JubjubVerifyChip, keccak256::KeccakChip,
+    keccakmerkle::KeccakMerkleChip, merkle::MerkleChip, merklewide::MerkleWideChip,
+    poseidon::PoseidonChip,
+};
+use crate::host::db::{RocksDB, TreeDB};
+use crate::host::ExternalHostCallEntryTable;
+use crate::proof::{
+    build_host_circuit, HostCircuitConfig, HostOpCircuit, OpType, FILTER_REGION, MERKLE_DEPTH,
+    MERKLE_WIDE_LEAF_WORDS,
+};
+use clap::ArgEnum;
+use ff::Field;
+use halo2_proofs::circuit::{floor_planner::FlatFloorPlanner, FloorPlanner, Layouter};
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::{
+    Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed, Instance,
+    Selector,
+};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HostCircuitStats {
+    pub op: OpType,
+    pub k: usize,
+    /// rows of the circuit
+    pub total_rows: usize,
+    /// rows touched by synthesizing the circuit with every round padded
+    pub used_rows: usize,
+    /// max number of rounds the circuit can hold
+    pub capacity: usize,
+    /// selected entries consumed by one round, none for the combined circuit
+    /// whose rounds are of different sizes
+    pub chunk_size: Option<usize>,
+    /// advice rows of the selector for one padded round, none for the combined
+    /// circuit whose rounds are of different kinds
+    pub rows_per_round: Option<usize>,
+    /// advice rows of the selector that do not depend on the number of rounds
+    pub initial_rows: Option<usize>,
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub selectors: usize,
+    pub lookups: usize,
+    pub degree: usize,
+}
+
+/// An assignment which only tracks the rows that have been touched, and the
+/// span of the advice rows of each region other than the filter region.
+#[derive(Default)]
+struct RowCounter {
+    rows: usize,
+    in_filter: bool,
+    region: Option<(usize, usize)>,
+    selector_rows: usize,
+}
+
+impl RowCounter {
+    fn touch(&mut self, row: usize) {
+        self.rows = usize::max(self.rows, row + 1);
+    }
+
+    fn touch_advice(&mut self, row: usize) {
+        self.touch(row);
+        if !self.in_filter {
+            self.region = Some(self.region.map_or((row, row), |(start, end)| {
+                (usize::min(start, row), usize::max(end, row))
+            }));
+        }
+    }
+}
+
+impl Assignment<Fr> for RowCounter {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.in_filter = name_fn().into() == FILTER_REGION;
+        self.region = None;
+    }
+
+    fn exit_region(&mut self) {
+        if let Some((start, end)) = self.region.take() {
+            self.selector_rows += end - start + 1;
+        }
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _annotation: A,
+        _selector: &Selector,
+        row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, _column: Column<Instance>, _row: usize) -> Result<Option<Fr>, Error> {
+        Ok(Some(Fr::zero()))
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        _column: Column<Advice>,
+        row: usize,
+        _to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<Fr>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch_advice(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        _column: Column<Fixed>,
+        row: usize,
+        _to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<Fr>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _left_column: Column<Any>,
+        left_row: usize,
+        _right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.touch(left_row);
+        self.touch(right_row);
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _column: Column<Fixed>,
+        _row: usize,
+        _to: Option<Assigned<Fr>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _gadget_name: Option<String>) {}
+}
+
+/// The host circuit of `S` with an empty trace whose selector only synthesizes
+/// the first `rounds` padded rounds.
+struct RoundRowsCircuit<S: HostOpSelector> {
+    k: usize,
+    rounds: usize,
+    helper: S::Helper,
+}
+
+impl<S: HostOpSelector> Circuit<Fr> for RoundRowsCircuit<S> {
+    type Config = HostCircuitConfig<S::Config>;
+    type FloorPlanner = FlatFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        RoundRowsCircuit {
+            k: self.k,
+            rounds: self.rounds,
+            helper: self.helper.clone(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        HostOpCircuit::<Fr, S>::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        let host_op_chip =
+            HostOpChip::<Fr, S>::construct(config.hostconfig.clone(), config.selectconfig.clone());
+        let all_arg_cells = layouter.assign_region(
+            || FILTER_REGION,
+            |region| {
+                let mut offset = 0;
+                let (arg_cells, _) =
+                    host_op_chip.assign(&region, self.k, &mut offset, &vec![], &vec![])?;
+                Ok(arg_cells)
+            },
+        )?;
+        // every round of an empty trace is a padding round of the same layout
+        let round_args = all_arg_cells.len() / S::max_rounds(self.k);
+        let arg_cells = all_arg_cells[0..round_args * self.rounds].to_vec();
+        let mut selector_chip = layouter.assign_region(
+            || "selector rounds",
+            |region| {
+                let mut selector_chip = S::construct(config.selectconfig.clone());
+                selector_chip.synthesize(&mut 0, &arg_cells, &region, &self.helper)?;
+                Ok(selector_chip)
+            },
+        )?;
+        selector_chip.synthesize_separate(&arg_cells, &layouter)
+    }
+}
+
+/// Advice rows used by the selector of `S` to synthesize `rounds` padded rounds.
+fn selector_rows<S: HostOpSelector>(
+    k: usize,
+    rounds: usize,
+    helper: S::Helper,
+) -> Result<usize, Error> {
+    let circuit = RoundRowsCircuit::<S> { k, rounds, helper };
+    let mut cs = ConstraintSystem::default();
+    let config = RoundRowsCircuit::<S>::configure(&mut cs);
+    let mut counter = RowCounter::default();
+    FlatFloorPlanner::synthesize(&mut counter, &circuit, config, cs.constants().clone())?;
+    Ok(counter.selector_rows)
+}
+
+/// Configure the host circuit of `S` and synthesize it with an empty trace,
+/// so that every round is filled with padding and the row usage is maximal.
+/// The rows of one round are the difference between the selector rows of two
+/// padded rounds and one padded round.
+pub fn host_circuit_stats<S: HostOpSelector>(
+    op: OpType,
+    k: usize,
+    helper: S::Helper,
+    uniform_rounds: bool,
+) -> Result<HostCircuitStats, Error> {
+    let circuit =
+        build_host_circuit::<S>(&ExternalHostCallEntryTable::default(), k, helper.clone());
+    let mut cs = ConstraintSystem::default();
+    let config = HostOpCircuit::<Fr, S>::configure(&mut cs);
+    let mut counter = RowCounter::default();
+    FlatFloorPlanner::synthesize(&mut counter, &circuit, config, cs.constants().clone())?;
+
+    let capacity = S::max_rounds(k);
+    let (rows_per_round, initial_rows) = if uniform_rounds && capacity > 0 {
+        let one = selector_rows::<S>(k, 1, helper.clone())?;
+        if capacity > 1 {
+            let two = selector_rows::<S>(k, 2, helper)?;
+            (Some(two - one), Some((2 * one).saturating_sub(two)))
+        } else {
+            (Some(one), None)
+        }
+    } else {
+        (None, None)
+    };
+    Ok(HostCircuitStats {
+        op,
+        k,
+        total_rows: 1 << k,
+        used_rows: counter.rows,
+        capacity,
+        chunk_size: uniform_rounds.then(S::chunk_size),
+        rows_per_round,
+        initial_rows,
+        advice_columns: cs.num_advice_columns(),
+        fixed_columns: cs.num_fixed_columns(),
+        instance_columns: cs.num_instance_columns(),
+        selectors: cs.num_selectors(),
+        lookups: cs.lookups().len(),
+        degree: cs.degree(),
+    })
+}
+
+pub fn exec_host_circuit_stats(op: OpType, k: usize) -> Result<HostCircuitStats, Error> {
+    // the padding rounds of the merkle ops only read default nodes, which an
+    // empty rocksdb provides without reaching the local mongodb
+    let dir = tempfile::tempdir().expect("can not create the stats tree db folder");
+    let db: Rc<RefCell<dyn TreeDB>> = Rc::new(RefCell::new(
+        RocksDB::new(dir.path()).expect("can not open the stats tree db"),
+    ));
+    match op {
+        OpType::BN256PAIR => host_circuit_stats::<Bn256PairChip<Fr>>(op, k, (), true),
+        OpType::BN256MULTIPAIR => host_circuit_stats::<Bn256MultiPairChip<Fr>>(op, k, (), true),
+        OpType::BN256SUM => host_circuit_stats::<Bn256SumChip<Fr>>(op, k, (), true),
+        OpType::POSEIDONHASH => host_circuit_stats::<PoseidonChip<Fr, 9, 8>>(op, k, (), true),
+        OpType::POSEIDONT3HASH => host_circuit_stats::<PoseidonChip<Fr, 3, 2>>(op, k, (), true),
+        OpType::POSEIDONT5HASH => host_circuit_stats::<PoseidonChip<Fr, 5, 4>>(op, k, (), true),
+        OpType::MERKLE => host_circuit_stats::<MerkleChip<Fr, MERKLE_DEPTH>>(
+            op,
+            k,
+            MerkleHelper::TreeDB(Some(db)),
+            true,
+        ),
+        OpType::KECCAKMERKLE => {
+            host_circuit_stats::<KeccakMerkleChip<Fr, MERKLE_DEPTH>>(op, k, Some(db), true)
+        }
+        OpType::MERKLEWIDE => host_circuit_stats::<
+            MerkleWideChip<Fr, MERKLE_DEPTH, MERKLE_WIDE_LEAF_WORDS>,
+        >(op, k, Some(db), true),
+        OpType::JUBJUBSUM => host_circuit_stats::<AltJubChip<Fr>>(op, k, (), true),
+        OpType::JUBJUBBASESUM => host_circuit_stats::<AltJubBaseChip>(op, k, (), true),
+        OpType::JUBJUBVERIFY => host_circuit_stats::<JubjubVerifyChip<Fr>>(op, k, (), true),
+        OpType::KECCAKHASH => host_circuit_stats::<KeccakChip<Fr>>(op, k, (), true),
+        OpType::COMBINED => host_circuit_stats::<CombinedChip<MERKLE_DEPTH>>(
+            op,
+            k,
+            CombinedHelper::new(k, MerkleHelper::TreeDB(Some(db))),
+            false,
+        ),
+    }
+}
+
+/// Collect the statistics of every host op type at circuit size `k`.
+pub fn exec_all_host_circuit_stats(k: usize) -> Result<Vec<HostCircuitStats>, Error> {
+    OpType::value_variants()
+        .iter()
+        .map(|op| exec_host_circuit_stats(op.clone(), k))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exec_host_circuit_stats, HostCircuitStats};
+    use crate::proof::OpType;
+
+    /// The stats of op at k = 22 after checking that its capacity of padded
+    /// rounds fits into the circuit.
+    fn capacity_stats(op: OpType) -> HostCircuitStats {
+        let stats = exec_host_circuit_stats(op, 22).unwrap();
+        let rows_per_round = stats.rows_per_round.unwrap();
+        assert!(rows_per_round > 0);
+        assert!(stats.initial_rows.unwrap() + rows_per_round * stats.capacity < stats.total_rows);
+        assert!(stats.used_rows <= stats.total_rows);
+        stats
+    }
+
+    #[test]
+    fn test_poseidon_stats() {
+        let stats = exec_host_circuit_stats(OpType::POSEIDONT3HASH, 22).unwrap();
+        assert_eq!(stats.capacity, 16384);
+        let rows_per_round = stats.rows_per_round.unwrap();
+        assert!(rows_per_round > 0);
+        assert!(stats.initial_rows.is_some());
+        // the capacity of the rounds fits into the circuit
+        assert!(stats.initial_rows.unwrap() + rows_per_round * stats.capacity < stats.total_rows);
+    }
+
+    #[test]
+    fn test_poseidon_t5_stats() {
+        let stats = capacity_stats(OpType::POSEIDONT5HASH);
+        assert_eq!(stats.capacity, 3584);
+    }
+
+    #[test]
+    fn test_poseidon_hash_stats() {
+        let stats = capacity_stats(OpType::POSEIDONHASH);
+        assert_eq!(stats.capacity, 2048);
+        assert_eq!(stats.chunk_size, Some(37));
+    }
+
+    #[test]
+    fn test_merkle_wide_stats() {
+        let stats = capacity_stats(OpType::MERKLEWIDE);
+        assert_eq!(stats.capacity, 640);
+    }
+
+    #[test]
+    fn test_jubjub_base_sum_stats() {
+        let stats = capacity_stats(OpType::JUBJUBBASESUM);
+        assert_eq!(stats.capacity, 1700);
+    }
+
+    #[test]
+    fn test_bn256_pair_stats() {
+        // k = 23 holds two rounds, which separates the rows of one pairing
+        // from the rows shared by all the rounds
+        let stats = exec_host_circuit_stats(OpType::BN256PAIR, 23).unwrap();
+        assert_eq!(stats.capacity, 2);
+        let rows_per_round = stats.rows_per_round.unwrap();
+        let initial_rows = stats.initial_rows.unwrap();
+        println!(
+            "bn256 pairing: {} rows per round, {} initial rows",
+            rows_per_round, initial_rows
+        );
+        // one pairing fits into 2^22 rows and two pairings do not
+        assert!(initial_rows + rows_per_round < 1 << 22);
+        assert!(initial_rows + 2 * rows_per_round >= 1 << 22);
+    }
+}